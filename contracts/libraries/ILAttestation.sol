@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.26;
+
+/**
+ * @title ILAttestation
+ * @notice EIP-712 typed attestations of impermanent loss computed off-chain
+ * @dev Mirrors eigenlayer-compute/src/eip712.rs: operators sign the typed-data digest of an
+ *      Attestation under a domain bound to the chain and to the verifying contract
+ *      (EigenAVSManager or the ConfidentialInsurance receiver), and `verify` recovers the signer
+ */
+library ILAttestation {
+    // =============================================================================
+    //                               CUSTOM ERRORS
+    // =============================================================================
+
+    error InvalidSignature();
+    error AttestationExpired();
+    error ChainMismatch();
+
+    // =============================================================================
+    //                               TYPES
+    // =============================================================================
+
+    struct Attestation {
+        uint256 policyId;
+        uint256 impermanentLoss;
+        uint256 payout;
+        bytes32 pricesHash;
+        uint256 nonce;
+        uint256 chainId;
+        uint256 deadline;
+        bytes32 resultHash;
+    }
+
+    // =============================================================================
+    //                               CONSTANTS
+    // =============================================================================
+
+    bytes32 internal constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+
+    bytes32 internal constant IL_ATTESTATION_TYPEHASH = keccak256(
+        "ILAttestation(uint256 policyId,uint256 impermanentLoss,uint256 payout,bytes32 pricesHash,"
+        "uint256 nonce,uint256 chainId,uint256 deadline,bytes32 resultHash)"
+    );
+
+    bytes32 internal constant NAME_HASH = keccak256("ConfidentialInsurance");
+    bytes32 internal constant VERSION_HASH = keccak256("1");
+
+    /// @dev secp256k1n / 2; larger s values are malleable (EIP-2)
+    uint256 internal constant HALF_ORDER = 0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0;
+
+    // =============================================================================
+    //                               HASHING
+    // =============================================================================
+
+    /**
+     * @notice Domain separator for signatures verified by `verifyingContract` on this chain
+     * @param verifyingContract Contract that verifies the attestations
+     * @return The EIP-712 domain separator
+     */
+    function domainSeparator(address verifyingContract) internal view returns (bytes32) {
+        return keccak256(abi.encode(DOMAIN_TYPEHASH, NAME_HASH, VERSION_HASH, block.chainid, verifyingContract));
+    }
+
+    /**
+     * @notice EIP-712 struct hash of an attestation
+     */
+    function hashStruct(Attestation memory attestation) internal pure returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                IL_ATTESTATION_TYPEHASH,
+                attestation.policyId,
+                attestation.impermanentLoss,
+                attestation.payout,
+                attestation.pricesHash,
+                attestation.nonce,
+                attestation.chainId,
+                attestation.deadline,
+                attestation.resultHash
+            )
+        );
+    }
+
+    /**
+     * @notice The digest operators sign
+     * @dev keccak256("\x19\x01" || domainSeparator || hashStruct(attestation))
+     */
+    function hashTypedData(Attestation memory attestation, address verifyingContract)
+        internal
+        view
+        returns (bytes32)
+    {
+        return keccak256(abi.encodePacked("\x19\x01", domainSeparator(verifyingContract), hashStruct(attestation)));
+    }
+
+    /**
+     * @notice Hash of the price path an attestation was computed from
+     * @return keccak256(abi.encode(priceData, timestamps))
+     */
+    function pricesHash(uint256[] memory priceData, uint256[] memory timestamps) internal pure returns (bytes32) {
+        return keccak256(abi.encode(priceData, timestamps));
+    }
+
+    // =============================================================================
+    //                               VERIFICATION
+    // =============================================================================
+
+    /**
+     * @notice Recover the signer of a 65-byte `r || s || v` signature
+     * @dev Accepts v of 27/28 or 0/1 and rejects high-s signatures
+     */
+    function recover(bytes32 digest, bytes memory signature) internal pure returns (address signer) {
+        if (signature.length != 65) revert InvalidSignature();
+        bytes32 r;
+        bytes32 s;
+        uint8 v;
+        assembly {
+            r := mload(add(signature, 0x20))
+            s := mload(add(signature, 0x40))
+            v := byte(0, mload(add(signature, 0x60)))
+        }
+        if (v < 27) v += 27;
+        if (v != 27 && v != 28) revert InvalidSignature();
+        if (uint256(s) > HALF_ORDER) revert InvalidSignature();
+
+        signer = ecrecover(digest, v, r, s);
+        if (signer == address(0)) revert InvalidSignature();
+    }
+
+    /**
+     * @notice Verify an attestation signed for `verifyingContract` on this chain
+     * @param attestation The attested values
+     * @param signature The operator's signature of the typed-data digest
+     * @param verifyingContract Contract the domain is bound to, usually address(this)
+     * @return signer The operator that signed the attestation
+     */
+    function verify(Attestation memory attestation, bytes memory signature, address verifyingContract)
+        internal
+        view
+        returns (address signer)
+    {
+        if (attestation.chainId != block.chainid) revert ChainMismatch();
+        if (block.timestamp > attestation.deadline) revert AttestationExpired();
+        return recover(hashTypedData(attestation, verifyingContract), signature);
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.26;
+
+/**
+ * @title PositionCommitment
+ * @notice Commitment to an insured position's entry data, stored as PolicyManager's `entryCommit`
+ * @dev Mirrors AttestationRequest::entry_commitment in eigenlayer-compute/src/request.rs. Operators
+ *      reject attestation requests whose position data and salt do not open the policy's commitment,
+ *      so entry amounts cannot be inflated at claim time
+ */
+library PositionCommitment {
+    // =============================================================================
+    //                               TYPES
+    // =============================================================================
+
+    /// @dev Pool kinds: 0 constant product, 1 weighted (weightABps, 0), 2 StableSwap (amplification, 0),
+    ///      3 concentrated (priceLower, priceUpper)
+    struct Position {
+        address tokenA;
+        address tokenB;
+        uint256 initialTokenAAmount; // token A's native decimals
+        uint256 initialTokenBAmount; // token B's native decimals
+        uint256 initialTokenAPrice; // priceDecimals
+        uint256 initialTokenBPrice; // priceDecimals
+        uint8 priceDecimals;
+        uint8 poolKind;
+        uint256 poolParam0;
+        uint256 poolParam1;
+        uint256 liquidity; // zero without fee-growth snapshots
+        uint256 feeGrowthInside0EntryX128;
+        uint256 feeGrowthInside1EntryX128;
+    }
+
+    // =============================================================================
+    //                               HASHING
+    // =============================================================================
+
+    /**
+     * @notice Commitment to `position`, hidden by `salt`
+     * @param position The position as it was entered
+     * @param salt Random value the LP keeps to open the commitment
+     * @return keccak256 of the ABI-encoded position fields followed by the salt
+     */
+    function commit(Position memory position, bytes32 salt) internal pure returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                position.tokenA,
+                position.tokenB,
+                position.initialTokenAAmount,
+                position.initialTokenBAmount,
+                position.initialTokenAPrice,
+                position.initialTokenBPrice,
+                position.priceDecimals,
+                position.poolKind,
+                position.poolParam0,
+                position.poolParam1,
+                position.liquidity,
+                position.feeGrowthInside0EntryX128,
+                position.feeGrowthInside1EntryX128,
+                salt
+            )
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Keccak-256 digests and Solidity ABI encoding.
+//!
+//! [`encode`] and [`encode_packed`] follow `abi.encode` and
+//! `abi.encodePacked`, so a digest computed here equals the one a contract
+//! computes over the same values.
+
+use crate::u256::U256;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// 32-byte hash, serialized as a `0x`-prefixed hex string like Solidity's
+/// `bytes32`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct B256(pub [u8; 32]);
+
+impl B256 {
+    pub const ZERO: B256 = B256([0; 32]);
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    /// The hash as a big-endian integer, as `uint256(bytes32)` converts it.
+    pub fn to_u256(&self) -> U256 {
+        U256::from_be_bytes(self.0)
+    }
+}
+
+impl From<U256> for B256 {
+    fn from(value: U256) -> Self {
+        B256(value.to_be_bytes())
+    }
+}
+
+impl AsRef<[u8]> for B256 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for B256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl Serialize for B256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for B256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        from_hex(&text)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(B256)
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&text), &"0x-prefixed 32-byte hex"))
+    }
+}
+
+/// 20-byte account address, serialized as `0x`-prefixed hex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub const ZERO: Address = Address([0; 20]);
+
+    /// The low 20 bytes of a hash, as Ethereum derives account addresses.
+    pub fn from_word(word: B256) -> Self {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&word.0[12..]);
+        Address(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        from_hex(&text)
+            .and_then(|bytes| <[u8; 20]>::try_from(bytes).ok())
+            .map(Address)
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&text), &"0x-prefixed 20-byte hex"))
+    }
+}
+
+/// A Solidity value to be ABI encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// `uintN`, with `N` the bit width (a multiple of 8 up to 256)
+    Uint(U256, usize),
+    Address(Address),
+    Bool(bool),
+    /// `bytesN`, holding the first `N` bytes
+    FixedBytes(B256, usize),
+    Bytes(Vec<u8>),
+    String(String),
+    /// `T[]`, with every element of the same type
+    Array(Vec<Token>),
+}
+
+impl Token {
+    /// `uint256`
+    pub fn uint(value: U256) -> Self {
+        Token::Uint(value, 256)
+    }
+
+    /// `bytes32`
+    pub fn bytes32(value: B256) -> Self {
+        Token::FixedBytes(value, 32)
+    }
+
+    /// `uint256[]`
+    pub fn uints(values: &[U256]) -> Self {
+        Token::Array(values.iter().copied().map(Token::uint).collect())
+    }
+
+    fn is_dynamic(&self) -> bool {
+        matches!(self, Token::Bytes(_) | Token::String(_) | Token::Array(_))
+    }
+
+    // The 32-byte head word of a static value
+    fn word(&self) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        match self {
+            Token::Uint(value, _) => word = value.to_be_bytes(),
+            Token::Address(address) => word[12..].copy_from_slice(&address.0),
+            Token::Bool(value) => word[31] = *value as u8,
+            Token::FixedBytes(value, size) => word[..*size].copy_from_slice(&value.0[..*size]),
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => unreachable!("dynamic values have no static word"),
+        }
+        word
+    }
+
+    // The length-prefixed tail of a dynamic value
+    fn tail(&self) -> Vec<u8> {
+        let (length, mut body) = match self {
+            Token::Bytes(bytes) => (bytes.len(), bytes.clone()),
+            Token::String(text) => (text.len(), text.as_bytes().to_vec()),
+            Token::Array(items) => (items.len(), encode(items)),
+            _ => unreachable!("static values have no tail"),
+        };
+        body.resize(body.len().div_ceil(32) * 32, 0);
+        let mut tail = U256::from(length as u64).to_be_bytes().to_vec();
+        tail.extend(body);
+        tail
+    }
+}
+
+/// `abi.encode(tokens...)`: 32-byte heads, with dynamic values appended as
+/// length-prefixed, zero-padded tails.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut head = Vec::with_capacity(tokens.len() * 32);
+    let mut tail = Vec::new();
+    for token in tokens {
+        if token.is_dynamic() {
+            let offset = U256::from((tokens.len() * 32 + tail.len()) as u64);
+            head.extend_from_slice(&offset.to_be_bytes());
+            tail.extend(token.tail());
+        } else {
+            head.extend_from_slice(&token.word());
+        }
+    }
+    head.extend_from_slice(&tail);
+    head
+}
+
+/// `abi.encodePacked(tokens...)`: every value at its natural width with no
+/// padding or length prefix, except array elements, which are padded to 32
+/// bytes.
+pub fn encode_packed(tokens: &[Token]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Uint(_, bits) => packed.extend_from_slice(&token.word()[32 - bits / 8..]),
+            Token::Address(address) => packed.extend_from_slice(&address.0),
+            Token::Bool(value) => packed.push(*value as u8),
+            Token::FixedBytes(value, size) => packed.extend_from_slice(&value.0[..*size]),
+            Token::Bytes(bytes) => packed.extend_from_slice(bytes),
+            Token::String(text) => packed.extend_from_slice(text.as_bytes()),
+            Token::Array(items) => {
+                for item in items {
+                    if item.is_dynamic() {
+                        packed.extend(encode_packed(std::slice::from_ref(item)));
+                    } else {
+                        packed.extend_from_slice(&item.word());
+                    }
+                }
+            }
+        }
+    }
+    packed
+}
+
+pub fn keccak256(data: impl AsRef<[u8]>) -> B256 {
+    use sha3::{Digest, Keccak256};
+    B256(Keccak256::digest(data.as_ref()).into())
+}
+
+/// `keccak256(abi.encode(tokens...))`
+pub fn keccak256_encode(tokens: &[Token]) -> B256 {
+    keccak256(encode(tokens))
+}
+
+/// First four bytes of the hash of a function or error signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature);
+    [hash.0[0], hash.0[1], hash.0[2], hash.0[3]]
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(2 + bytes.len() * 2);
+    text.push_str("0x");
+    for byte in bytes {
+        text.push_str(&format!("{:02x}", byte));
+    }
+    text
+}
+
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.strip_prefix("0x")?;
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        from_hex(text).unwrap()
+    }
+
+    #[test]
+    fn keccak_matches_known_digests() {
+        assert_eq!(
+            keccak256(b"").to_string(),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            keccak256("abc").to_string(),
+            "0x4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(selector("transfer(address,uint256)"), [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(selector("Panic(uint256)"), [0x4e, 0x48, 0x7b, 0x71]);
+    }
+
+    #[test]
+    fn encode_pads_static_values_and_appends_tails() {
+        let encoded = encode(&[
+            Token::uint(U256::from(1)),
+            Token::Bytes(vec![0xaa, 0xbb]),
+            Token::Bool(true),
+        ]);
+        assert_eq!(
+            encoded,
+            hex(concat!(
+                "0x",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000060",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "aabb000000000000000000000000000000000000000000000000000000000000",
+            ))
+        );
+    }
+
+    #[test]
+    fn encode_appends_array_tails() {
+        let values = [U256::from(1), U256::from(2)];
+        let encoded = encode(&[Token::uints(&values), Token::uints(&[])]);
+        assert_eq!(
+            encoded,
+            hex(concat!(
+                "0x",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "00000000000000000000000000000000000000000000000000000000000000a0",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+            ))
+        );
+        assert_eq!(encode_packed(&[Token::uints(&values)]), encoded[96..160].to_vec());
+    }
+
+    #[test]
+    fn encode_packed_uses_natural_widths() {
+        let mut address = [0u8; 20];
+        address[19] = 0x11;
+        let packed = encode_packed(&[
+            Token::Uint(U256::from(0x0102), 16),
+            Token::Address(Address(address)),
+            Token::Bool(false),
+            Token::String("ab".to_string()),
+            Token::FixedBytes(B256::from(U256::MAX), 4),
+        ]);
+        assert_eq!(
+            packed,
+            hex("0x01020000000000000000000000000000000000000011006162ffffffff")
+        );
+    }
+
+    #[test]
+    fn b256_and_address_serde_is_hex() {
+        let hash = keccak256(b"abc");
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json.len(), 2 + 2 + 64);
+        assert_eq!(serde_json::from_str::<B256>(&json).unwrap(), hash);
+        assert!(serde_json::from_str::<B256>(r#""0x1234""#).is_err());
+        assert_eq!(B256::from(hash.to_u256()), hash);
+
+        let address = Address::from_word(hash);
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"0x{}\"", &hash.to_string()[26..]));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+    }
+}
@@ -0,0 +1,346 @@
+//! Consensus over the attestation values of verified signers.
+//!
+//! The signature schemes decide which operators count; a [`Strategy`]
+//! decides what value they agree on. Every signer whose value is further
+//! from the consensus than the policy's tolerance is reported as a
+//! [`Deviation`] so it can be flagged for slashing.
+
+use crate::abi::{keccak256_encode, Token, B256};
+use crate::error::ComputeError;
+use crate::ilmath::{BASIS_POINTS, MAX_BASIS_POINTS};
+use crate::u256::U256;
+use serde::{Deserialize, Serialize};
+
+/// How the signers' values are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Strategy {
+    /// Arithmetic mean, the spec method's behaviour
+    #[default]
+    Mean,
+    /// Middle value, or the midpoint of the two middle values
+    Median,
+    /// Mean after dropping `trim_bps` of the signers from each end
+    TrimmedMean { trim_bps: u16 },
+    /// Stake-weighted mean, requiring signers holding `quorum_bps` of the
+    /// operators' total stake. Each signer's share rounds down.
+    StakeWeighted { quorum_bps: u16 },
+    /// The value whose `keccak256(abi.encode(value))` more than half of the
+    /// signers attested to
+    Majority,
+}
+
+/// Strategy, stakes and deviation tolerance of an aggregation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationPolicy {
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// Stake of each operator, indexed like the operator keys or set.
+    /// Required by [`Strategy::StakeWeighted`].
+    #[serde(default)]
+    pub stakes: Vec<U256>,
+    /// Largest distance from the consensus, in basis points of it, that is
+    /// not reported as a deviation
+    #[serde(default)]
+    pub deviation_tolerance_bps: u16,
+}
+
+/// A verified signer's attestation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vote {
+    /// Index of the attestation
+    pub index: usize,
+    /// Index of the operator, and of its stake
+    pub operator: usize,
+    pub value: U256,
+}
+
+/// A signer whose value is outside the tolerance around the consensus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deviation {
+    pub index: usize,
+    pub value: U256,
+    /// Distance from the consensus in basis points of it, `U256::MAX` when
+    /// the consensus is zero
+    pub deviation_bps: U256,
+}
+
+/// Agreed value of a set of votes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consensus {
+    /// Zero when the threshold or quorum is not met
+    pub value: U256,
+    pub meets_threshold: bool,
+    pub deviations: Vec<Deviation>,
+}
+
+impl AggregationPolicy {
+    /// Checks the policy against the number of operators before any
+    /// signature is verified.
+    pub fn validate(&self, operator_count: usize) -> Result<(), ComputeError> {
+        let stakes_required = matches!(self.strategy, Strategy::StakeWeighted { .. });
+        if (stakes_required || !self.stakes.is_empty()) && self.stakes.len() != operator_count {
+            return Err(ComputeError::LengthMismatch {
+                parameter: "stakes",
+                expected: operator_count,
+                actual: self.stakes.len(),
+            });
+        }
+        match self.strategy {
+            Strategy::TrimmedMean { trim_bps } if trim_bps >= MAX_BASIS_POINTS / 2 => Err(ComputeError::OutOfRange {
+                parameter: "trim_bps",
+                index: None,
+            }),
+            Strategy::StakeWeighted { quorum_bps } if quorum_bps == 0 || quorum_bps > MAX_BASIS_POINTS => {
+                Err(ComputeError::OutOfRange {
+                    parameter: "quorum_bps",
+                    index: None,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Combines the votes under the strategy. The signer count must reach
+    /// `threshold` whatever the strategy; stake-weighted and majority
+    /// strategies add their own quorum on top.
+    pub fn aggregate(&self, votes: &[Vote], threshold: U256) -> Result<Consensus, ComputeError> {
+        let signers = U256::from(votes.len() as u64);
+        if votes.is_empty() || signers < threshold {
+            return Ok(Consensus::default());
+        }
+
+        let value = match self.strategy {
+            Strategy::Mean => Some(mean(votes)?),
+            Strategy::Median => Some(median(votes)),
+            Strategy::TrimmedMean { trim_bps } => {
+                let mut sorted = votes.to_vec();
+                sorted.sort_by_key(|vote| vote.value);
+                let trimmed = votes.len() * trim_bps as usize / BASIS_POINTS as usize;
+                Some(mean(&sorted[trimmed..votes.len() - trimmed])?)
+            }
+            Strategy::StakeWeighted { quorum_bps } => self.stake_weighted_mean(votes, quorum_bps)?,
+            Strategy::Majority => majority(votes, threshold),
+        };
+
+        let Some(value) = value else {
+            return Ok(Consensus::default());
+        };
+        Ok(Consensus {
+            value,
+            meets_threshold: true,
+            deviations: deviations(votes, value, self.deviation_tolerance_bps),
+        })
+    }
+
+    // None below the stake quorum
+    fn stake_weighted_mean(&self, votes: &[Vote], quorum_bps: u16) -> Result<Option<U256>, ComputeError> {
+        let total_stake = sum(self.stakes.iter().copied(), "stakes")?;
+        let signed_stake = sum(votes.iter().map(|vote| self.stakes[vote.operator]), "stakes")?;
+        let quorum = total_stake
+            .mul_div_rounding_up(U256::from(quorum_bps as u64), U256::from(BASIS_POINTS))
+            .ok_or(ComputeError::ArithmeticOverflow {
+                parameter: "stakes",
+                index: None,
+            })?;
+        if signed_stake.is_zero() || signed_stake < quorum {
+            return Ok(None);
+        }
+
+        let mut value = U256::ZERO;
+        for vote in votes {
+            // Each share is at most the vote's value, so only the sum can overflow
+            let share = vote
+                .value
+                .mul_div(self.stakes[vote.operator], signed_stake)
+                .unwrap_or(U256::MAX);
+            value = value.checked_add(share).ok_or(ComputeError::ArithmeticOverflow {
+                parameter: "attestations",
+                index: Some(vote.index),
+            })?;
+        }
+        Ok(Some(value))
+    }
+}
+
+fn sum(values: impl Iterator<Item = U256>, parameter: &'static str) -> Result<U256, ComputeError> {
+    let mut total = U256::ZERO;
+    for (i, value) in values.enumerate() {
+        total = total.checked_add(value).ok_or(ComputeError::ArithmeticOverflow {
+            parameter,
+            index: Some(i),
+        })?;
+    }
+    Ok(total)
+}
+
+fn mean(votes: &[Vote]) -> Result<U256, ComputeError> {
+    let mut total = U256::ZERO;
+    for vote in votes {
+        total = total.checked_add(vote.value).ok_or(ComputeError::ArithmeticOverflow {
+            parameter: "attestations",
+            index: Some(vote.index),
+        })?;
+    }
+    Ok(total / U256::from(votes.len() as u64))
+}
+
+fn median(votes: &[Vote]) -> U256 {
+    let mut values: Vec<U256> = votes.iter().map(|vote| vote.value).collect();
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        return values[middle];
+    }
+    let (low, high) = (values[middle - 1], values[middle]);
+    low + (high - low) / U256::from(2)
+}
+
+// The value of the largest group of identical result hashes, if it holds
+// more than half of the votes and at least `threshold` of them
+fn majority(votes: &[Vote], threshold: U256) -> Option<U256> {
+    let mut groups: Vec<(B256, U256, usize)> = Vec::new();
+    for vote in votes {
+        let hash = keccak256_encode(&[Token::uint(vote.value)]);
+        match groups.iter_mut().find(|(seen, _, _)| *seen == hash) {
+            Some((_, _, count)) => *count += 1,
+            None => groups.push((hash, vote.value, 1)),
+        }
+    }
+    let (_, value, count) = groups.into_iter().max_by_key(|(_, _, count)| *count)?;
+    (count * 2 > votes.len() && U256::from(count as u64) >= threshold).then_some(value)
+}
+
+fn deviations(votes: &[Vote], consensus: U256, tolerance_bps: u16) -> Vec<Deviation> {
+    votes
+        .iter()
+        .filter_map(|vote| {
+            let distance = if vote.value > consensus {
+                vote.value - consensus
+            } else {
+                consensus - vote.value
+            };
+            let deviation_bps = distance
+                .mul_div(U256::from(BASIS_POINTS), consensus)
+                .unwrap_or(U256::MAX);
+            (deviation_bps > U256::from(tolerance_bps as u64)).then_some(Deviation {
+                index: vote.index,
+                value: vote.value,
+                deviation_bps,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(values: &[u64]) -> Vec<Vote> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| Vote {
+                index,
+                operator: index,
+                value: U256::from(*value),
+            })
+            .collect()
+    }
+
+    fn policy(strategy: Strategy) -> AggregationPolicy {
+        AggregationPolicy {
+            strategy,
+            ..AggregationPolicy::default()
+        }
+    }
+
+    #[test]
+    fn robust_strategies_ignore_one_outlier() {
+        let votes = votes(&[100, 102, 98, 101, 1_000_000]);
+        let mean = policy(Strategy::Mean).aggregate(&votes, U256::ONE).unwrap();
+        assert_eq!(mean.value, U256::from(200_080));
+
+        let median = policy(Strategy::Median).aggregate(&votes, U256::ONE).unwrap();
+        assert_eq!(median.value, U256::from(101));
+
+        let trimmed = policy(Strategy::TrimmedMean { trim_bps: 2000 }).aggregate(&votes, U256::ONE).unwrap();
+        assert_eq!(trimmed.value, U256::from(101));
+
+        // The midpoint of an even count
+        let even = policy(Strategy::Median).aggregate(&votes[..4], U256::ONE).unwrap();
+        assert_eq!(even.value, U256::from(100));
+    }
+
+    #[test]
+    fn deviations_beyond_the_tolerance_are_reported() {
+        let votes = votes(&[100, 102, 101, 1_000_000]);
+        let mut median = policy(Strategy::Median);
+        median.deviation_tolerance_bps = 200;
+        let consensus = median.aggregate(&votes, U256::ONE).unwrap();
+        assert_eq!(consensus.value, U256::from(101));
+        assert_eq!(consensus.deviations.len(), 1);
+        assert_eq!(consensus.deviations[0].index, 3);
+        assert_eq!(consensus.deviations[0].deviation_bps, U256::from(98_999_900));
+
+        median.deviation_tolerance_bps = 0;
+        let consensus = median.aggregate(&votes, U256::ONE).unwrap();
+        assert_eq!(consensus.deviations.iter().map(|d| d.index).collect::<Vec<_>>(), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn stake_weighted_mean_needs_a_stake_quorum() {
+        let stakes: Vec<U256> = [60, 30, 10].map(U256::from).to_vec();
+        let policy = AggregationPolicy {
+            strategy: Strategy::StakeWeighted { quorum_bps: 6667 },
+            stakes,
+            deviation_tolerance_bps: 0,
+        };
+        policy.validate(3).unwrap();
+
+        // (60 * 90 + 30 * 180) / 90
+        let consensus = policy.aggregate(&votes(&[90, 180]), U256::ONE).unwrap();
+        assert!(consensus.meets_threshold);
+        assert_eq!(consensus.value, U256::from(120));
+
+        // Operators 1 and 2 hold 40% of the stake
+        let minority = [1, 2].map(|operator| Vote {
+            index: operator,
+            operator,
+            value: U256::from(400),
+        });
+        assert_eq!(policy.aggregate(&minority, U256::ONE).unwrap(), Consensus::default());
+
+        assert_eq!(
+            policy.validate(4),
+            Err(ComputeError::LengthMismatch {
+                parameter: "stakes",
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn majority_requires_more_than_half_in_exact_agreement() {
+        let majority = policy(Strategy::Majority);
+        let consensus = majority.aggregate(&votes(&[7, 7, 8]), U256::from(2)).unwrap();
+        assert_eq!(consensus.value, U256::from(7));
+        assert_eq!(consensus.deviations.len(), 1);
+
+        assert!(!majority.aggregate(&votes(&[7, 7, 8, 8]), U256::ONE).unwrap().meets_threshold);
+        assert!(!majority.aggregate(&votes(&[7, 7, 8]), U256::from(3)).unwrap().meets_threshold);
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        assert!(policy(Strategy::TrimmedMean { trim_bps: 5000 }).validate(1).is_err());
+        assert!(policy(Strategy::StakeWeighted { quorum_bps: 0 }).validate(0).is_err());
+        assert_eq!(
+            serde_json::from_str::<Strategy>(r#"{"type":"trimmedMean","trimBps":1000}"#).unwrap(),
+            Strategy::TrimmedMean { trim_bps: 1000 }
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! AMM invariants used to value LP positions against holding.
+
+use crate::fixed_point::{lp_value_multiplier, wad_sqrt, WAD};
+use crate::u256::U256;
+use serde::{Deserialize, Serialize};
+
+/// Prices of token A and token B in the quote currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prices {
+    pub token_a: U256,
+    pub token_b: U256,
+}
+
+impl Prices {
+    /// Price of token A in units of token B, as a WAD.
+    pub fn relative(&self) -> Option<U256> {
+        self.token_a.mul_div(WAD, self.token_b)
+    }
+
+    /// Quote value of `amount_a` token A plus `amount_b` token B, with
+    /// amounts, prices and the result all in 18 decimals.
+    pub fn value_of(&self, amount_a: U256, amount_b: U256) -> Option<U256> {
+        amount_a
+            .mul_div(self.token_a, WAD)?
+            .checked_add(amount_b.mul_div(self.token_b, WAD)?)
+    }
+}
+
+/// Tokens deposited into a pool and the prices when they were deposited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deposit {
+    pub amount_a: U256,
+    pub amount_b: U256,
+    pub entry_prices: Prices,
+}
+
+impl Deposit {
+    /// Change of token A's price relative to token B since entry, as a WAD.
+    pub fn price_ratio(&self, prices: &Prices) -> Option<U256> {
+        prices
+            .token_a
+            .mul_div(WAD, self.entry_prices.token_a)?
+            .mul_div(self.entry_prices.token_b, prices.token_b)
+    }
+}
+
+/// Pool invariant used to value an LP position once prices move.
+///
+/// Models assume arbitrage has moved the pool to the market price and return
+/// `None` when a value does not fit in 256 bits.
+pub trait AmmModel {
+    /// Quote value of the LP position at `prices`, excluding fees.
+    fn position_value(&self, prices: &Prices) -> Option<U256>;
+
+    /// Quote value at `prices` of the tokens that entered the position.
+    fn hodl_value(&self, prices: &Prices) -> Option<U256>;
+}
+
+/// Pool type carried by an [`AttestationRequest`](crate::request::AttestationRequest).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PoolType {
+    /// Uniswap v2-style `x * y = k` over the full price range.
+    #[default]
+    ConstantProduct,
+    /// Balancer-style weighted pool; token B holds the remaining weight.
+    #[serde(rename_all = "camelCase")]
+    Weighted { weight_a_bps: u16 },
+    /// Two-coin Curve StableSwap pool with its on-chain `A` (without
+    /// `A_PRECISION`).
+    StableSwap { amplification: u64 },
+    /// Uniswap v3-style range between two WAD prices of token A in token B.
+    #[serde(rename_all = "camelCase")]
+    Concentrated { price_lower: U256, price_upper: U256 },
+}
+
+impl PoolType {
+    /// Builds the model for `deposit`, or `None` when the pool parameters or
+    /// entry prices are invalid.
+    pub fn model(&self, deposit: Deposit) -> Option<Box<dyn AmmModel>> {
+        if deposit.entry_prices.token_a.is_zero() || deposit.entry_prices.token_b.is_zero() {
+            return None;
+        }
+        Some(match *self {
+            PoolType::ConstantProduct => Box::new(ConstantProduct { deposit }),
+            PoolType::Weighted { weight_a_bps } => Box::new(WeightedPool::new(deposit, weight_a_bps)?),
+            PoolType::StableSwap { amplification } => Box::new(StableSwap::new(deposit, amplification)?),
+            PoolType::Concentrated { price_lower, price_upper } => {
+                Box::new(ConcentratedLiquidity::new(deposit, price_lower, price_upper)?)
+            }
+        })
+    }
+
+    /// The pool as `(kind, param0, param1)`, as entry commitments and
+    /// transcripts record it: 0 for constant product, 1 for weighted
+    /// `(weight_a_bps, 0)`, 2 for StableSwap `(amplification, 0)` and 3 for
+    /// concentrated `(price_lower, price_upper)`.
+    pub fn encoded(&self) -> (u8, U256, U256) {
+        match *self {
+            PoolType::ConstantProduct => (0, U256::ZERO, U256::ZERO),
+            PoolType::Weighted { weight_a_bps } => (1, U256::from(weight_a_bps as u64), U256::ZERO),
+            PoolType::StableSwap { amplification } => (2, U256::from(amplification), U256::ZERO),
+            PoolType::Concentrated { price_lower, price_upper } => (3, price_lower, price_upper),
+        }
+    }
+}
+
+/// Full-range `x * y = k`: the position is worth `2 * sqrt(r) / (1 + r)` of
+/// holding, where `r` is the relative price change.
+pub struct ConstantProduct {
+    deposit: Deposit,
+}
+
+impl AmmModel for ConstantProduct {
+    fn position_value(&self, prices: &Prices) -> Option<U256> {
+        let multiplier = lp_value_multiplier(self.deposit.price_ratio(prices)?)?;
+        self.hodl_value(prices)?.mul_div(multiplier, WAD)
+    }
+
+    fn hodl_value(&self, prices: &Prices) -> Option<U256> {
+        prices.value_of(self.deposit.amount_a, self.deposit.amount_b)
+    }
+}
+
+/// Weighted pool `x^w * y^(1 - w) = k`: the position is worth
+/// `r^w / (w * r + 1 - w)` of holding.
+pub struct WeightedPool {
+    deposit: Deposit,
+    weight_a_bps: u16,
+}
+
+impl WeightedPool {
+    /// Binary digits kept from the weight when raising to it.
+    const WEIGHT_BITS: u32 = 40;
+
+    fn new(deposit: Deposit, weight_a_bps: u16) -> Option<Self> {
+        if weight_a_bps == 0 || weight_a_bps >= 10000 {
+            return None;
+        }
+        Some(WeightedPool { deposit, weight_a_bps })
+    }
+
+    // `ratio^w` for a WAD ratio: each binary digit of `w` multiplies in the
+    // matching repeated square root of the ratio.
+    fn pow_weight(&self, ratio: U256) -> Option<U256> {
+        let weight = (u64::from(self.weight_a_bps) << Self::WEIGHT_BITS) / 10000;
+        let mut root = ratio;
+        let mut result = WAD;
+        for bit in (0..Self::WEIGHT_BITS).rev() {
+            root = wad_sqrt(root);
+            if weight >> bit & 1 == 1 {
+                result = result.mul_div(root, WAD)?;
+            }
+        }
+        Some(result)
+    }
+}
+
+impl AmmModel for WeightedPool {
+    fn position_value(&self, prices: &Prices) -> Option<U256> {
+        let ratio = self.deposit.price_ratio(prices)?;
+        let weight = U256::from(u64::from(self.weight_a_bps) * 100_000_000_000_000); // bps to WAD
+        let denominator = ratio
+            .mul_div(weight, WAD)?
+            .checked_add(WAD - weight)?;
+        let multiplier = self.pow_weight(ratio)?.mul_div(WAD, denominator)?;
+        self.hodl_value(prices)?.mul_div(multiplier, WAD)
+    }
+
+    fn hodl_value(&self, prices: &Prices) -> Option<U256> {
+        prices.value_of(self.deposit.amount_a, self.deposit.amount_b)
+    }
+}
+
+/// Two-coin StableSwap invariant
+/// `Ann * (x + y) + D = Ann * D + D^3 / (4 * x * y)` with `Ann = 2 * A`,
+/// following Curve's `get_D`/`get_y`. The deposit sets `D`, and the pool
+/// price moves by the same ratio as the market price.
+pub struct StableSwap {
+    deposit: Deposit,
+    ann: U256,
+    invariant: U256,
+    entry_pool_price: U256,
+}
+
+impl StableSwap {
+    /// Iteration cap used by Curve's Newton loops.
+    const MAX_ITERATIONS: usize = 255;
+
+    fn new(deposit: Deposit, amplification: u64) -> Option<Self> {
+        if amplification == 0 || deposit.amount_a.is_zero() || deposit.amount_b.is_zero() {
+            return None;
+        }
+        let ann = U256::from(amplification).checked_mul(U256::from(2))?;
+        let mut pool = StableSwap {
+            deposit,
+            ann,
+            invariant: U256::ZERO,
+            entry_pool_price: U256::ZERO,
+        };
+        pool.invariant = pool.get_d(deposit.amount_a, deposit.amount_b)?;
+        pool.entry_pool_price = pool.pool_price(deposit.amount_a, deposit.amount_b)?;
+        Some(pool)
+    }
+
+    // `D^3 / (4 * x * y)`
+    fn d_product(d: U256, x: U256, y: U256) -> Option<U256> {
+        d.mul_div(d, x.checked_mul(U256::from(2))?)?
+            .mul_div(d, y.checked_mul(U256::from(2))?)
+    }
+
+    fn get_d(&self, x: U256, y: U256) -> Option<U256> {
+        let sum = x.checked_add(y)?;
+        let mut d = sum;
+        for _ in 0..Self::MAX_ITERATIONS {
+            let d_p = Self::d_product(d, x, y)?;
+            let previous = d;
+            let numerator = self.ann.checked_mul(sum)?.checked_add(d_p.checked_mul(U256::from(2))?)?;
+            let denominator = (self.ann - U256::ONE)
+                .checked_mul(d)?
+                .checked_add(d_p.checked_mul(U256::from(3))?)?;
+            d = numerator.mul_div(d, denominator)?;
+            if d.max(previous) - d.min(previous) <= U256::ONE {
+                return Some(d);
+            }
+        }
+        None
+    }
+
+    // Token B balance that keeps the invariant when token A's balance is `x`
+    fn get_y(&self, x: U256) -> Option<U256> {
+        let d = self.invariant;
+        let c = d
+            .mul_div(d, x.checked_mul(U256::from(2))?)?
+            .mul_div(d, self.ann.checked_mul(U256::from(2))?)?;
+        let b = x.checked_add(d / self.ann)?;
+        let mut y = d;
+        for _ in 0..Self::MAX_ITERATIONS {
+            let previous = y;
+            let denominator = y
+                .checked_mul(U256::from(2))?
+                .checked_add(b)?
+                .checked_sub(d)?;
+            y = y.checked_mul(y)?.checked_add(c)?.checked_div(denominator)?;
+            if y.max(previous) - y.min(previous) <= U256::ONE {
+                return Some(y);
+            }
+        }
+        None
+    }
+
+    // Marginal price of token A in token B at balances `(x, y)`, as a WAD:
+    // `(Ann + D^3 / (4 * x^2 * y)) / (Ann + D^3 / (4 * x * y^2))`
+    fn pool_price(&self, x: U256, y: U256) -> Option<U256> {
+        let d_p = Self::d_product(self.invariant, x, y)?;
+        let ann = self.ann.checked_mul(WAD)?;
+        let slope_x = ann.checked_add(d_p.mul_div(WAD, x)?)?;
+        let slope_y = ann.checked_add(d_p.mul_div(WAD, y)?)?;
+        slope_x.mul_div(WAD, slope_y)
+    }
+
+    // Token A balance at which the pool quotes `target` (the price falls as
+    // the balance grows)
+    fn balance_at_price(&self, target: U256) -> Option<U256> {
+        let above = |x: U256| -> Option<bool> { Some(self.pool_price(x, self.get_y(x)?)? > target) };
+
+        let (mut low, mut high) = if above(self.deposit.amount_a)? {
+            let mut high = self.deposit.amount_a;
+            while above(high)? {
+                high = high.checked_mul(U256::from(2))?;
+            }
+            (high / U256::from(2), high)
+        } else {
+            (U256::ONE, self.deposit.amount_a)
+        };
+
+        while high - low > U256::ONE {
+            let mid = low + (high - low) / U256::from(2);
+            if above(mid)? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(high)
+    }
+}
+
+impl AmmModel for StableSwap {
+    fn position_value(&self, prices: &Prices) -> Option<U256> {
+        let target = self
+            .entry_pool_price
+            .mul_div(self.deposit.price_ratio(prices)?, WAD)?;
+        let x = self.balance_at_price(target)?;
+        prices.value_of(x, self.get_y(x)?)
+    }
+
+    fn hodl_value(&self, prices: &Prices) -> Option<U256> {
+        prices.value_of(self.deposit.amount_a, self.deposit.amount_b)
+    }
+}
+
+/// Uniswap v3-style liquidity between two prices of token A in token B.
+/// Tokens that do not fit the range at entry are refunded, so only the
+/// amounts that entered the position count towards the hodl value.
+pub struct ConcentratedLiquidity {
+    liquidity: U256,
+    sqrt_lower: U256,
+    sqrt_upper: U256,
+    sqrt_entry: U256,
+}
+
+impl ConcentratedLiquidity {
+    fn new(deposit: Deposit, price_lower: U256, price_upper: U256) -> Option<Self> {
+        if price_lower.is_zero() || price_lower >= price_upper {
+            return None;
+        }
+        let sqrt_lower = wad_sqrt(price_lower);
+        let sqrt_upper = wad_sqrt(price_upper);
+        let sqrt_entry = wad_sqrt(deposit.entry_prices.relative()?);
+        if sqrt_lower.is_zero() || sqrt_lower >= sqrt_upper {
+            return None;
+        }
+
+        // L = x * sa * sb / (sb - sa) below the range, y / (sb - sa) above it,
+        // and the smaller of the two legs inside it
+        let from_a = |sqrt_price: U256| {
+            deposit
+                .amount_a
+                .mul_div(sqrt_price, sqrt_upper - sqrt_price)?
+                .mul_div(sqrt_upper, WAD)
+        };
+        let from_b = |sqrt_price: U256| deposit.amount_b.mul_div(WAD, sqrt_price - sqrt_lower);
+        let liquidity = if sqrt_entry <= sqrt_lower {
+            from_a(sqrt_lower)?
+        } else if sqrt_entry >= sqrt_upper {
+            from_b(sqrt_upper)?
+        } else {
+            from_a(sqrt_entry)?.min(from_b(sqrt_entry)?)
+        };
+
+        Some(ConcentratedLiquidity {
+            liquidity,
+            sqrt_lower,
+            sqrt_upper,
+            sqrt_entry,
+        })
+    }
+
+    // Token amounts held at a WAD square-root price, rounded down
+    fn amounts(&self, sqrt_price: U256) -> Option<(U256, U256)> {
+        let sqrt_price = sqrt_price.max(self.sqrt_lower).min(self.sqrt_upper);
+        let amount_a = self
+            .liquidity
+            .mul_div(self.sqrt_upper - sqrt_price, sqrt_price)?
+            .mul_div(WAD, self.sqrt_upper)?;
+        let amount_b = self.liquidity.mul_div(sqrt_price - self.sqrt_lower, WAD)?;
+        Some((amount_a, amount_b))
+    }
+}
+
+impl AmmModel for ConcentratedLiquidity {
+    fn position_value(&self, prices: &Prices) -> Option<U256> {
+        let sqrt_price = wad_sqrt(prices.relative()?);
+        let (amount_a, amount_b) = self.amounts(sqrt_price)?;
+        prices.value_of(amount_a, amount_b)
+    }
+
+    fn hodl_value(&self, prices: &Prices) -> Option<U256> {
+        let (amount_a, amount_b) = self.amounts(self.sqrt_entry)?;
+        prices.value_of(amount_a, amount_b)
+    }
+}
@@ -0,0 +1,195 @@
+//! BLS12-381 signatures of operator attestations.
+//!
+//! Uses the same scheme as `@noble/curves`' `bls12_381`, which the AVS
+//! node's `BLSSignatureAggregator` signs with: 48-byte compressed G1 public
+//! keys, 96-byte compressed G2 signatures, and messages hashed to G2 with
+//! the `BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_` ciphersuite.
+//!
+//! Aggregating signatures over the same message is only sound for keys with
+//! a proof of possession, which operators provide when they register.
+
+use crate::abi::{keccak256, keccak256_encode, Token};
+use crate::u256::U256;
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar};
+
+/// Domain separation tag of the basic scheme with G2 signatures.
+pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+pub const PUBLIC_KEY_LENGTH: usize = 48;
+pub const SIGNATURE_LENGTH: usize = 96;
+
+/// Tag hashed into every attestation message, so a signature over one is
+/// not a valid signature of anything else the key signs.
+pub const ATTESTATION_TAG: &str = "ConfidentialInsurance.Attestation";
+
+/// Message an operator signs for an attestation value of a policy:
+/// `keccak256(abi.encode(keccak256(ATTESTATION_TAG), policyId, attestation))`.
+/// Binding the policy keeps a signature from being replayed for another one.
+pub fn attestation_message(policy_id: U256, attestation: U256) -> [u8; 32] {
+    keccak256_encode(&[
+        Token::bytes32(keccak256(ATTESTATION_TAG)),
+        Token::uint(policy_id),
+        Token::uint(attestation),
+    ])
+    .0
+}
+
+/// Decodes a compressed G1 public key, rejecting the identity and points
+/// outside the prime-order subgroup.
+pub fn public_key(bytes: &[u8]) -> Option<G1Affine> {
+    let bytes: &[u8; PUBLIC_KEY_LENGTH] = bytes.try_into().ok()?;
+    Option::from(G1Affine::from_compressed(bytes)).filter(|key: &G1Affine| !bool::from(key.is_identity()))
+}
+
+/// Decodes a compressed G2 signature, rejecting points outside the
+/// prime-order subgroup.
+pub fn signature(bytes: &[u8]) -> Option<G2Affine> {
+    let bytes: &[u8; SIGNATURE_LENGTH] = bytes.try_into().ok()?;
+    Option::from(G2Affine::from_compressed(bytes))
+}
+
+fn hash_to_g2(message: &[u8]) -> G2Affine {
+    G2Affine::from(<G2Projective as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(message, DST))
+}
+
+/// Checks `e(g1, signature) == Π e(public_key, H(message))` over the pairs.
+/// Pairs with the same message should share one summed public key.
+fn verify_pairs(pairs: &[(G1Affine, &[u8])], signature: &G2Affine) -> bool {
+    let generator = -G1Affine::generator();
+    let signature = G2Prepared::from(*signature);
+    let hashes: Vec<G2Prepared> = pairs.iter().map(|(_, message)| G2Prepared::from(hash_to_g2(message))).collect();
+    let mut terms: Vec<(&G1Affine, &G2Prepared)> = vec![(&generator, &signature)];
+    terms.extend(pairs.iter().zip(&hashes).map(|((key, _), hash)| (key, hash)));
+    multi_miller_loop(&terms).final_exponentiation() == Gt::identity()
+}
+
+/// Verifies one signature; malformed encodings are not valid.
+pub fn verify(public_key_bytes: &[u8], message: &[u8], signature_bytes: &[u8]) -> bool {
+    match (public_key(public_key_bytes), signature(signature_bytes)) {
+        (Some(key), Some(signature)) => verify_pairs(&[(key, message)], &signature),
+        _ => false,
+    }
+}
+
+/// Signs `message` with a 32-byte big-endian secret key, as operators do.
+/// Returns `None` for a key that is zero or not below the group order.
+pub fn sign(secret_key: &[u8; 32], message: &[u8]) -> Option<[u8; SIGNATURE_LENGTH]> {
+    let scalar = secret_scalar(secret_key)?;
+    Some(G2Affine::from(hash_to_g2(message) * scalar).to_compressed())
+}
+
+/// Compressed public key of a 32-byte big-endian secret key.
+pub fn derive_public_key(secret_key: &[u8; 32]) -> Option<[u8; PUBLIC_KEY_LENGTH]> {
+    let scalar = secret_scalar(secret_key)?;
+    Some(G1Affine::from(G1Affine::generator() * scalar).to_compressed())
+}
+
+pub(crate) fn secret_scalar(secret_key: &[u8; 32]) -> Option<Scalar> {
+    let mut little_endian = *secret_key;
+    little_endian.reverse();
+    Option::<Scalar>::from(Scalar::from_bytes(&little_endian)).filter(|scalar| *scalar != Scalar::zero())
+}
+
+/// Running aggregate of verified signatures.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregate {
+    signature: G2Projective,
+    // Summed public key per distinct message, in first-seen order
+    keys_by_message: Vec<([u8; 32], G1Projective)>,
+    public_key: G1Projective,
+}
+
+impl Aggregate {
+    /// Verifies `signature_bytes` by `public_key_bytes` over `message` and
+    /// adds it to the aggregate. Returns whether it was added.
+    pub fn add(&mut self, public_key_bytes: &[u8], message: [u8; 32], signature_bytes: &[u8]) -> bool {
+        let (Some(key), Some(signature)) = (public_key(public_key_bytes), signature(signature_bytes)) else {
+            return false;
+        };
+        if !verify_pairs(&[(key, &message)], &signature) {
+            return false;
+        }
+        self.signature += signature;
+        self.public_key += key;
+        match self.keys_by_message.iter_mut().find(|(seen, _)| *seen == message) {
+            Some((_, sum)) => *sum += key,
+            None => self.keys_by_message.push((message, key.into())),
+        }
+        true
+    }
+
+    /// Verifies the aggregate signature against the summed public key of
+    /// each distinct message.
+    pub fn verify(&self) -> bool {
+        if self.keys_by_message.is_empty() {
+            return false;
+        }
+        let pairs: Vec<(G1Affine, &[u8])> = self
+            .keys_by_message
+            .iter()
+            .map(|(message, key)| (G1Affine::from(key), &message[..]))
+            .collect();
+        verify_pairs(&pairs, &G2Affine::from(self.signature))
+    }
+
+    /// Compressed aggregate signature.
+    pub fn signature(&self) -> [u8; SIGNATURE_LENGTH] {
+        G2Affine::from(self.signature).to_compressed()
+    }
+
+    /// Compressed sum of the signers' public keys.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LENGTH] {
+        G1Affine::from(self.public_key).to_compressed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[31] = seed;
+        key
+    }
+
+    #[test]
+    fn sign_and_verify_round_trip() {
+        let message = attestation_message(U256::ONE, U256::from(42));
+        let public_key = derive_public_key(&secret(1)).unwrap();
+        let signature = sign(&secret(1), &message).unwrap();
+        assert!(verify(&public_key, &message, &signature));
+        assert!(!verify(&public_key, &attestation_message(U256::ONE, U256::from(43)), &signature));
+        assert!(!verify(&public_key, &attestation_message(U256::from(2), U256::from(42)), &signature));
+        assert!(!verify(&derive_public_key(&secret(2)).unwrap(), &message, &signature));
+        assert!(!verify(&public_key[1..], &message, &signature));
+        assert_eq!(sign(&[0; 32], &message), None);
+        assert_eq!(sign(&[0xff; 32], &message), None);
+    }
+
+    #[test]
+    fn aggregate_verifies_same_and_distinct_messages() {
+        let same = attestation_message(U256::ONE, U256::from(7));
+        let other = attestation_message(U256::ONE, U256::from(8));
+        let mut aggregate = Aggregate::default();
+        assert!(!aggregate.verify());
+        for (seed, message) in [(1, same), (2, same), (3, other)] {
+            let public_key = derive_public_key(&secret(seed)).unwrap();
+            assert!(aggregate.add(&public_key, message, &sign(&secret(seed), &message).unwrap()));
+        }
+        assert!(aggregate.verify());
+
+        // Same-message signers verify against their summed key alone
+        let mut same_only = Aggregate::default();
+        for seed in [1, 2] {
+            let public_key = derive_public_key(&secret(seed)).unwrap();
+            same_only.add(&public_key, same, &sign(&secret(seed), &same).unwrap());
+        }
+        assert!(verify(&same_only.public_key(), &same, &same_only.signature()));
+
+        // A signature over another message is not added
+        let public_key = derive_public_key(&secret(4)).unwrap();
+        assert!(!aggregate.add(&public_key, same, &sign(&secret(4), &other).unwrap()));
+    }
+}
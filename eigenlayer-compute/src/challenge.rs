@@ -0,0 +1,173 @@
+//! Fraud-proof evidence against signed attestation responses.
+//!
+//! A challenger re-executes a disputed request with
+//! [`ComputeEngine::challenge_attestation`](crate::ComputeEngine::challenge_attestation).
+//! When the canonical transcript disagrees with what the operator signed,
+//! the result is an [`Evidence`] that carries everything needed to check
+//! the claim without the engine: the request, the transcript with its
+//! inputs, intermediates and expected outputs, and the signed response with
+//! its signer. [`Evidence::encode`] is the `evidence` argument of
+//! `EigenAVSManager.challengeAttestation`.
+//!
+//! Signed responses commit to the request only through the operator's
+//! result hash, which a challenger cannot open. The request's position is
+//! instead bound by the policy's on-chain entry commitment, which
+//! [`Evidence::verify`] takes from its caller rather than from the
+//! evidence, and its current prices by the signed prices hash; the arbiter
+//! checks those against the oracle round the claim was settled with.
+
+use crate::abi::{self, Token, B256};
+use crate::keystore::KeyScheme;
+use crate::request::{AttestationRequest, AttestationResponse};
+use crate::transcript::{ComputeCall, Transcript};
+use crate::u256::{Bytes, U256};
+use serde::{Deserialize, Serialize};
+
+/// A result the operator signed that re-execution does not reproduce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mismatch {
+    /// Field of the response and output of the transcript
+    pub field: String,
+    pub expected: U256,
+    pub attested: U256,
+}
+
+/// Self-contained proof that a signed response is wrong.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Evidence {
+    pub policy_id: U256,
+    /// The disputed request
+    pub request: AttestationRequest,
+    /// Re-execution of `request`; its outputs are the expected values
+    pub transcript: Transcript,
+    /// The response as the operator signed it, with its signer
+    pub attested: AttestationResponse,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Evidence {
+    /// Evidence that `attested` disagrees with the re-execution of
+    /// `request` in `transcript`, or `None` when the operator signed what
+    /// the engine computes.
+    pub fn new(request: AttestationRequest, transcript: Transcript, attested: AttestationResponse) -> Option<Self> {
+        let mismatches = mismatches(&transcript, &attested);
+        if mismatches.is_empty() {
+            return None;
+        }
+        Some(Evidence {
+            policy_id: attested.policy_id,
+            request,
+            transcript,
+            attested,
+            mismatches,
+        })
+    }
+
+    /// Whether the evidence holds up: the request opens `entry_commit`,
+    /// the policy's `entryCommit` as `PolicyManager` stores it, the
+    /// transcript records the request and commits to its words, the signer
+    /// signed the attested response for the same policy and prices, and the
+    /// two disagree as recorded.
+    ///
+    /// This does not re-execute the request; challenge the attestation
+    /// again to check the transcript's intermediates and outputs.
+    pub fn verify(&self, entry_commit: B256) -> bool {
+        let call = ComputeCall::ProcessAttestationRequest {
+            request: Box::new(self.request.clone()),
+        };
+        let opens_commitment = match self.request.position_salt {
+            Some(salt) => !entry_commit.is_zero() && self.request.entry_commitment(salt) == entry_commit,
+            None => false,
+        };
+        opens_commitment
+            && self.transcript.method == call.method()
+            && self.transcript.inputs == call.transcript().inputs
+            && self.transcript.verify_hash()
+            && self.attested.verify_signature()
+            && self.attested.policy_id == self.policy_id
+            && self.attested.prices_hash == self.request.prices_hash()
+            && self.request.policy_id == self.policy_id
+            && !self.mismatches.is_empty()
+            && self.mismatches == mismatches(&self.transcript, &self.attested)
+    }
+
+    /// The evidence as `challengeAttestation` takes it:
+    ///
+    /// ```text
+    /// abi.encode(uint256 policyId,
+    ///            string engineVersion, string rounding, string method,
+    ///            string[] inputNames, uint256[] inputs,
+    ///            string[] intermediateNames, uint256[] intermediates,
+    ///            string[] outputNames, uint256[] outputs, bytes32 resultHash,
+    ///            uint256 impermanentLoss, bool hasLoss, uint256 payout, bool isValid,
+    ///            bytes32 pricesHash, uint256 nonce, uint256 deadline, bytes32 attestedResultHash,
+    ///            uint256 chainId, address verifyingContract,
+    ///            uint8 signerScheme, bytes signerPublicKey, address signer, bytes signature)
+    /// ```
+    ///
+    /// The attested values are the response as signed, and with the chain
+    /// and verifying contract they give its `ILAttestation` digest. The
+    /// signer scheme is 0 for ECDSA, whose signer is the address, and 1 for
+    /// BLS, whose address is zero.
+    pub fn encode(&self) -> Bytes {
+        let transcript = &self.transcript;
+        let mut tokens = vec![
+            Token::uint(self.policy_id),
+            Token::String(transcript.engine_version.clone()),
+            Token::String(transcript.rounding.clone()),
+            Token::String(transcript.method.clone()),
+        ];
+        for words in [&transcript.inputs, &transcript.intermediates, &transcript.outputs] {
+            tokens.push(Token::Array(words.iter().map(|word| Token::String(word.name.clone())).collect()));
+            tokens.push(Token::Array(words.iter().map(|word| Token::uint(word.value)).collect()));
+        }
+        tokens.push(Token::bytes32(transcript.result_hash));
+
+        let attested = &self.attested;
+        let domain = attested.domain.as_ref();
+        let signer = attested.signer.as_ref();
+        let scheme = match signer.map(|signer| signer.scheme) {
+            Some(KeyScheme::Bls) => 1,
+            _ => 0,
+        };
+        tokens.extend([
+            Token::uint(attested.impermanent_loss),
+            Token::Bool(attested.has_loss),
+            Token::uint(attested.payout),
+            Token::Bool(attested.is_valid),
+            Token::bytes32(attested.prices_hash),
+            Token::uint(attested.nonce),
+            Token::uint(attested.deadline),
+            Token::bytes32(attested.result_hash),
+            Token::uint(domain.map_or(U256::ZERO, |domain| domain.chain_id)),
+            Token::Address(domain.map(|domain| domain.verifying_contract).unwrap_or_default()),
+            Token::Uint(U256::from(scheme), 8),
+            Token::Bytes(signer.map(|signer| signer.public_key.0.clone()).unwrap_or_default()),
+            Token::Address(signer.and_then(|signer| signer.address).unwrap_or_default()),
+            Token::Bytes(attested.signature.as_ref().map(|signature| signature.0.clone()).unwrap_or_default()),
+        ]);
+        Bytes(abi::encode(&tokens))
+    }
+}
+
+// Response fields that differ from the transcript's outputs
+fn mismatches(transcript: &Transcript, attested: &AttestationResponse) -> Vec<Mismatch> {
+    let bool_word = |value: bool| U256::from(value as u64);
+    [
+        ("impermanent_loss", attested.impermanent_loss),
+        ("has_loss", bool_word(attested.has_loss)),
+        ("payout", attested.payout),
+        ("is_valid", bool_word(attested.is_valid)),
+    ]
+    .into_iter()
+    .filter_map(|(field, attested)| {
+        // A transcript without the output expects zero
+        let expected = transcript.output_value(field).unwrap_or(U256::ZERO);
+        (expected != attested).then(|| Mismatch {
+            field: field.to_string(),
+            expected,
+            attested,
+        })
+    })
+    .collect()
+}
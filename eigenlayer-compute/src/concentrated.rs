@@ -0,0 +1,308 @@
+//! Impermanent loss for Uniswap v3/v4 concentrated-liquidity positions.
+//!
+//! Token amounts follow Uniswap's `TickMath` and `LiquidityAmounts` (rounding
+//! down), and values are expressed in token1 units at the current price.
+
+use crate::error::ComputeError;
+use crate::fixed_point::Q96;
+use crate::u256::U256;
+use serde::Serialize;
+
+/// `TickMath.MIN_TICK`
+pub const MIN_TICK: i32 = -887272;
+
+/// `TickMath.MAX_TICK`
+pub const MAX_TICK: i32 = 887272;
+
+/// `TickMath.MIN_SQRT_PRICE`
+pub const MIN_SQRT_PRICE: U256 = U256([4295128739, 0, 0, 0]);
+
+/// `TickMath.MAX_SQRT_PRICE`
+pub const MAX_SQRT_PRICE: U256 = U256([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0]);
+
+fn out_of_range(parameter: &'static str) -> ComputeError {
+    ComputeError::OutOfRange { parameter, index: None }
+}
+
+const OVERFLOW: ComputeError = ComputeError::ArithmeticOverflow {
+    parameter: "liquidity",
+    index: None,
+};
+
+/// Token amounts and impermanent loss of a range position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcentratedIL {
+    pub entry_amount0: U256,
+    pub entry_amount1: U256,
+    pub current_amount0: U256,
+    pub current_amount1: U256,
+    /// Entry amounts valued at the current price, in token1
+    pub hodl_value: U256,
+    /// Current amounts valued at the current price, in token1
+    pub lp_value: U256,
+    pub impermanent_loss: U256,
+    /// Whether the current price lies inside `[tick_lower, tick_upper)`
+    pub in_range: bool,
+}
+
+/// `TickMath.getSqrtPriceAtTick`: `sqrt(1.0001^tick) * 2^96`, rounded up.
+pub fn sqrt_price_at_tick(tick: i32) -> Result<U256, ComputeError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(out_of_range("tick"));
+    }
+    let abs_tick = tick.unsigned_abs();
+
+    // Q128.128 factors sqrt(1.0001)^-(2^i)
+    const FACTORS: [u128; 19] = [
+        0xfff97272373d413259a46990580e213a,
+        0xfff2e50f5f656932ef12357cf3c7fdcc,
+        0xffe5caca7e10e4e61c3624eaa0941cd0,
+        0xffcb9843d60f6159c9db58835c926644,
+        0xff973b41fa98c081472e6896dfb254c0,
+        0xff2ea16466c96a3843ec78b326b52861,
+        0xfe5dee046a99a2a811c461f1969c3053,
+        0xfcbe86c7900a88aedcffc83b479aa3a4,
+        0xf987a7253ac413176f2b074cf7815e54,
+        0xf3392b0822b70005940c7a398e4b70f3,
+        0xe7159475a2c29b7443b29c7fa6e889d9,
+        0xd097f3bdfd2022b8845ad8f792aa5825,
+        0xa9f746462d870fdf8a65dc1f90e061e5,
+        0x70d869a156d2a1b890bb3df62baf32f7,
+        0x31be135f97d08fd981231505542fcfa6,
+        0x9aa508b5b7a84e1c677de54f3e99bc9,
+        0x5d6af8dedb81196699c329225ee604,
+        0x2216e584f5fa1ea926041bedfe98,
+        0x48a170391f7dc42444e8fa2,
+    ];
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from_u128(0xfffcb933bd6fad37aa2d162d1a594001)
+    } else {
+        U256::ONE << 128
+    };
+    for (bit, factor) in FACTORS.iter().enumerate() {
+        if abs_tick & (0x2 << bit) != 0 {
+            ratio = (ratio * U256::from_u128(*factor)) >> 128;
+        }
+    }
+
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128.128 to Q64.96, rounding up
+    let round_up = ratio.0[0] & u32::MAX as u64 != 0;
+    Ok((ratio >> 32) + U256::from(round_up as u64))
+}
+
+fn check_sqrt_price(sqrt_price_x96: U256, parameter: &'static str) -> Result<U256, ComputeError> {
+    if sqrt_price_x96 < MIN_SQRT_PRICE || sqrt_price_x96 >= MAX_SQRT_PRICE {
+        return Err(out_of_range(parameter));
+    }
+    Ok(sqrt_price_x96)
+}
+
+/// `LiquidityAmounts.getAmount0ForLiquidity`:
+/// `L * (sqrt_b - sqrt_a) / (sqrt_a * sqrt_b)` in Q96.
+fn amount0_for_liquidity(sqrt_a: U256, sqrt_b: U256, liquidity: U256) -> Result<U256, ComputeError> {
+    let (sqrt_a, sqrt_b) = if sqrt_a > sqrt_b { (sqrt_b, sqrt_a) } else { (sqrt_a, sqrt_b) };
+    (liquidity << 96)
+        .mul_div(sqrt_b - sqrt_a, sqrt_b)
+        .map(|scaled| scaled / sqrt_a)
+        .ok_or(OVERFLOW)
+}
+
+/// `LiquidityAmounts.getAmount1ForLiquidity`: `L * (sqrt_b - sqrt_a)` in Q96.
+fn amount1_for_liquidity(sqrt_a: U256, sqrt_b: U256, liquidity: U256) -> Result<U256, ComputeError> {
+    let (sqrt_a, sqrt_b) = if sqrt_a > sqrt_b { (sqrt_b, sqrt_a) } else { (sqrt_a, sqrt_b) };
+    liquidity.mul_div(sqrt_b - sqrt_a, Q96).ok_or(OVERFLOW)
+}
+
+/// `LiquidityAmounts.getAmountsForLiquidity`: token amounts held by a position
+/// at `sqrt_price_x96`. Below the range the position is all token0, above it
+/// all token1. Liquidity is a `uint128` on-chain, which keeps `L << 96`
+/// within 256 bits.
+pub fn amounts_for_liquidity(
+    sqrt_price_x96: U256,
+    sqrt_lower: U256,
+    sqrt_upper: U256,
+    liquidity: U256,
+) -> Result<(U256, U256), ComputeError> {
+    if liquidity > U256::from_u128(u128::MAX) {
+        return Err(out_of_range("liquidity"));
+    }
+    if sqrt_price_x96 <= sqrt_lower {
+        Ok((amount0_for_liquidity(sqrt_lower, sqrt_upper, liquidity)?, U256::ZERO))
+    } else if sqrt_price_x96 < sqrt_upper {
+        Ok((
+            amount0_for_liquidity(sqrt_price_x96, sqrt_upper, liquidity)?,
+            amount1_for_liquidity(sqrt_lower, sqrt_price_x96, liquidity)?,
+        ))
+    } else {
+        Ok((U256::ZERO, amount1_for_liquidity(sqrt_lower, sqrt_upper, liquidity)?))
+    }
+}
+
+/// Values `amount0` and `amount1` in token1 at `sqrt_price_x96`.
+fn value_in_token1(amount0: U256, amount1: U256, sqrt_price_x96: U256) -> Result<U256, ComputeError> {
+    amount0
+        .mul_div(sqrt_price_x96, Q96)
+        .and_then(|partial| partial.mul_div(sqrt_price_x96, Q96))
+        .and_then(|value0| value0.checked_add(amount1))
+        .ok_or(OVERFLOW)
+}
+
+/// Impermanent loss of `liquidity` in `[tick_lower, tick_upper)` opened at
+/// `sqrt_price_entry_x96` and marked at `sqrt_price_current_x96`.
+pub fn calculate_concentrated_il(
+    liquidity: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+    sqrt_price_entry_x96: U256,
+    sqrt_price_current_x96: U256,
+) -> Result<ConcentratedIL, ComputeError> {
+    if tick_lower >= tick_upper {
+        return Err(out_of_range("tick_upper"));
+    }
+    let sqrt_lower = sqrt_price_at_tick(tick_lower).map_err(|_| out_of_range("tick_lower"))?;
+    let sqrt_upper = sqrt_price_at_tick(tick_upper).map_err(|_| out_of_range("tick_upper"))?;
+    let sqrt_entry = check_sqrt_price(sqrt_price_entry_x96, "sqrt_price_entry_x96")?;
+    let sqrt_current = check_sqrt_price(sqrt_price_current_x96, "sqrt_price_current_x96")?;
+
+    let (entry_amount0, entry_amount1) = amounts_for_liquidity(sqrt_entry, sqrt_lower, sqrt_upper, liquidity)?;
+    let (current_amount0, current_amount1) = amounts_for_liquidity(sqrt_current, sqrt_lower, sqrt_upper, liquidity)?;
+
+    let hodl_value = value_in_token1(entry_amount0, entry_amount1, sqrt_current)?;
+    let lp_value = value_in_token1(current_amount0, current_amount1, sqrt_current)?;
+
+    Ok(ConcentratedIL {
+        entry_amount0,
+        entry_amount1,
+        current_amount0,
+        current_amount1,
+        hodl_value,
+        lp_value,
+        impermanent_loss: hodl_value.saturating_sub(lp_value),
+        in_range: sqrt_lower <= sqrt_current && sqrt_current < sqrt_upper,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f64_sqrt_price(tick: i32) -> f64 {
+        1.0001f64.powf(tick as f64 / 2.0) * 2f64.powi(96)
+    }
+
+    fn to_f64(value: U256) -> f64 {
+        value.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn tick_math_bounds() {
+        assert_eq!(sqrt_price_at_tick(0), Ok(Q96));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK), Ok(MIN_SQRT_PRICE));
+        assert_eq!(sqrt_price_at_tick(MAX_TICK), Ok(MAX_SQRT_PRICE));
+        assert_eq!(
+            MAX_SQRT_PRICE.to_string(),
+            "1461446703485210103287273052203988822378723970342"
+        );
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1), Err(out_of_range("tick")));
+    }
+
+    #[test]
+    fn tick_math_matches_float_for_every_bit() {
+        for bit in 0..20 {
+            for tick in [1i32 << bit, -(1i32 << bit)] {
+                let exact = to_f64(sqrt_price_at_tick(tick).unwrap());
+                let expected = f64_sqrt_price(tick);
+                assert!(((exact - expected) / expected).abs() < 1e-9, "tick {}", tick);
+            }
+        }
+    }
+
+    #[test]
+    fn no_loss_without_price_move() {
+        let sqrt_price = sqrt_price_at_tick(100).unwrap();
+        let result = calculate_concentrated_il(U256::from(10u64.pow(18)), -600, 600, sqrt_price, sqrt_price).unwrap();
+        assert!(result.in_range);
+        assert_eq!(result.impermanent_loss, U256::ZERO);
+        assert_eq!(result.hodl_value, result.lp_value);
+    }
+
+    #[test]
+    fn in_range_loss_exceeds_full_range_loss() {
+        // Narrow ranges amplify IL compared to 2 * sqrt(r) / (1 + r) - 1
+        let liquidity = U256::from(10u64.pow(18));
+        let entry = sqrt_price_at_tick(0).unwrap();
+        let current = sqrt_price_at_tick(400).unwrap();
+        let result = calculate_concentrated_il(liquidity, -600, 600, entry, current).unwrap();
+        assert!(result.in_range);
+
+        let loss = to_f64(result.impermanent_loss) / to_f64(result.hodl_value);
+        let ratio = 1.0001f64.powi(400);
+        let full_range_loss = 1.0 - 2.0 * ratio.sqrt() / (1.0 + ratio);
+        assert!(loss > full_range_loss * 5.0);
+    }
+
+    #[test]
+    fn out_of_range_positions_hold_a_single_token() {
+        let liquidity = U256::from(10u64.pow(18));
+        let entry = sqrt_price_at_tick(0).unwrap();
+
+        let above = calculate_concentrated_il(liquidity, -600, 600, entry, sqrt_price_at_tick(1200).unwrap()).unwrap();
+        assert!(!above.in_range);
+        assert_eq!(above.current_amount0, U256::ZERO);
+        assert!(above.impermanent_loss > U256::ZERO);
+
+        let below = calculate_concentrated_il(liquidity, -600, 600, entry, sqrt_price_at_tick(-1200).unwrap()).unwrap();
+        assert!(!below.in_range);
+        assert_eq!(below.current_amount1, U256::ZERO);
+        assert!(below.impermanent_loss > U256::ZERO);
+
+        // Entered and still above the range: nothing was swapped
+        let parked = calculate_concentrated_il(
+            liquidity,
+            -600,
+            600,
+            sqrt_price_at_tick(900).unwrap(),
+            sqrt_price_at_tick(1500).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(parked.impermanent_loss, U256::ZERO);
+    }
+
+    #[test]
+    fn liquidity_is_bounded_by_uint128() {
+        let entry = sqrt_price_at_tick(-1200).unwrap();
+        let current = sqrt_price_at_tick(0).unwrap();
+        let max = U256::from_u128(u128::MAX);
+        let result = calculate_concentrated_il(max, -600, 600, entry, current).unwrap();
+        // Below the range the entry is all token0: L / sqrt_lower - L / sqrt_upper
+        let expected = to_f64(max) * (1.0 / f64_sqrt_price(-600) - 1.0 / f64_sqrt_price(600)) * 2f64.powi(96);
+        assert!((to_f64(result.entry_amount0) - expected).abs() / expected < 1e-9);
+        assert_eq!(
+            calculate_concentrated_il(max + U256::ONE, -600, 600, entry, current),
+            Err(out_of_range("liquidity"))
+        );
+        // 2^160 used to lose its high bits in L << 96 and come out as zero
+        assert_eq!(
+            calculate_concentrated_il(U256::ONE << 160, -600, 600, entry, current),
+            Err(out_of_range("liquidity"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert_eq!(
+            calculate_concentrated_il(U256::ONE, 600, -600, Q96, Q96),
+            Err(out_of_range("tick_upper"))
+        );
+        assert_eq!(
+            calculate_concentrated_il(U256::ONE, -600, 600, U256::ZERO, Q96),
+            Err(out_of_range("sqrt_price_entry_x96"))
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! secp256k1 ECDSA signatures of operator attestations.
+//!
+//! Signatures are 65 bytes, `r || s || v`, as `ethers` produces them. The
+//! AVS node's `ECDSASignatureAggregator` signs with `wallet.signMessage`, so
+//! the default digest is the EIP-191 personal message hash; EIP-712 digests
+//! are supported for signers that use typed data.
+
+use crate::abi::{keccak256, keccak256_encode, Address, Token, B256};
+use crate::u256::U256;
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+
+pub const SIGNATURE_LENGTH: usize = 65;
+pub const PUBLIC_KEY_LENGTH: usize = 33;
+
+/// `keccak256("Attestation(uint256 value)")`, the EIP-712 type of an
+/// attestation value.
+pub fn attestation_typehash() -> B256 {
+    keccak256("Attestation(uint256 value)")
+}
+
+/// How the digest an operator signed is derived from its attestation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Digest {
+    /// `signMessage(keccak256(abi.encode(attestation)))`
+    Eip191,
+    /// `signTypedData` of `Attestation(uint256 value)` under the domain
+    Eip712 { domain_separator: B256 },
+}
+
+impl Digest {
+    /// The digest signed for `attestation`.
+    pub fn of(&self, attestation: U256) -> B256 {
+        match self {
+            Digest::Eip191 => eip191_hash(&keccak256_encode(&[Token::uint(attestation)]).0),
+            Digest::Eip712 { domain_separator } => eip712_hash(
+                *domain_separator,
+                keccak256_encode(&[Token::bytes32(attestation_typehash()), Token::uint(attestation)]),
+            ),
+        }
+    }
+}
+
+/// `keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)`
+pub fn eip191_hash(message: &[u8]) -> B256 {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message);
+    keccak256(data)
+}
+
+/// `keccak256("\x19\x01" || domainSeparator || structHash)`
+pub fn eip712_hash(domain_separator: B256, struct_hash: B256) -> B256 {
+    let mut data = Vec::with_capacity(66);
+    data.extend_from_slice(b"\x19\x01");
+    data.extend_from_slice(&domain_separator.0);
+    data.extend_from_slice(&struct_hash.0);
+    keccak256(data)
+}
+
+/// Recovers the address that signed `digest`. `v` may be 27/28 or 0/1.
+/// Malformed signatures and high-`s` signatures, which EIP-2 makes
+/// non-canonical, recover nothing.
+pub fn recover(digest: B256, signature: &[u8]) -> Option<Address> {
+    let signature: &[u8; SIGNATURE_LENGTH] = signature.try_into().ok()?;
+    let v = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_byte(v)?;
+    let parsed = Signature::from_slice(&signature[..64]).ok()?;
+    if parsed.normalize_s().is_some() {
+        return None;
+    }
+    let key = VerifyingKey::recover_from_prehash(&digest.0, &parsed, recovery_id).ok()?;
+    Some(address_of(&key))
+}
+
+/// Signs `digest` with a 32-byte secret key, returning `r || s || v` with
+/// `v` of 27 or 28. Returns `None` for a key that is zero or not below the
+/// group order.
+pub fn sign(secret_key: &[u8; 32], digest: B256) -> Option<[u8; SIGNATURE_LENGTH]> {
+    let key = SigningKey::from_bytes(secret_key.into()).ok()?;
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest.0).ok()?;
+    let mut bytes = [0u8; SIGNATURE_LENGTH];
+    bytes[..64].copy_from_slice(&signature.to_bytes());
+    bytes[64] = 27 + recovery_id.to_byte();
+    Some(bytes)
+}
+
+/// Address of a 32-byte secret key.
+pub fn derive_address(secret_key: &[u8; 32]) -> Option<Address> {
+    let key = SigningKey::from_bytes(secret_key.into()).ok()?;
+    Some(address_of(key.verifying_key()))
+}
+
+/// Compressed SEC1 public key of a 32-byte secret key.
+pub fn derive_public_key(secret_key: &[u8; 32]) -> Option<[u8; PUBLIC_KEY_LENGTH]> {
+    let key = SigningKey::from_bytes(secret_key.into()).ok()?;
+    key.verifying_key().to_encoded_point(true).as_bytes().try_into().ok()
+}
+
+fn address_of(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    Address::from_word(keccak256(&point.as_bytes()[1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::from_hex;
+
+    fn secret(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[31] = seed;
+        key
+    }
+
+    #[test]
+    fn derives_ethereum_addresses() {
+        // The address of secret key 1
+        assert_eq!(
+            derive_address(&secret(1)).unwrap().to_string(),
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+        assert_eq!(derive_address(&[0; 32]), None);
+    }
+
+    #[test]
+    fn eip191_hash_matches_personal_sign() {
+        // ethers.hashMessage("hello")
+        assert_eq!(
+            eip191_hash(b"hello").to_string(),
+            "0x50b2c43fd39106bafbba0da34fc430e1f91e3c96ea2acee2bc34119f92b37750"
+        );
+    }
+
+    #[test]
+    fn recovers_signers_of_both_digests() {
+        let domain_separator = keccak256("domain");
+        for digest in [Digest::Eip191, Digest::Eip712 { domain_separator }] {
+            let hash = digest.of(U256::from(42));
+            let signature = sign(&secret(7), hash).unwrap();
+            assert_eq!(recover(hash, &signature), derive_address(&secret(7)));
+            assert_ne!(recover(digest.of(U256::from(43)), &signature), derive_address(&secret(7)));
+
+            let mut zero_based = signature;
+            zero_based[64] -= 27;
+            assert_eq!(recover(hash, &zero_based), derive_address(&secret(7)));
+            assert_eq!(recover(hash, &signature[..64]), None);
+        }
+        assert_ne!(Digest::Eip191.of(U256::ONE), Digest::Eip712 { domain_separator }.of(U256::ONE));
+    }
+
+    #[test]
+    fn rejects_high_s_signatures() {
+        let hash = Digest::Eip191.of(U256::from(42));
+        let signature = sign(&secret(7), hash).unwrap();
+        // s' = n - s with the parity of v flipped recovers the same key
+        let order = U256::from_be_bytes(
+            from_hex("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+                .unwrap()
+                .try_into()
+                .unwrap(),
+        );
+        let s = U256::from_be_bytes(signature[32..64].try_into().unwrap());
+        let mut malleated = signature;
+        malleated[32..64].copy_from_slice(&(order - s).to_be_bytes());
+        malleated[64] ^= 1;
+        assert_eq!(recover(hash, &malleated), None);
+    }
+}
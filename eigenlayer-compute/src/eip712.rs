@@ -0,0 +1,255 @@
+//! EIP-712 typed attestations of compute results.
+//!
+//! Operators sign the typed-data digest of an [`ILAttestation`] under a
+//! [`Domain`] bound to the chain and to the contract that verifies it, the
+//! `EigenAVSManager` or the `ConfidentialInsurance` receiver.
+//! `contracts/libraries/ILAttestation.sol` computes the same digest, so
+//! signatures made here verify on-chain with `ecrecover`.
+
+use crate::abi::{keccak256, keccak256_encode, Address, Token, B256};
+use crate::ecdsa;
+use crate::u256::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const DOMAIN_NAME: &str = "ConfidentialInsurance";
+pub const DOMAIN_VERSION: &str = "1";
+
+pub const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+pub const IL_ATTESTATION_TYPE: &str = concat!(
+    "ILAttestation(uint256 policyId,uint256 impermanentLoss,uint256 payout,bytes32 pricesHash,",
+    "uint256 nonce,uint256 chainId,uint256 deadline,bytes32 resultHash)"
+);
+
+fn default_name() -> String {
+    DOMAIN_NAME.to_string()
+}
+
+fn default_version() -> String {
+    DOMAIN_VERSION.to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Domain {
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default = "default_version")]
+    pub version: String,
+    pub chain_id: U256,
+    /// Contract that verifies the signatures
+    pub verifying_contract: Address,
+}
+
+impl Domain {
+    /// The service's domain on `chain_id` for `verifying_contract`.
+    pub fn new(chain_id: U256, verifying_contract: Address) -> Self {
+        Domain {
+            name: default_name(),
+            version: default_version(),
+            chain_id,
+            verifying_contract,
+        }
+    }
+
+    pub fn separator(&self) -> B256 {
+        keccak256_encode(&[
+            Token::bytes32(keccak256(DOMAIN_TYPE)),
+            Token::bytes32(keccak256(&self.name)),
+            Token::bytes32(keccak256(&self.version)),
+            Token::uint(self.chain_id),
+            Token::Address(self.verifying_contract),
+        ])
+    }
+}
+
+/// What an operator attests to for a policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ILAttestation {
+    pub policy_id: U256,
+    pub impermanent_loss: U256,
+    pub payout: U256,
+    /// [`prices_hash`] of the price path the loss was computed from
+    pub prices_hash: B256,
+    /// Per-policy counter, so a signature cannot be replayed for a later
+    /// claim
+    pub nonce: U256,
+    pub chain_id: U256,
+    /// Unix time after which the attestation is no longer valid
+    pub deadline: U256,
+    /// Result hash of the transcript of the computation, which commits to
+    /// its inputs
+    pub result_hash: B256,
+}
+
+/// `keccak256(abi.encode(priceData, timestamps))`
+pub fn prices_hash(price_data: &[U256], timestamps: &[U256]) -> B256 {
+    keccak256_encode(&[Token::uints(price_data), Token::uints(timestamps)])
+}
+
+/// Why a signed attestation is not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationError {
+    /// The signature is malformed or not canonical
+    InvalidSignature,
+    /// The signature is valid but by someone else
+    WrongSigner { recovered: Address },
+    /// The attestation's chain is not the domain's
+    ChainMismatch,
+    /// The deadline has passed
+    Expired,
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::InvalidSignature => f.write_str("invalid signature"),
+            AttestationError::WrongSigner { recovered } => write!(f, "signed by {}", recovered),
+            AttestationError::ChainMismatch => f.write_str("attestation is for another chain"),
+            AttestationError::Expired => f.write_str("attestation has expired"),
+        }
+    }
+}
+
+impl std::error::Error for AttestationError {}
+
+impl ILAttestation {
+    pub fn typehash() -> B256 {
+        keccak256(IL_ATTESTATION_TYPE)
+    }
+
+    pub fn struct_hash(&self) -> B256 {
+        keccak256_encode(&[
+            Token::bytes32(Self::typehash()),
+            Token::uint(self.policy_id),
+            Token::uint(self.impermanent_loss),
+            Token::uint(self.payout),
+            Token::bytes32(self.prices_hash),
+            Token::uint(self.nonce),
+            Token::uint(self.chain_id),
+            Token::uint(self.deadline),
+            Token::bytes32(self.result_hash),
+        ])
+    }
+
+    /// The digest operators sign, with ECDSA or with BLS.
+    pub fn digest(&self, domain: &Domain) -> B256 {
+        ecdsa::eip712_hash(domain.separator(), self.struct_hash())
+    }
+
+    /// 65-byte `r || s || v` signature, as `eth_signTypedData_v4` returns.
+    pub fn sign(&self, domain: &Domain, secret_key: &[u8; 32]) -> Option<[u8; ecdsa::SIGNATURE_LENGTH]> {
+        ecdsa::sign(secret_key, self.digest(domain))
+    }
+
+    pub fn recover(&self, domain: &Domain, signature: &[u8]) -> Option<Address> {
+        ecdsa::recover(self.digest(domain), signature)
+    }
+
+    /// Checks what `ILAttestation.verify` checks on-chain: the chain, the
+    /// deadline at unix time `now`, and that `signer` signed it.
+    pub fn verify(&self, domain: &Domain, signature: &[u8], signer: Address, now: U256) -> Result<(), AttestationError> {
+        if self.chain_id != domain.chain_id {
+            return Err(AttestationError::ChainMismatch);
+        }
+        if now > self.deadline {
+            return Err(AttestationError::Expired);
+        }
+        match self.recover(domain, signature) {
+            Some(recovered) if recovered == signer => Ok(()),
+            Some(recovered) => Err(AttestationError::WrongSigner { recovered }),
+            None => Err(AttestationError::InvalidSignature),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[31] = seed;
+        key
+    }
+
+    // The vector `test/ILAttestation.t.sol` checks on-chain
+    fn vector() -> (Domain, ILAttestation) {
+        let domain = Domain::new(U256::from(31337), Address([0x11; 20]));
+        let attestation = ILAttestation {
+            policy_id: U256::from(7),
+            impermanent_loss: U256::from(250_000_000_000_000_000),
+            payout: U256::from(200_000_000_000_000_000),
+            prices_hash: prices_hash(&[U256::from(1), U256::from(2)], &[U256::from(100), U256::from(200)]),
+            nonce: U256::ONE,
+            chain_id: U256::from(31337),
+            deadline: U256::from(1_700_000_000),
+            result_hash: keccak256("result"),
+        };
+        (domain, attestation)
+    }
+
+    #[test]
+    fn typehashes_match_the_type_strings() {
+        assert_eq!(
+            keccak256(DOMAIN_TYPE).to_string(),
+            "0x8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f"
+        );
+        let (domain, attestation) = vector();
+        assert_eq!(
+            attestation.digest(&domain).to_string(),
+            "0x28caa217ab265223e6ea27ec2d87ae68eb51ff15345598d822d0530e680bca8f"
+        );
+        assert_ne!(domain.separator(), Domain::new(U256::ONE, domain.verifying_contract).separator());
+        assert_ne!(
+            attestation.struct_hash(),
+            ILAttestation {
+                nonce: U256::from(2),
+                ..attestation
+            }
+            .struct_hash()
+        );
+    }
+
+    #[test]
+    fn signatures_verify_against_signer_chain_and_deadline() {
+        let (domain, attestation) = vector();
+        let signer = ecdsa::derive_address(&secret(1)).unwrap();
+        let signature = attestation.sign(&domain, &secret(1)).unwrap();
+        let now = U256::from(1_600_000_000);
+        assert_eq!(attestation.verify(&domain, &signature, signer, now), Ok(()));
+
+        let other = ecdsa::derive_address(&secret(2)).unwrap();
+        assert_eq!(
+            attestation.verify(&domain, &signature, other, now),
+            Err(AttestationError::WrongSigner { recovered: signer })
+        );
+        assert_eq!(
+            attestation.verify(&domain, &signature, signer, attestation.deadline + U256::ONE),
+            Err(AttestationError::Expired)
+        );
+        let elsewhere = Domain::new(U256::ONE, domain.verifying_contract);
+        assert_eq!(
+            attestation.verify(&elsewhere, &signature, signer, now),
+            Err(AttestationError::ChainMismatch)
+        );
+        // Another verifying contract yields another digest
+        let other_contract = Domain::new(domain.chain_id, Address([0x22; 20]));
+        assert_ne!(attestation.recover(&other_contract, &signature), Some(signer));
+        assert_eq!(
+            attestation.verify(&domain, &signature[..64], signer, now),
+            Err(AttestationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn domain_name_and_version_default_when_omitted() {
+        let domain: Domain = serde_json::from_value(serde_json::json!({
+            "chainId": "0x7a69",
+            "verifyingContract": "0x1111111111111111111111111111111111111111",
+        }))
+        .unwrap();
+        assert_eq!(domain, vector().0);
+    }
+}
@@ -0,0 +1,2204 @@
+//! Pure compute engine behind both front ends.
+//!
+//! The JSON-RPC server and the `simple` binary only convert types and map
+//! errors; every rule of the five spec methods and of attestation requests
+//! lives here. Amounts, prices and values are 18-decimal fixed point unless a
+//! request carries token metadata saying otherwise.
+//!
+//! Once its [`OperatorRegistry`] lists operators, the engine only counts
+//! attestations from active registered operators and takes their stakes from
+//! it; with an empty registry the caller's keys and operator set are trusted.
+//! Given an [`OperatorKey`], it signs the attestation responses it computes,
+//! and given an [`EnvelopeKey`] it answers requests encrypted to that key.
+//! With a [`KeyShare`] it takes part in threshold decryption of results
+//! encrypted to its operator set.
+//!
+//! Attestation requests must open an entry commitment: the policy's
+//! on-chain `entryCommit` given a [`PolicyManager`], or else the one they
+//! carry. Requests without either are only accepted by an engine that
+//! [allows uncommitted requests](ComputeEngine::allow_uncommitted_requests).
+//!
+//! [`ComputeEngine::trace`] re-runs a call into a [`Transcript`] of its
+//! inputs, intermediate values and outputs, so operators and challengers can
+//! compare results word by word, and
+//! [`ComputeEngine::challenge_attestation`] turns a signed response that the
+//! transcript contradicts into fraud-proof [`Evidence`].
+
+use crate::abi::{Address, B256};
+use crate::aggregation::{AggregationPolicy, Deviation, Vote};
+use crate::amm::{AmmModel, Deposit, PoolType, Prices};
+use crate::bls;
+use crate::challenge::Evidence;
+use crate::ecdsa;
+use crate::eip712::Domain;
+use crate::envelope::{EncryptedResponse, EncryptionKey, Envelope, EnvelopeError, EnvelopeKey, ResponseOpening};
+use crate::error::ComputeError;
+use crate::groth16::CircuitRegistry;
+use crate::keystore::{OperatorIdentity, OperatorKey};
+use crate::policy::PolicyManager;
+use crate::registry::{EthCall, OperatorRegistry, RegistryError};
+use crate::request::{AttestationRequest, AttestationResponse};
+use crate::threshold::{DecryptionShare, KeyShare, ThresholdCiphertext, ThresholdKey};
+use crate::transcript::{address_word, bytes_word, ComputeCall, SignedTranscript, Transcript};
+use crate::u256::{Bytes, U256};
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+
+/// Operators a signer bitmap can index.
+pub const MAX_OPERATORS: usize = 256;
+
+/// Cloning shares the operator registry, keys and circuits.
+#[derive(Debug, Default, Clone)]
+pub struct ComputeEngine {
+    registry: Arc<RwLock<OperatorRegistry>>,
+    key: Option<Arc<OperatorKey>>,
+    envelope_key: Option<Arc<EnvelopeKey>>,
+    key_share: Option<Arc<KeyShare>>,
+    policy_manager: Option<Arc<PolicyManager>>,
+    circuits: Arc<CircuitRegistry>,
+    domain: Option<Domain>,
+    allow_uncommitted: bool,
+}
+
+/// Result of [`ComputeEngine::aggregate_attestations`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregatedAttestation {
+    /// Consensus of the signed attestations, zero below the threshold
+    pub value: U256,
+    pub meets_threshold: bool,
+    /// Signers too far from the consensus
+    pub deviations: Vec<Deviation>,
+    /// Compressed G2 sum of the valid signatures, empty below the threshold
+    pub aggregate_signature: Bytes,
+    /// Compressed G1 sum of the signers' public keys, empty below the
+    /// threshold
+    pub aggregate_public_key: Bytes,
+    /// Bit `i` is set when operator `i`'s signature verified
+    pub signer_bitmap: U256,
+    /// Result hash of the call's transcript (see [`ComputeEngine::trace`])
+    pub result_hash: B256,
+    /// Operator signature of `result_hash`, as a [`SignedTranscript`] carries
+    /// it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<OperatorIdentity>,
+}
+
+/// Result of [`ComputeEngine::aggregate_ecdsa_attestations`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EcdsaAggregatedAttestation {
+    /// Consensus of the counted attestations, zero below the threshold
+    pub value: U256,
+    pub meets_threshold: bool,
+    /// Signers too far from the consensus
+    pub deviations: Vec<Deviation>,
+    /// Operators whose signature counted toward the threshold, in
+    /// submission order
+    pub signers: Vec<Address>,
+    /// Indices of signatures that were malformed, recovered an address
+    /// outside the operator set, or repeated a signer that already counted
+    pub rejected: Vec<usize>,
+    /// Result hash of the call's transcript (see [`ComputeEngine::trace`])
+    pub result_hash: B256,
+    /// Operator signature of `result_hash`, as a [`SignedTranscript`] carries
+    /// it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<OperatorIdentity>,
+}
+
+impl ComputeEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_registry(registry: OperatorRegistry) -> Self {
+        ComputeEngine {
+            registry: Arc::new(RwLock::new(registry)),
+            ..Self::default()
+        }
+    }
+
+    /// Signs attestation responses with `key`, under the domain set with
+    /// [`Self::with_domain`].
+    pub fn with_key(mut self, key: OperatorKey) -> Self {
+        self.key = Some(Arc::new(key));
+        self
+    }
+
+    /// EIP-712 domain attestation responses are signed under: the chain and
+    /// the contract that verifies them.
+    pub fn with_domain(mut self, domain: Domain) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// Opens encrypted requests with `key`.
+    pub fn with_envelope_key(mut self, key: EnvelopeKey) -> Self {
+        self.envelope_key = Some(Arc::new(key));
+        self
+    }
+
+    /// Decrypts threshold ciphertexts with `share` and the other nodes'
+    /// shares.
+    pub fn with_key_share(mut self, share: KeyShare) -> Self {
+        self.key_share = Some(Arc::new(share));
+        self
+    }
+
+    /// Checks every attestation request against the entry commitment of its
+    /// policy in `policy_manager`.
+    pub fn with_policy_manager(mut self, policy_manager: PolicyManager) -> Self {
+        self.policy_manager = Some(Arc::new(policy_manager));
+        self
+    }
+
+    /// Accepts attestation requests without an entry commitment to check
+    /// when there is no policy manager, for local testing. Such requests
+    /// are otherwise rejected, since their position data is unchecked.
+    pub fn allow_uncommitted_requests(mut self) -> Self {
+        self.allow_uncommitted = true;
+        self
+    }
+
+    /// Verifies encrypted attestations with the circuits' keys.
+    pub fn with_circuits(mut self, circuits: CircuitRegistry) -> Self {
+        self.circuits = Arc::new(circuits);
+        self
+    }
+
+    /// Identity of the operator key responses are signed with.
+    pub fn operator_identity(&self) -> Option<&OperatorIdentity> {
+        self.key.as_deref().map(OperatorKey::identity)
+    }
+
+    /// Key clients encrypt requests to.
+    pub fn encryption_key(&self) -> Option<EncryptionKey> {
+        self.envelope_key.as_deref().map(EnvelopeKey::descriptor)
+    }
+
+    /// Key of the operator set this node holds a share of.
+    pub fn threshold_key(&self) -> Option<&ThresholdKey> {
+        self.key_share.as_deref().map(KeyShare::key)
+    }
+
+    pub fn registry(&self) -> RwLockReadGuard<'_, OperatorRegistry> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Syncs a copy of the registry from `EigenAVSManager` and swaps it in,
+    /// so aggregation is not blocked on the node.
+    pub fn sync_registry(&self, manager: Address, node: &impl EthCall) -> Result<usize, RegistryError> {
+        let mut registry = self.registry().clone();
+        let count = registry.sync(manager, node)?;
+        *self.registry.write().unwrap_or_else(PoisonError::into_inner) = registry;
+        Ok(count)
+    }
+
+    /// Verifies each operator's BLS signature over its attestation,
+    /// aggregates the valid ones and combines the attestations they signed
+    /// under `policy`.
+    ///
+    /// Operator `i` signs [`bls::attestation_message`] of `policy_id` and
+    /// `attestations[i]` with the key in `operator_public_keys[i]`. Missing,
+    /// malformed or invalid signatures are left out of the bitmap and the
+    /// consensus, and so are keys of operators that are not active in the
+    /// registry and repeated keys.
+    ///
+    /// The result carries the result hash of the call's transcript, signed
+    /// with the operator key if there is one.
+    pub fn aggregate_attestations(
+        &self,
+        policy_id: U256,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operator_public_keys: &[Bytes],
+        threshold: U256,
+        policy: &AggregationPolicy,
+    ) -> Result<AggregatedAttestation, ComputeError> {
+        let call = ComputeCall::AggregateAttestations {
+            policy_id,
+            attestations: attestations.to_vec(),
+            signatures: signatures.to_vec(),
+            operator_public_keys: operator_public_keys.to_vec(),
+            threshold,
+            policy: policy.clone(),
+        };
+        let mut transcript = call.transcript();
+        let aggregated = self.record_bls_aggregation(
+            policy_id,
+            attestations,
+            signatures,
+            operator_public_keys,
+            threshold,
+            policy,
+            &mut transcript,
+        )?;
+        let SignedTranscript {
+            transcript,
+            signature,
+            signer,
+        } = SignedTranscript::new(transcript.seal(), self.key.as_deref());
+        Ok(AggregatedAttestation {
+            result_hash: transcript.result_hash,
+            signature,
+            signer,
+            ..aggregated
+        })
+    }
+
+    // Aggregates the BLS attestations, recording the registry and the
+    // results in `transcript`
+    #[allow(clippy::too_many_arguments)]
+    fn record_bls_aggregation(
+        &self,
+        policy_id: U256,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operator_public_keys: &[Bytes],
+        threshold: U256,
+        policy: &AggregationPolicy,
+        transcript: &mut Transcript,
+    ) -> Result<AggregatedAttestation, ComputeError> {
+        self.record_registry(transcript);
+        let aggregated =
+            self.aggregate_bls(policy_id, attestations, signatures, operator_public_keys, threshold, policy)?;
+        transcript.output("value", aggregated.value);
+        transcript.output("meets_threshold", bool_word(aggregated.meets_threshold));
+        transcript.deviations(&aggregated.deviations);
+        transcript.output("aggregate_signature", bytes_word(&aggregated.aggregate_signature));
+        transcript.output("aggregate_public_key", bytes_word(&aggregated.aggregate_public_key));
+        transcript.output("signer_bitmap", aggregated.signer_bitmap);
+        Ok(aggregated)
+    }
+
+    fn aggregate_bls(
+        &self,
+        policy_id: U256,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operator_public_keys: &[Bytes],
+        threshold: U256,
+        policy: &AggregationPolicy,
+    ) -> Result<AggregatedAttestation, ComputeError> {
+        check_lengths("signatures", attestations.len(), signatures.len())?;
+        check_lengths("operator_public_keys", attestations.len(), operator_public_keys.len())?;
+        let registry = self.registry();
+        let policy = registry_stakes(&registry, policy);
+        policy.validate(if registry.is_empty() { operator_public_keys.len() } else { registry.len() })?;
+        if attestations.len() > MAX_OPERATORS {
+            return Err(ComputeError::OutOfRange {
+                parameter: "attestations",
+                index: Some(MAX_OPERATORS),
+            });
+        }
+
+        if U256::from(attestations.len() as u64) < threshold {
+            return Err(ComputeError::InsufficientAttestations {
+                threshold,
+                submitted: attestations.len(),
+            });
+        }
+
+        let mut aggregate = bls::Aggregate::default();
+        let mut signer_bitmap = U256::ZERO;
+        let mut votes = Vec::new();
+
+        for (i, attestation) in attestations.iter().enumerate() {
+            if attestation.is_zero() {
+                continue;
+            }
+            let operator = if registry.is_empty() {
+                // Without a registry an operator is its key, counted at its first index
+                let key = &operator_public_keys[i];
+                operator_public_keys[..i].iter().position(|earlier| earlier == key).unwrap_or(i)
+            } else {
+                match registry.position_by_bls_key(&operator_public_keys[i].0) {
+                    Some(operator) => operator,
+                    None => continue,
+                }
+            };
+            if votes.iter().any(|vote: &Vote| vote.operator == operator) {
+                continue;
+            }
+            let message = bls::attestation_message(policy_id, *attestation);
+            if aggregate.add(&operator_public_keys[i].0, message, &signatures[i].0) {
+                signer_bitmap = signer_bitmap + (U256::ONE << i as u32);
+                votes.push(Vote {
+                    index: i,
+                    operator,
+                    value: *attestation,
+                });
+            }
+        }
+
+        let consensus = policy.aggregate(&votes, threshold)?;
+        if !consensus.meets_threshold || !aggregate.verify() {
+            return Ok(AggregatedAttestation {
+                signer_bitmap,
+                ..AggregatedAttestation::default()
+            });
+        }
+
+        Ok(AggregatedAttestation {
+            value: consensus.value,
+            meets_threshold: true,
+            deviations: consensus.deviations,
+            aggregate_signature: Bytes(aggregate.signature().to_vec()),
+            aggregate_public_key: Bytes(aggregate.public_key().to_vec()),
+            signer_bitmap,
+            ..AggregatedAttestation::default()
+        })
+    }
+
+    /// Recovers the signer of each 65-byte ECDSA signature over its
+    /// attestation and combines the attestations signed by distinct members
+    /// of `operators` under `policy`, whose stakes are indexed like
+    /// `operators`.
+    ///
+    /// With a registry, its active operators are the operator set, narrowed
+    /// to `operators` when given, and the policy's stakes are the registry's.
+    ///
+    /// The first signature of an operator counts; later ones are rejected
+    /// along with unrecoverable signatures and unknown signers. Zero
+    /// attestations are abstentions and are neither counted nor rejected.
+    ///
+    /// The result carries the result hash of the call's transcript, signed
+    /// with the operator key if there is one.
+    pub fn aggregate_ecdsa_attestations(
+        &self,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operators: Option<&[Address]>,
+        threshold: U256,
+        digest: ecdsa::Digest,
+        policy: &AggregationPolicy,
+    ) -> Result<EcdsaAggregatedAttestation, ComputeError> {
+        let call = ComputeCall::AggregateEcdsaAttestations {
+            attestations: attestations.to_vec(),
+            signatures: signatures.to_vec(),
+            operators: operators.map(<[Address]>::to_vec),
+            threshold,
+            domain_separator: match digest {
+                ecdsa::Digest::Eip712 { domain_separator } => Some(domain_separator),
+                ecdsa::Digest::Eip191 => None,
+            },
+            policy: policy.clone(),
+        };
+        let mut transcript = call.transcript();
+        let aggregated = self.record_ecdsa_aggregation(
+            attestations,
+            signatures,
+            operators,
+            threshold,
+            digest,
+            policy,
+            &mut transcript,
+        )?;
+        let SignedTranscript {
+            transcript,
+            signature,
+            signer,
+        } = SignedTranscript::new(transcript.seal(), self.key.as_deref());
+        Ok(EcdsaAggregatedAttestation {
+            result_hash: transcript.result_hash,
+            signature,
+            signer,
+            ..aggregated
+        })
+    }
+
+    // Aggregates the ECDSA attestations, recording the registry and the
+    // results in `transcript`
+    #[allow(clippy::too_many_arguments)]
+    fn record_ecdsa_aggregation(
+        &self,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operators: Option<&[Address]>,
+        threshold: U256,
+        digest: ecdsa::Digest,
+        policy: &AggregationPolicy,
+        transcript: &mut Transcript,
+    ) -> Result<EcdsaAggregatedAttestation, ComputeError> {
+        self.record_registry(transcript);
+        let aggregated = self.aggregate_ecdsa(attestations, signatures, operators, threshold, digest, policy)?;
+        transcript.output("value", aggregated.value);
+        transcript.output("meets_threshold", bool_word(aggregated.meets_threshold));
+        transcript.deviations(&aggregated.deviations);
+        for (i, signer) in aggregated.signers.iter().enumerate() {
+            transcript.output(format!("signers[{}]", i), address_word(*signer));
+        }
+        for (i, index) in aggregated.rejected.iter().enumerate() {
+            transcript.output(format!("rejected[{}]", i), U256::from(*index as u64));
+        }
+        Ok(aggregated)
+    }
+
+    fn aggregate_ecdsa(
+        &self,
+        attestations: &[U256],
+        signatures: &[Bytes],
+        operators: Option<&[Address]>,
+        threshold: U256,
+        digest: ecdsa::Digest,
+        policy: &AggregationPolicy,
+    ) -> Result<EcdsaAggregatedAttestation, ComputeError> {
+        check_lengths("signatures", attestations.len(), signatures.len())?;
+        let registry = self.registry();
+        // Index of the operator that `signer` is in the set, if any
+        let operator_of = |signer: &Address| match (registry.is_empty(), operators) {
+            (true, Some(operators)) => operators.iter().position(|operator| operator == signer),
+            (false, Some(operators)) => registry
+                .position_by_signer(signer)
+                .filter(|i| operators.contains(&registry.operators()[*i].address)),
+            (false, None) => registry.position_by_signer(signer),
+            (true, None) => None,
+        };
+        let operator_count = match operators {
+            _ if !registry.is_empty() => registry.len(),
+            Some(operators) if !operators.is_empty() => operators.len(),
+            _ => return Err(ComputeError::EmptyInput { parameter: "operators" }),
+        };
+        let policy = registry_stakes(&registry, policy);
+        policy.validate(operator_count)?;
+        if attestations.len() > MAX_OPERATORS {
+            return Err(ComputeError::OutOfRange {
+                parameter: "attestations",
+                index: Some(MAX_OPERATORS),
+            });
+        }
+
+        if U256::from(attestations.len() as u64) < threshold {
+            return Err(ComputeError::InsufficientAttestations {
+                threshold,
+                submitted: attestations.len(),
+            });
+        }
+
+        let mut signers = Vec::new();
+        let mut rejected = Vec::new();
+        let mut votes = Vec::new();
+
+        for (i, attestation) in attestations.iter().enumerate() {
+            if attestation.is_zero() {
+                continue;
+            }
+            let signer = ecdsa::recover(digest.of(*attestation), &signatures[i].0);
+            match signer.and_then(|signer| operator_of(&signer).map(|operator| (signer, operator))) {
+                Some((signer, operator)) if !votes.iter().any(|vote: &Vote| vote.operator == operator) => {
+                    signers.push(signer);
+                    votes.push(Vote {
+                        index: i,
+                        operator,
+                        value: *attestation,
+                    });
+                }
+                _ => rejected.push(i),
+            }
+        }
+
+        let consensus = policy.aggregate(&votes, threshold)?;
+        Ok(EcdsaAggregatedAttestation {
+            value: consensus.value,
+            meets_threshold: consensus.meets_threshold,
+            deviations: consensus.deviations,
+            signers,
+            rejected,
+            ..EcdsaAggregatedAttestation::default()
+        })
+    }
+
+    /// Full-range impermanent loss net of fees, with amounts and prices in
+    /// 18 decimals. Fees are `pool_fee_rate` basis points of the entry value.
+    #[allow(clippy::too_many_arguments)]
+    pub fn calculate_impermanent_loss(
+        &self,
+        initial_token_a_amount: U256,
+        initial_token_b_amount: U256,
+        current_token_a_price: U256,
+        current_token_b_price: U256,
+        initial_token_a_price: U256,
+        initial_token_b_price: U256,
+        pool_fee_rate: U256,
+    ) -> Result<(U256, bool), ComputeError> {
+        let valuation = self.full_range_valuation(
+            initial_token_a_amount,
+            initial_token_b_amount,
+            current_token_a_price,
+            current_token_b_price,
+            initial_token_a_price,
+            initial_token_b_price,
+            pool_fee_rate,
+        )?;
+        Ok((valuation.impermanent_loss, !valuation.impermanent_loss.is_zero()))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn full_range_valuation(
+        &self,
+        initial_token_a_amount: U256,
+        initial_token_b_amount: U256,
+        current_token_a_price: U256,
+        current_token_b_price: U256,
+        initial_token_a_price: U256,
+        initial_token_b_price: U256,
+        pool_fee_rate: U256,
+    ) -> Result<Valuation, ComputeError> {
+        for (parameter, price) in [
+            ("initial_token_a_price", initial_token_a_price),
+            ("initial_token_b_price", initial_token_b_price),
+            ("current_token_b_price", current_token_b_price),
+        ] {
+            if price.is_zero() {
+                return Err(ComputeError::ZeroPrice { parameter, index: None });
+            }
+        }
+
+        let deposit = Deposit {
+            amount_a: initial_token_a_amount,
+            amount_b: initial_token_b_amount,
+            entry_prices: Prices {
+                token_a: initial_token_a_price,
+                token_b: initial_token_b_price,
+            },
+        };
+        let current_prices = Prices {
+            token_a: current_token_a_price,
+            token_b: current_token_b_price,
+        };
+        let model = PoolType::ConstantProduct.model(deposit).ok_or(overflow("initial_token_a_amount"))?;
+        let fees_earned =
+            flat_fees(model.as_ref(), &deposit.entry_prices, pool_fee_rate).ok_or(overflow("pool_fee_rate"))?;
+        Valuation::new(model.as_ref(), &deposit, &current_prices, fees_earned)
+    }
+
+    /// Loss above `deductible`, times `coverage_ratio` basis points, capped
+    /// at `coverage_amount`.
+    pub fn calculate_payout(
+        &self,
+        _policy_id: U256,
+        impermanent_loss: U256,
+        coverage_amount: U256,
+        deductible: U256,
+        coverage_ratio: U256,
+    ) -> U256 {
+        Payout::new(impermanent_loss, coverage_amount, deductible, coverage_ratio).payout
+    }
+
+    /// Checks consecutive prices against `deviation_threshold` basis points
+    /// and that timestamps strictly increase. Returns the prices that stayed
+    /// within the threshold.
+    pub fn validate_oracle_prices(
+        &self,
+        price_data: &[U256],
+        timestamps: &[U256],
+        deviation_threshold: U256,
+    ) -> Result<(bool, Vec<U256>), ComputeError> {
+        if price_data.is_empty() {
+            return Err(ComputeError::EmptyInput { parameter: "price_data" });
+        }
+        check_lengths("timestamps", price_data.len(), timestamps.len())?;
+
+        let mut valid_prices = Vec::new();
+        let mut is_valid = true;
+
+        // Check for price deviations
+        for i in 1..price_data.len() {
+            let prev_price = price_data[i - 1];
+            let curr_price = price_data[i];
+
+            if prev_price.is_zero() {
+                return Err(ComputeError::ZeroPrice {
+                    parameter: "price_data",
+                    index: Some(i - 1),
+                });
+            }
+
+            // A change too large to express in basis points exceeds any threshold
+            let change = curr_price.max(prev_price) - curr_price.min(prev_price);
+            let deviation = change.mul_div(U256::from(10000), prev_price).unwrap_or(U256::MAX);
+
+            if deviation > deviation_threshold {
+                is_valid = false;
+            } else {
+                valid_prices.push(curr_price);
+            }
+        }
+
+        // Check timestamp ordering
+        if (1..timestamps.len()).any(|i| timestamps[i] <= timestamps[i - 1]) {
+            is_valid = false;
+        }
+
+        Ok((is_valid, valid_prices))
+    }
+
+    /// Verifies the Groth16 proof `abi.encode(circuitId, a, b, c)` with the
+    /// circuit's registered key and returns the computed value carried in
+    /// the first public input. A proof that does not verify, or whose
+    /// circuit binds another ciphertext, is `(false, 0)`.
+    pub fn verify_encrypted_attestation(
+        &self,
+        encrypted_attestation: &Bytes,
+        proof: &Bytes,
+        public_inputs: &[U256],
+    ) -> Result<(bool, U256), ComputeError> {
+        for (parameter, input) in [("encrypted_attestation", encrypted_attestation), ("proof", proof)] {
+            if input.is_empty() {
+                return Err(ComputeError::EmptyInput { parameter });
+            }
+        }
+        if public_inputs.is_empty() {
+            return Err(ComputeError::EmptyInput { parameter: "public_inputs" });
+        }
+
+        if self.circuits.verify(&encrypted_attestation.0, &proof.0, public_inputs)? {
+            Ok((true, public_inputs[0]))
+        } else {
+            Ok((false, U256::ZERO))
+        }
+    }
+
+    /// Values the position under the request's pool type, nets fees from
+    /// the fee-growth snapshots or the flat rate, and computes the payout in
+    /// the payout token's decimals. The response carries the result hash of
+    /// the request's transcript, and with a key and a domain it is signed as
+    /// an [`ILAttestation`](crate::eip712::ILAttestation).
+    ///
+    /// The position must first open the policy's entry commitment, if there
+    /// is one to check (see [`Self::check_entry_commitment`]).
+    pub fn process_attestation_request(&self, request: &AttestationRequest) -> Result<AttestationResponse, ComputeError> {
+        let call = ComputeCall::ProcessAttestationRequest {
+            request: Box::new(request.clone()),
+        };
+        let mut transcript = call.transcript();
+        let mut response = self.record_request(request, &mut transcript)?;
+        response.result_hash = transcript.seal().result_hash;
+        if let (Some(key), Some(domain)) = (&self.key, &self.domain) {
+            response.sign(key, domain);
+        }
+        Ok(response)
+    }
+
+    // Evaluates `request` into its unsigned response, recording how it was
+    // computed in `transcript`
+    fn record_request(
+        &self,
+        request: &AttestationRequest,
+        transcript: &mut Transcript,
+    ) -> Result<AttestationResponse, ComputeError> {
+        let (response, valuation, payout) = self.evaluate_request(request)?;
+        valuation.record(transcript);
+        payout.record(transcript);
+        transcript.intermediate("payout", payout.payout);
+        transcript.output("policy_id", response.policy_id);
+        transcript.output("impermanent_loss", response.impermanent_loss);
+        transcript.output("has_loss", bool_word(response.has_loss));
+        transcript.output("payout", response.payout);
+        transcript.output("is_valid", bool_word(response.is_valid));
+        Ok(response)
+    }
+
+    // The unsigned response to `request` with the 18-decimal valuation and
+    // payout it was computed from
+    fn evaluate_request(
+        &self,
+        request: &AttestationRequest,
+    ) -> Result<(AttestationResponse, Valuation, Payout), ComputeError> {
+        self.check_entry_commitment(request)?;
+        let normalized = request.normalize().ok_or(overflow("request"))?;
+
+        for (parameter, price) in [
+            ("initial_token_a_price", normalized.deposit.entry_prices.token_a),
+            ("initial_token_b_price", normalized.deposit.entry_prices.token_b),
+            ("current_token_b_price", normalized.current_prices.token_b),
+        ] {
+            if price.is_zero() {
+                return Err(ComputeError::ZeroPrice { parameter, index: None });
+            }
+        }
+
+        let model = request.pool_type.model(normalized.deposit).ok_or(ComputeError::OutOfRange {
+            parameter: "pool_type",
+            index: None,
+        })?;
+
+        let fees_earned = match &request.fee_growth {
+            Some(snapshots) => snapshots
+                .fees_value(&request.token_a, &request.token_b, &normalized.current_prices)
+                .ok_or(overflow("fee_growth"))?,
+            None => flat_fees(model.as_ref(), &normalized.deposit.entry_prices, request.pool_fee_rate)
+                .ok_or(overflow("pool_fee_rate"))?,
+        };
+
+        let valuation = Valuation::new(model.as_ref(), &normalized.deposit, &normalized.current_prices, fees_earned)?;
+        let payout = Payout::new(
+            valuation.impermanent_loss,
+            normalized.coverage_amount,
+            normalized.deductible,
+            request.coverage_ratio,
+        );
+
+        // Report in the payout token's decimals
+        let denormalize = |value| request.payout_token.denormalize(value).ok_or(overflow("payout_token"));
+        let impermanent_loss = denormalize(valuation.impermanent_loss)?;
+        let response = AttestationResponse {
+            policy_id: request.policy_id,
+            impermanent_loss,
+            has_loss: !impermanent_loss.is_zero(),
+            payout: denormalize(payout.payout)?,
+            is_valid: true,
+            prices_hash: request.prices_hash(),
+            nonce: request.nonce,
+            deadline: request.deadline,
+            result_hash: B256::default(),
+            domain: None,
+            signature: None,
+            signer: None,
+        };
+        Ok((response, valuation, payout))
+    }
+
+    /// Checks the request's position data against the entry commitment
+    /// fetched from the policy manager or, without one, the `entry_commit`
+    /// the request carries. With neither the request is rejected, unless
+    /// the engine [allows uncommitted requests](Self::allow_uncommitted_requests).
+    /// A fetched commitment that is zero or differs from a supplied one is
+    /// rejected, and so is a request without a salt to open it.
+    pub fn check_entry_commitment(&self, request: &AttestationRequest) -> Result<(), ComputeError> {
+        let policy_id = request.policy_id;
+        let expected = match &self.policy_manager {
+            Some(policy_manager) => {
+                let fetched = fetch_entry_commitment(policy_manager, policy_id)?;
+                if request.entry_commit.is_some_and(|supplied| supplied != fetched) {
+                    return Err(ComputeError::CommitmentMismatch { policy_id });
+                }
+                fetched
+            }
+            None => match request.entry_commit {
+                Some(supplied) => supplied,
+                None if self.allow_uncommitted => return Ok(()),
+                None => {
+                    return Err(ComputeError::CommitmentUnavailable {
+                        policy_id,
+                        reason: "no policy manager and no entry_commit".to_string(),
+                    })
+                }
+            },
+        };
+        let salt = request.position_salt.ok_or(ComputeError::EmptyInput { parameter: "position_salt" })?;
+        if request.entry_commitment(salt) != expected {
+            return Err(ComputeError::CommitmentMismatch { policy_id });
+        }
+        Ok(())
+    }
+
+    /// Decrypts an attestation request sealed to the engine's envelope key,
+    /// processes it and encrypts the response to the requester. Only the
+    /// commitment to the response, signed when the engine has an operator
+    /// key, is readable by others. Errors in the request are returned in
+    /// the clear, as for [`Self::process_attestation_request`].
+    pub fn process_encrypted_attestation_request(
+        &self,
+        envelope: &Envelope,
+    ) -> Result<EncryptedResponse, ComputeError> {
+        let envelope_key = self.envelope_key.as_deref().ok_or(ComputeError::InvalidEnvelope {
+            reason: "cannot be opened: the server has no encryption key",
+        })?;
+        let (plaintext, reply_key) = envelope_key.open(envelope)?;
+        let request: AttestationRequest = serde_json::from_slice(&plaintext).map_err(|_| EnvelopeError::Payload)?;
+
+        let mut salt = B256::default();
+        OsRng.fill_bytes(&mut salt.0);
+        let opening = ResponseOpening {
+            response: self.process_attestation_request(&request)?,
+            salt,
+        };
+        let commitment = opening.commitment();
+        let plaintext = serde_json::to_vec(&opening).expect("responses serialize to JSON");
+        Ok(EncryptedResponse {
+            ciphertext: reply_key.seal(&plaintext),
+            commitment,
+            signature: self.key.as_deref().map(|key| key.sign(&commitment.0)),
+            signer: self.operator_identity().cloned(),
+        })
+    }
+
+    /// Encrypts a result, such as a payout, to the operator set, so that
+    /// only `threshold` nodes together can decrypt it.
+    pub fn threshold_encrypt(&self, policy_id: U256, value: U256) -> Result<ThresholdCiphertext, ComputeError> {
+        let key = self.threshold_key().ok_or(no_key_share())?;
+        Ok(key.encrypt(policy_id, value, &mut OsRng).expect("key validated by KeyShare::new"))
+    }
+
+    /// This node's partial decryption of `ciphertext`, with a proof that it
+    /// used the node's key share.
+    pub fn partial_decrypt(&self, ciphertext: &ThresholdCiphertext) -> Result<DecryptionShare, ComputeError> {
+        let share = self.key_share.as_deref().ok_or(no_key_share())?;
+        share.decrypt_share(ciphertext, &mut OsRng)
+    }
+
+    /// Aggregates partial decryptions from the operator set's nodes. Shares
+    /// whose proof does not verify against the threshold key are skipped,
+    /// and the first `threshold` valid ones decrypt the value.
+    pub fn combine_decryption_shares(
+        &self,
+        ciphertext: &ThresholdCiphertext,
+        shares: &[DecryptionShare],
+    ) -> Result<U256, ComputeError> {
+        if shares.is_empty() {
+            return Err(ComputeError::EmptyInput { parameter: "shares" });
+        }
+        self.threshold_key().ok_or(no_key_share())?.combine(ciphertext, shares)
+    }
+
+    /// Runs `call` and records it in a canonical transcript: its inputs,
+    /// the intermediate values the result was computed from and the
+    /// outputs, committed to by the result hash, which the operator key
+    /// signs. Any operator or challenger tracing the same call under the
+    /// same engine version and operator registry gets the same hash.
+    pub fn trace(&self, call: &ComputeCall) -> Result<SignedTranscript, ComputeError> {
+        let mut transcript = call.transcript();
+        match call {
+            ComputeCall::CalculateImpermanentLoss {
+                initial_token_a_amount,
+                initial_token_b_amount,
+                current_token_a_price,
+                current_token_b_price,
+                initial_token_a_price,
+                initial_token_b_price,
+                pool_fee_rate,
+            } => {
+                let valuation = self.full_range_valuation(
+                    *initial_token_a_amount,
+                    *initial_token_b_amount,
+                    *current_token_a_price,
+                    *current_token_b_price,
+                    *initial_token_a_price,
+                    *initial_token_b_price,
+                    *pool_fee_rate,
+                )?;
+                valuation.record(&mut transcript);
+                transcript.output("impermanent_loss", valuation.impermanent_loss);
+                transcript.output("has_loss", bool_word(!valuation.impermanent_loss.is_zero()));
+            }
+            ComputeCall::CalculatePayout {
+                impermanent_loss,
+                coverage_amount,
+                deductible,
+                coverage_ratio,
+                ..
+            } => {
+                let payout = Payout::new(*impermanent_loss, *coverage_amount, *deductible, *coverage_ratio);
+                payout.record(&mut transcript);
+                transcript.output("payout", payout.payout);
+            }
+            ComputeCall::ValidateOraclePrices {
+                price_data,
+                timestamps,
+                deviation_threshold,
+            } => {
+                let (is_valid, valid_prices) =
+                    self.validate_oracle_prices(price_data, timestamps, *deviation_threshold)?;
+                transcript.output("is_valid", bool_word(is_valid));
+                for (i, price) in valid_prices.into_iter().enumerate() {
+                    transcript.output(format!("valid_prices[{}]", i), price);
+                }
+            }
+            ComputeCall::AggregateAttestations {
+                policy_id,
+                attestations,
+                signatures,
+                operator_public_keys,
+                threshold,
+                policy,
+            } => {
+                self.record_bls_aggregation(
+                    *policy_id,
+                    attestations,
+                    signatures,
+                    operator_public_keys,
+                    *threshold,
+                    policy,
+                    &mut transcript,
+                )?;
+            }
+            ComputeCall::AggregateEcdsaAttestations {
+                attestations,
+                signatures,
+                operators,
+                threshold,
+                domain_separator,
+                policy,
+            } => {
+                let digest = match domain_separator {
+                    Some(domain_separator) => ecdsa::Digest::Eip712 {
+                        domain_separator: *domain_separator,
+                    },
+                    None => ecdsa::Digest::Eip191,
+                };
+                self.record_ecdsa_aggregation(
+                    attestations,
+                    signatures,
+                    operators.as_deref(),
+                    *threshold,
+                    digest,
+                    policy,
+                    &mut transcript,
+                )?;
+            }
+            ComputeCall::VerifyEncryptedAttestation {
+                encrypted_attestation,
+                proof,
+                public_inputs,
+            } => {
+                let (is_valid, value) = self.verify_encrypted_attestation(encrypted_attestation, proof, public_inputs)?;
+                transcript.output("is_valid", bool_word(is_valid));
+                transcript.output("value", value);
+            }
+            ComputeCall::ProcessAttestationRequest { request } => {
+                self.record_request(request, &mut transcript)?;
+            }
+        }
+        Ok(SignedTranscript::new(transcript.seal(), self.key.as_deref()))
+    }
+
+    /// Re-executes the request behind a disputed signed response and
+    /// returns evidence of every result that differs, or `None` when the
+    /// operator signed what the engine computes.
+    ///
+    /// The response must carry a valid signature by its signer, be for the
+    /// request's policy and be computed from the request's prices. The
+    /// request's position must open the policy's `entryCommit`, which the
+    /// engine reads from its policy manager and records in the evidence's
+    /// request; without a policy manager nothing can be challenged. The
+    /// evidence is only as good as that commitment, so whoever checks it
+    /// passes the on-chain value to [`Evidence::verify`]. Requests the
+    /// engine rejects cannot be challenged.
+    pub fn challenge_attestation(
+        &self,
+        mut request: AttestationRequest,
+        attested: AttestationResponse,
+    ) -> Result<Option<Evidence>, ComputeError> {
+        let policy_id = request.policy_id;
+        let policy_manager = self.policy_manager.as_ref().ok_or_else(|| ComputeError::CommitmentUnavailable {
+            policy_id,
+            reason: "no policy manager to read it from".to_string(),
+        })?;
+        if !attested.verify_signature() {
+            return Err(ComputeError::UnverifiableAttestation {
+                reason: "is not signed by its signer",
+            });
+        }
+        if attested.policy_id != policy_id {
+            return Err(ComputeError::UnverifiableAttestation {
+                reason: "is for another policy than the request",
+            });
+        }
+        if attested.prices_hash != request.prices_hash() {
+            return Err(ComputeError::UnverifiableAttestation {
+                reason: "is for other prices than the request's",
+            });
+        }
+        let entry_commit = fetch_entry_commitment(policy_manager, policy_id)?;
+        if request.entry_commit.is_some_and(|supplied| supplied != entry_commit) {
+            return Err(ComputeError::CommitmentMismatch { policy_id });
+        }
+        request.entry_commit = Some(entry_commit);
+
+        let call = ComputeCall::ProcessAttestationRequest {
+            request: Box::new(request.clone()),
+        };
+        let transcript = self.trace(&call)?.transcript;
+        Ok(Evidence::new(request, transcript, attested))
+    }
+
+    // Aggregations count the registry's operators with its stakes, so the
+    // registry is part of what they were computed from
+    fn record_registry(&self, transcript: &mut Transcript) {
+        let registry = self.registry();
+        for (i, (operator, stake)) in registry.operators().iter().zip(registry.active_stakes()).enumerate() {
+            transcript.intermediate(format!("registry[{}].address", i), address_word(operator.address));
+            transcript.intermediate(format!("registry[{}].stake", i), stake);
+        }
+    }
+}
+
+// Position and holding values at the current prices, in 18 decimals
+#[derive(Debug, Clone, Copy)]
+struct Valuation {
+    hold_value: U256,
+    lp_value: U256,
+    fees_earned: U256,
+    // Token A's price change relative to token B as a WAD, saturating
+    price_ratio: U256,
+    // Shortfall of the position plus fees against holding the deposit
+    impermanent_loss: U256,
+}
+
+impl Valuation {
+    fn new(
+        model: &dyn AmmModel,
+        deposit: &Deposit,
+        current_prices: &Prices,
+        fees_earned: U256,
+    ) -> Result<Self, ComputeError> {
+        let hold_value = model.hodl_value(current_prices).ok_or(overflow("initial_token_a_amount"))?;
+        let lp_value = model.position_value(current_prices).ok_or(overflow("initial_token_a_amount"))?;
+        let total_lp_value = lp_value.checked_add(fees_earned).ok_or(overflow("initial_token_a_amount"))?;
+        Ok(Valuation {
+            hold_value,
+            lp_value,
+            fees_earned,
+            price_ratio: deposit.price_ratio(current_prices).unwrap_or(U256::MAX),
+            impermanent_loss: hold_value.saturating_sub(total_lp_value),
+        })
+    }
+
+    fn record(&self, transcript: &mut Transcript) {
+        transcript.intermediate("price_ratio", self.price_ratio);
+        transcript.intermediate("hold_value", self.hold_value);
+        transcript.intermediate("lp_value", self.lp_value);
+        transcript.intermediate("fees_earned", self.fees_earned);
+        transcript.intermediate("impermanent_loss", self.impermanent_loss);
+    }
+}
+
+// Steps of `calculate_payout`
+#[derive(Debug, Clone, Copy)]
+struct Payout {
+    covered_loss: U256,
+    payout_before_cap: U256,
+    payout: U256,
+}
+
+impl Payout {
+    fn new(impermanent_loss: U256, coverage_amount: U256, deductible: U256, coverage_ratio: U256) -> Self {
+        // Loss above the deductible threshold
+        let covered_loss = impermanent_loss.saturating_sub(deductible);
+
+        // Apply coverage ratio (e.g., 80% coverage). A quotient that does not
+        // fit in 256 bits exceeds any cap below.
+        let payout_before_cap = covered_loss
+            .mul_div(coverage_ratio, U256::from(10000)) // basis points
+            .unwrap_or(U256::MAX);
+
+        // Apply coverage amount cap
+        Payout {
+            covered_loss,
+            payout_before_cap,
+            payout: payout_before_cap.min(coverage_amount),
+        }
+    }
+
+    fn record(&self, transcript: &mut Transcript) {
+        transcript.intermediate("covered_loss", self.covered_loss);
+        transcript.intermediate("payout_before_cap", self.payout_before_cap);
+    }
+}
+
+// Fees approximated as `pool_fee_rate` basis points of the initial value
+fn flat_fees(model: &dyn AmmModel, entry_prices: &Prices, pool_fee_rate: U256) -> Option<U256> {
+    let initial_value = model.hodl_value(entry_prices)?;
+    initial_value.mul_div(pool_fee_rate, U256::from(10000)) // basis points
+}
+
+// The policy with the registry's stakes, indexed like its operators, unless
+// the registry is empty
+fn registry_stakes<'a>(registry: &OperatorRegistry, policy: &'a AggregationPolicy) -> Cow<'a, AggregationPolicy> {
+    if registry.is_empty() {
+        return Cow::Borrowed(policy);
+    }
+    Cow::Owned(AggregationPolicy {
+        stakes: registry.active_stakes(),
+        ..policy.clone()
+    })
+}
+
+// The policy's `entryCommit`, which must be set
+fn fetch_entry_commitment(policy_manager: &PolicyManager, policy_id: U256) -> Result<B256, ComputeError> {
+    let fetched = policy_manager.entry_commitment(policy_id).map_err(|err| {
+        let reason = match err {
+            RegistryError::Node(message) => message,
+            other => other.to_string(),
+        };
+        ComputeError::CommitmentUnavailable { policy_id, reason }
+    })?;
+    if fetched.is_zero() {
+        return Err(ComputeError::CommitmentUnavailable {
+            policy_id,
+            reason: "PolicyManager stores none".to_string(),
+        });
+    }
+    Ok(fetched)
+}
+
+fn bool_word(value: bool) -> U256 {
+    U256::from(value as u64)
+}
+
+fn check_lengths(parameter: &'static str, expected: usize, actual: usize) -> Result<(), ComputeError> {
+    if expected != actual {
+        return Err(ComputeError::LengthMismatch {
+            parameter,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn no_key_share() -> ComputeError {
+    ComputeError::UndecryptableCiphertext {
+        reason: "cannot be processed: the server has no key share",
+    }
+}
+
+fn overflow(parameter: &'static str) -> ComputeError {
+    ComputeError::ArithmeticOverflow { parameter, index: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed_point::WAD;
+    use crate::abi::keccak256;
+    use crate::aggregation::Strategy;
+    use crate::abi::selector;
+    use crate::envelope;
+    use crate::threshold;
+    use crate::keystore::KeyScheme;
+    use crate::registry::{Operator, OperatorStatus};
+    use crate::request::{rescale, FeeGrowthSnapshots, TokenMetadata};
+    use zeroize::Zeroizing;
+
+    #[test]
+    fn whale_positions_use_exact_ratios() {
+        let service = ComputeEngine::new();
+        let whale_loss = U256::ONE << 250;
+        let payout = service.calculate_payout(U256::ONE, whale_loss, U256::MAX, U256::ZERO, U256::from(8000));
+        assert_eq!(payout, whale_loss * U256::from(4) / U256::from(5));
+
+        // MAX * MAX / 10000 does not fit in 256 bits, so the cap applies
+        let payout = service.calculate_payout(U256::ONE, U256::MAX, U256::from(5000), U256::ZERO, U256::MAX);
+        assert_eq!(payout, U256::from(5000));
+
+        let (is_valid, prices) = service
+            .validate_oracle_prices(
+                &[U256::ONE << 250, (U256::ONE << 250) + (U256::ONE << 240)],
+                &[U256::from(1), U256::from(2)],
+                U256::from(10),
+            )
+            .unwrap();
+        assert!(is_valid);
+        assert_eq!(prices.len(), 1);
+    }
+
+    #[test]
+    fn falling_price_is_not_a_total_loss() {
+        let service = ComputeEngine::new();
+        // Token A falls 10% against token B: IL is ~0.14% of the hold value
+        let (impermanent_loss, has_loss) = service
+            .calculate_impermanent_loss(
+                U256::from(1000) * WAD,
+                U256::from(2000) * WAD,
+                U256::from(99) * WAD,
+                U256::from(55) * WAD,
+                U256::from(110) * WAD,
+                U256::from(55) * WAD,
+                U256::ZERO,
+            )
+            .unwrap();
+        let hold_value = U256::from(1000 * 99 + 2000 * 55) * WAD;
+        assert!(has_loss);
+        assert!(impermanent_loss < hold_value / U256::from(500));
+    }
+
+    #[test]
+    fn overflowing_request_is_an_error_instead_of_panicking() {
+        let service = ComputeEngine::new().allow_uncommitted_requests();
+        let request = AttestationRequest {
+            policy_id: U256::ONE,
+            initial_token_a_amount: U256::MAX,
+            initial_token_b_amount: U256::from(2000),
+            current_token_a_price: U256::from(100) * WAD,
+            current_token_b_price: U256::from(50),
+            initial_token_a_price: U256::from(110),
+            initial_token_b_price: U256::from(55),
+            pool_fee_rate: U256::from(30),
+            coverage_amount: U256::from(5000),
+            deductible: U256::from(100),
+            coverage_ratio: U256::from(8000),
+            pool_type: PoolType::ConstantProduct,
+            token_a: TokenMetadata::default(),
+            token_b: TokenMetadata::default(),
+            payout_token: TokenMetadata::default(),
+            price_decimals: 18,
+            fee_growth: None,
+            position_salt: None,
+            entry_commit: None,
+            nonce: U256::ZERO,
+            deadline: U256::ZERO,
+        };
+        assert_eq!(
+            service.process_attestation_request(&request).map(|response| response.is_valid),
+            Err(ComputeError::ArithmeticOverflow {
+                parameter: "initial_token_a_amount",
+                index: None
+            })
+        );
+    }
+
+    // `current_token_a_price` is in thousandths of the quote unit
+    fn pool_request(pool_type: PoolType, current_token_a_price: u64) -> AttestationRequest {
+        let milli = U256::from(1_000_000_000_000_000);
+        AttestationRequest {
+            policy_id: U256::ONE,
+            initial_token_a_amount: U256::from_u128(1_000_000_000_000_000_000_000),
+            initial_token_b_amount: U256::from_u128(1_000_000_000_000_000_000_000),
+            current_token_a_price: U256::from(current_token_a_price) * milli,
+            current_token_b_price: WAD,
+            initial_token_a_price: WAD,
+            initial_token_b_price: WAD,
+            pool_fee_rate: U256::ZERO,
+            coverage_amount: U256::MAX,
+            deductible: U256::ZERO,
+            coverage_ratio: U256::from(10000),
+            pool_type,
+            token_a: TokenMetadata::default(),
+            token_b: TokenMetadata::default(),
+            payout_token: TokenMetadata::default(),
+            price_decimals: 18,
+            fee_growth: None,
+            position_salt: None,
+            entry_commit: None,
+            nonce: U256::ZERO,
+            deadline: U256::ZERO,
+        }
+    }
+
+    fn pool_loss(pool_type: PoolType, current_token_a_price: u64) -> U256 {
+        let response = ComputeEngine::new().allow_uncommitted_requests()
+            .process_attestation_request(&pool_request(pool_type, current_token_a_price))
+            .unwrap();
+        assert!(response.is_valid);
+        response.impermanent_loss
+    }
+
+    #[test]
+    fn weighted_pool_loss_depends_on_weight() {
+        let constant_product = pool_loss(PoolType::ConstantProduct, 250);
+        let even = pool_loss(PoolType::Weighted { weight_a_bps: 5000 }, 250);
+        let heavy = pool_loss(PoolType::Weighted { weight_a_bps: 8000 }, 250);
+        // r = 0.25 costs 20% of the 1.25e21 hold value in an even pool
+        assert_eq!(constant_product, U256::from_u128(250_000_000_000_000_000_000));
+        assert_eq!(even, constant_product);
+        // 0.25^0.8 / 0.4 keeps ~82.5%
+        assert!(heavy < constant_product);
+        assert!(heavy > U256::from_u128(218_000_000_000_000_000_000));
+        assert!(heavy < U256::from_u128(220_000_000_000_000_000_000));
+    }
+
+    #[test]
+    fn stable_swap_concentrates_loss_around_the_peg() {
+        let stable = PoolType::StableSwap { amplification: 100 };
+        assert!(pool_loss(stable, 1000) < U256::from(1_000_000));
+        let depeg_stable = pool_loss(stable, 990);
+        let depeg_constant_product = pool_loss(PoolType::ConstantProduct, 990);
+        // Amplification packs liquidity at the peg, so a 1% depeg swaps far
+        // more of the pool into token A than constant product does
+        assert!(depeg_stable > depeg_constant_product * U256::from(50));
+        assert!(depeg_stable < U256::from_u128(4_000_000_000_000_000_000));
+    }
+
+    #[test]
+    fn stable_swap_matches_constant_product_at_low_amplification() {
+        // A large move pushes even an A = 1 pool towards constant product
+        let stable = pool_loss(PoolType::StableSwap { amplification: 1 }, 250);
+        let constant_product = pool_loss(PoolType::ConstantProduct, 250);
+        assert!(stable > constant_product / U256::from(2));
+    }
+
+    #[test]
+    fn concentrated_liquidity_amplifies_loss() {
+        let range = PoolType::Concentrated {
+            price_lower: WAD / U256::from(2),
+            price_upper: WAD * U256::from(2),
+        };
+        assert!(pool_loss(range, 1000) < U256::from(1_000_000));
+        let in_range = pool_loss(range, 810);
+        assert!(in_range > pool_loss(PoolType::ConstantProduct, 810));
+
+        // Below the range the position is all token A and keeps losing the
+        // token B it sold as token A falls further
+        let below = pool_loss(range, 250);
+        let further_below = pool_loss(range, 200);
+        assert!(below > in_range);
+        assert!(further_below > below);
+    }
+
+    #[test]
+    fn invalid_pool_parameters_are_rejected() {
+        let service = ComputeEngine::new().allow_uncommitted_requests();
+        for pool_type in [
+            PoolType::Weighted { weight_a_bps: 0 },
+            PoolType::Weighted { weight_a_bps: 10000 },
+            PoolType::StableSwap { amplification: 0 },
+            PoolType::Concentrated { price_lower: WAD, price_upper: WAD },
+        ] {
+            assert_eq!(
+                service.process_attestation_request(&pool_request(pool_type, 900)).map(|response| response.is_valid),
+                Err(ComputeError::OutOfRange {
+                    parameter: "pool_type",
+                    index: None
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn pool_type_defaults_to_constant_product() {
+        let request = serde_json::to_value(pool_request(PoolType::ConstantProduct, 1000)).unwrap();
+        let mut object = request.as_object().unwrap().clone();
+        object.remove("pool_type");
+        let request: AttestationRequest = serde_json::from_value(object.into()).unwrap();
+        assert_eq!(request.pool_type, PoolType::ConstantProduct);
+
+        let weighted: PoolType = serde_json::from_str(r#"{"type":"weighted","weightABps":8000}"#).unwrap();
+        assert_eq!(weighted, PoolType::Weighted { weight_a_bps: 8000 });
+    }
+
+    #[test]
+    fn rescale_between_decimals() {
+        assert_eq!(rescale(U256::from(1_500_000), 6, 18), Some(U256::from(1_500_000_000_000_000_000)));
+        assert_eq!(rescale(U256::from(1_999_999_999_999), 18, 6), Some(U256::from(1)));
+        assert_eq!(rescale(U256::from(7), 18, 18), Some(U256::from(7)));
+        assert_eq!(rescale(U256::MAX, 0, 18), None);
+        assert_eq!(rescale(U256::MAX, 255, 0), Some(U256::ZERO));
+    }
+
+    #[test]
+    fn mixed_decimals_are_normalized() {
+        let usdc = TokenMetadata {
+            address: Address::default(),
+            symbol: "USDC".to_string(),
+            decimals: 6,
+        };
+        let weth = TokenMetadata {
+            address: Address::default(),
+            symbol: "WETH".to_string(),
+            decimals: 18,
+        };
+        // 1 WETH and 2,000 USDC at $2,000, then WETH drops to $500
+        let request = AttestationRequest {
+            policy_id: U256::ONE,
+            initial_token_a_amount: WAD,
+            initial_token_b_amount: U256::from(2_000_000_000),
+            current_token_a_price: U256::from(50_000_000_000),
+            current_token_b_price: U256::from(100_000_000),
+            initial_token_a_price: U256::from(200_000_000_000),
+            initial_token_b_price: U256::from(100_000_000),
+            pool_fee_rate: U256::ZERO,
+            coverage_amount: U256::from(1_000_000_000),
+            deductible: U256::from(100_000_000),
+            coverage_ratio: U256::from(10000),
+            pool_type: PoolType::ConstantProduct,
+            token_a: weth,
+            token_b: usdc.clone(),
+            payout_token: usdc,
+            price_decimals: 8,
+            fee_growth: None,
+            position_salt: None,
+            entry_commit: None,
+            nonce: U256::ZERO,
+            deadline: U256::ZERO,
+        };
+        let response = ComputeEngine::new().allow_uncommitted_requests().process_attestation_request(&request).unwrap();
+        // Holding is worth $2,500 and the LP 80% of that: a $500 loss, less
+        // the $100 deductible, in 6-decimal USDC
+        assert!(response.is_valid);
+        assert_eq!(response.impermanent_loss, U256::from(500_000_000));
+        assert_eq!(response.payout, U256::from(400_000_000));
+    }
+
+    #[test]
+    fn fees_owed_follow_position_update() {
+        let q128 = U256::ONE << 128;
+        let snapshots = FeeGrowthSnapshots {
+            liquidity: U256::from(1_000_000),
+            fee_growth_inside0_entry_x128: q128 * U256::from(3),
+            fee_growth_inside1_entry_x128: U256::MAX - q128 + U256::ONE,
+            fee_growth_inside0_exit_x128: q128 * U256::from(5) + q128 / U256::from(2),
+            // Wrapped past zero: one unit of growth since entry
+            fee_growth_inside1_exit_x128: U256::ZERO,
+        };
+        assert_eq!(snapshots.fees_owed(), Some((U256::from(2_500_000), U256::from(1_000_000))));
+    }
+
+    #[test]
+    fn fee_growth_replaces_flat_fee_rate() {
+        // r = 0.25 loses 2.5e20 of value; fees worth 1e20 offset part of it
+        let mut request = pool_request(PoolType::ConstantProduct, 250);
+        request.pool_fee_rate = U256::from(10000);
+        request.fee_growth = Some(FeeGrowthSnapshots {
+            liquidity: U256::from_u128(100_000_000_000_000_000_000),
+            fee_growth_inside0_entry_x128: U256::ZERO,
+            fee_growth_inside1_entry_x128: U256::ZERO,
+            fee_growth_inside0_exit_x128: U256::ZERO,
+            fee_growth_inside1_exit_x128: U256::ONE << 128,
+        });
+        let response = ComputeEngine::new().allow_uncommitted_requests().process_attestation_request(&request).unwrap();
+        assert!(response.is_valid);
+        assert_eq!(response.impermanent_loss, U256::from_u128(150_000_000_000_000_000_000));
+    }
+
+    #[test]
+    fn responses_are_signed_with_the_operator_key() {
+        let request = pool_request(PoolType::ConstantProduct, 250);
+        let unsigned = ComputeEngine::new().allow_uncommitted_requests().process_attestation_request(&request).unwrap();
+        assert_eq!((unsigned.signature.is_some(), unsigned.verify_signature()), (false, false));
+
+        let mut secret = [0u8; 32];
+        secret[31] = 9;
+        let key = |scheme| OperatorKey::new(scheme, Zeroizing::new(secret)).unwrap();
+        // Without a domain there is nothing to sign under
+        let undomained = ComputeEngine::new().allow_uncommitted_requests().with_key(key(KeyScheme::Ecdsa));
+        assert!(undomained.process_attestation_request(&request).unwrap().signature.is_none());
+
+        let domain = test_domain();
+        for scheme in [KeyScheme::Ecdsa, KeyScheme::Bls] {
+            let engine = ComputeEngine::new().allow_uncommitted_requests().with_key(key(scheme)).with_domain(domain.clone());
+            let response = engine.process_attestation_request(&request).unwrap();
+            assert_eq!(response.signer.as_ref(), engine.operator_identity());
+            assert_eq!(response.policy_id, request.policy_id);
+            assert_eq!(response.domain.as_ref(), Some(&domain));
+            assert!(response.verify_signature());
+            if scheme == KeyScheme::Ecdsa {
+                // The signature is what ILAttestation.verify recovers on-chain
+                let signature = &response.signature.as_ref().unwrap().0;
+                let signer = response.signer.as_ref().unwrap().address.unwrap();
+                assert_eq!(response.attestation(&domain).recover(&domain, signature), Some(signer));
+            }
+
+            let tampered: [fn(&mut AttestationResponse); 4] = [
+                |response| response.payout = response.payout + U256::ONE,
+                |response| response.result_hash = B256([1; 32]),
+                |response| response.nonce = response.nonce + U256::ONE,
+                |response| response.has_loss = !response.has_loss,
+            ];
+            for tamper in tampered {
+                let mut response = response.clone();
+                tamper(&mut response);
+                assert!(!response.verify_signature());
+            }
+        }
+    }
+
+    fn test_domain() -> Domain {
+        Domain::new(U256::from(31337), Address([0x11; 20]))
+    }
+
+    #[test]
+    fn wrong_signed_responses_are_challenged_with_evidence() {
+        let operator = || OperatorKey::new(KeyScheme::Ecdsa, Zeroizing::new([9u8; 32])).unwrap();
+        let salt = B256([5; 32]);
+        let request = || {
+            let mut request = pool_request(PoolType::ConstantProduct, 250);
+            request.position_salt = Some(salt);
+            request.entry_commit = Some(request.entry_commitment(salt));
+            request
+        };
+        let honest = ComputeEngine::new()
+            .with_key(operator())
+            .with_domain(test_domain())
+            .process_attestation_request(&request())
+            .unwrap();
+        // Challenges need the on-chain commitment
+        let err = ComputeEngine::new().challenge_attestation(request(), honest.clone()).unwrap_err();
+        assert_eq!(err.name(), "COMMITMENT_UNAVAILABLE");
+        let entry_commit = request().entry_commitment(salt);
+        let challenger =
+            ComputeEngine::new().with_policy_manager(PolicyManager::new(Address::default(), Policies(entry_commit)));
+        assert!(challenger.challenge_attestation(request(), honest.clone()).unwrap().is_none());
+
+        let mut inflated = honest.clone();
+        inflated.payout = inflated.payout + U256::from(1000);
+        inflated.sign(&operator(), &test_domain());
+        let evidence = challenger.challenge_attestation(request(), inflated.clone()).unwrap().unwrap();
+        assert_eq!(
+            evidence.mismatches,
+            [crate::challenge::Mismatch {
+                field: "payout".to_string(),
+                expected: honest.payout,
+                attested: inflated.payout,
+            }]
+        );
+        assert!(evidence.verify(entry_commit));
+        assert_eq!(evidence.request.entry_commit, Some(entry_commit));
+        assert_eq!(evidence.attested.signer.as_ref(), Some(operator().identity()));
+        let encoded = evidence.encode();
+        assert_eq!(&encoded.0[..32], &crate::abi::encode(&[crate::abi::Token::uint(U256::ONE)])[..]);
+
+        let json = serde_json::to_string(&evidence).unwrap();
+        let mut decoded: Evidence = serde_json::from_str(&json).unwrap();
+        assert!(decoded.verify(entry_commit));
+        assert_eq!(decoded.encode(), encoded);
+        decoded.attested.payout = honest.payout;
+        assert!(!decoded.verify(entry_commit));
+        let mut moved = evidence.clone();
+        moved.request.current_token_a_price = moved.request.current_token_a_price + U256::ONE;
+        assert!(!moved.verify(entry_commit));
+
+        // A made-up position opens the commitment it carries, but not the
+        // one on-chain
+        let mut invented = request();
+        invented.initial_token_a_amount = invented.initial_token_a_amount + invented.initial_token_a_amount;
+        invented.entry_commit = Some(invented.entry_commitment(salt));
+        let call = ComputeCall::ProcessAttestationRequest {
+            request: Box::new(invented.clone()),
+        };
+        let transcript = ComputeEngine::new().trace(&call).unwrap().transcript;
+        let invented = Evidence::new(invented, transcript, honest.clone()).unwrap();
+        assert!(invented.verify(invented.request.entry_commit.unwrap()));
+        assert!(!invented.verify(entry_commit));
+
+        let unsigned = AttestationResponse {
+            signature: None,
+            ..inflated.clone()
+        };
+        let err = challenger.challenge_attestation(request(), unsigned).unwrap_err();
+        assert_eq!(err.name(), "UNVERIFIABLE_ATTESTATION");
+        let mut repriced = request();
+        repriced.current_token_a_price = repriced.current_token_a_price + U256::ONE;
+        let err = challenger.challenge_attestation(repriced, inflated.clone()).unwrap_err();
+        assert_eq!(err.name(), "UNVERIFIABLE_ATTESTATION");
+
+        // The on-chain commitment is fetched when the request has none
+        let mut uncommitted = request();
+        uncommitted.entry_commit = None;
+        assert!(challenger.challenge_attestation(uncommitted, inflated.clone()).unwrap().is_some());
+        let mut unsalted = request();
+        unsalted.position_salt = None;
+        let err = challenger.challenge_attestation(unsalted, inflated.clone()).unwrap_err();
+        assert_eq!(err, ComputeError::EmptyInput { parameter: "position_salt" });
+        let mut inflated_entry = request();
+        inflated_entry.initial_token_a_amount = inflated_entry.initial_token_a_amount + U256::ONE;
+        inflated_entry.entry_commit = Some(inflated_entry.entry_commitment(salt));
+        let err = challenger.challenge_attestation(inflated_entry, inflated).unwrap_err();
+        assert_eq!(err, ComputeError::CommitmentMismatch { policy_id: U256::ONE });
+    }
+
+    #[test]
+    fn traces_are_deterministic_and_signed() {
+        let mut secret = [0u8; 32];
+        secret[31] = 9;
+        let engine = ComputeEngine::new()
+            .allow_uncommitted_requests()
+            .with_key(OperatorKey::new(KeyScheme::Ecdsa, Zeroizing::new(secret)).unwrap());
+        let call = || ComputeCall::ProcessAttestationRequest {
+            request: Box::new(pool_request(PoolType::ConstantProduct, 250)),
+        };
+        let traced = engine.trace(&call()).unwrap();
+        let transcript = &traced.transcript;
+        assert_eq!(transcript.method, "compute_processAttestationRequest");
+        assert_eq!(transcript.engine_version, crate::transcript::ENGINE_VERSION);
+        assert!(transcript.verify_hash());
+        assert!(traced.verify_signature());
+
+        // Another operator, or a challenger without a key, gets the same hash
+        let replayed = ComputeEngine::new().allow_uncommitted_requests().trace(&call()).unwrap();
+        assert_eq!(replayed.transcript, traced.transcript);
+        assert!(!replayed.verify_signature());
+
+        let response = engine.process_attestation_request(&pool_request(PoolType::ConstantProduct, 250)).unwrap();
+        assert_eq!(response.result_hash, transcript.result_hash);
+        assert_eq!(transcript.output_value("payout"), Some(response.payout));
+        assert_eq!(transcript.output_value("impermanent_loss"), Some(response.impermanent_loss));
+        let intermediate = |name: &str| transcript.intermediates.iter().find(|word| word.name == name).unwrap().value;
+        assert_eq!(intermediate("price_ratio"), WAD / U256::from(4));
+        assert_eq!(
+            intermediate("hold_value") - intermediate("lp_value") - intermediate("fees_earned"),
+            response.impermanent_loss
+        );
+
+        let mut tampered = traced.clone();
+        tampered.transcript.intermediates[0].value = tampered.transcript.intermediates[0].value + U256::ONE;
+        assert!(!tampered.transcript.verify_hash());
+        assert!(!tampered.verify_signature());
+
+        let json = serde_json::json!({
+            "method": "compute_calculatePayout",
+            "params": {
+                "policy_id": "1",
+                "impermanent_loss": "1000",
+                "coverage_amount": "500",
+                "deductible": "100",
+                "coverage_ratio": "8000",
+            },
+        });
+        let traced = engine.trace(&serde_json::from_value(json).unwrap()).unwrap().transcript;
+        assert_eq!(traced.inputs.len(), 5);
+        assert_eq!(traced.output_value("payout"), Some(U256::from(500)));
+        let intermediate = |name: &str| traced.intermediates.iter().find(|word| word.name == name).unwrap().value;
+        assert_eq!(intermediate("payout_before_cap"), U256::from(720));
+    }
+
+    #[test]
+    fn encrypted_requests_get_committed_encrypted_responses() {
+        let request = pool_request(PoolType::ConstantProduct, 250);
+        let plaintext = serde_json::to_vec(&request).unwrap();
+        let envelope_key = EnvelopeKey::generate();
+        let recipient = envelope_key.public_key();
+        let (envelope, _) = envelope::seal(&recipient, &plaintext).unwrap();
+        assert_eq!(
+            ComputeEngine::new().process_encrypted_attestation_request(&envelope).unwrap_err().code(),
+            -32018
+        );
+
+        let mut secret = [0u8; 32];
+        secret[31] = 9;
+        let engine = ComputeEngine::new()
+            .allow_uncommitted_requests()
+            .with_envelope_key(envelope_key)
+            .with_key(OperatorKey::new(KeyScheme::Ecdsa, Zeroizing::new(secret)).unwrap())
+            .with_domain(test_domain());
+        assert_eq!(engine.encryption_key().unwrap().public_key.0, recipient);
+
+        let (envelope, reply_key) = envelope::seal(&recipient, &plaintext).unwrap();
+        let encrypted = engine.process_encrypted_attestation_request(&envelope).unwrap();
+        assert!(encrypted.verify_signature());
+        let opening = encrypted.open(&reply_key).unwrap();
+        let response = engine.process_attestation_request(&request).unwrap();
+        assert_eq!(
+            (opening.response.impermanent_loss, opening.response.payout),
+            (response.impermanent_loss, response.payout)
+        );
+        assert!(opening.response.verify_signature());
+
+        // A forged commitment is caught when the reply is opened
+        let mut forged = encrypted.clone();
+        forged.commitment.0[0] ^= 1;
+        assert_eq!(forged.open(&reply_key).unwrap_err(), EnvelopeError::CommitmentMismatch);
+        assert!(!forged.verify_signature());
+
+        let (envelope, _) = envelope::seal(&recipient, b"not a request").unwrap();
+        assert_eq!(
+            engine.process_encrypted_attestation_request(&envelope).unwrap_err(),
+            ComputeError::InvalidEnvelope { reason: EnvelopeError::Payload.reason() }
+        );
+    }
+
+    #[test]
+    fn operator_nodes_decrypt_results_together() {
+        // One engine per node, as separate server instances would run
+        let nodes: Vec<ComputeEngine> = threshold::deal(2, 3, &mut OsRng)
+            .unwrap()
+            .into_iter()
+            .map(|share| ComputeEngine::new().with_key_share(share))
+            .collect();
+        let policy_id = U256::from(3);
+        let payout = U256::from(4_000_000_000u64);
+        let ciphertext = nodes[0].threshold_encrypt(policy_id, payout).unwrap();
+        assert_eq!(nodes[1].threshold_key(), nodes[0].threshold_key());
+
+        let shares: Vec<DecryptionShare> =
+            nodes.iter().map(|node| node.partial_decrypt(&ciphertext).unwrap()).collect();
+        assert_eq!(nodes[2].combine_decryption_shares(&ciphertext, &shares[1..]), Ok(payout));
+        assert_eq!(
+            nodes[0].combine_decryption_shares(&ciphertext, &shares[..1]),
+            Err(ComputeError::InsufficientShares { threshold: 2, valid: 1 })
+        );
+        assert_eq!(ComputeEngine::new().partial_decrypt(&ciphertext).unwrap_err().code(), -32019);
+    }
+
+    // PolicyManager storing `self.0` for policy 1 only
+    struct Policies(B256);
+
+    impl EthCall for Policies {
+        fn eth_call(&self, _to: Address, data: &[u8]) -> Result<Vec<u8>, RegistryError> {
+            assert_eq!(data[..4], selector("getPolicyCommitment(uint256)"));
+            let policy_id = U256::from_be_bytes(data[4..].try_into().unwrap());
+            Ok(if policy_id == U256::ONE { self.0 } else { B256::default() }.0.to_vec())
+        }
+    }
+
+    #[test]
+    fn requests_must_open_the_entry_commitment() {
+        let salt = B256([5; 32]);
+        let mut request = pool_request(PoolType::ConstantProduct, 250);
+        request.token_a.address = Address([0xaa; 20]);
+        request.token_b.address = Address([0xbb; 20]);
+        let commitment = request.entry_commitment(salt);
+        // Shared with test/PositionCommitment.t.sol
+        assert_eq!(commitment.to_string(), "0x3dae2820de294d7eb3e1046991e0ee35d7ca3d9ed8be2f15dd5480fae8b4856d");
+
+        let engine = ComputeEngine::new();
+        request.entry_commit = Some(commitment);
+        assert_eq!(
+            engine.process_attestation_request(&request).unwrap_err(),
+            ComputeError::EmptyInput { parameter: "position_salt" }
+        );
+        request.position_salt = Some(salt);
+        assert!(engine.process_attestation_request(&request).is_ok());
+
+        // An LP inflating the entry amount at claim time
+        let policy_id = request.policy_id;
+        request.initial_token_a_amount = request.initial_token_a_amount + U256::ONE;
+        assert_eq!(
+            engine.process_attestation_request(&request).unwrap_err(),
+            ComputeError::CommitmentMismatch { policy_id }
+        );
+
+        // The fetched commitment overrides a supplied one
+        let policy_manager = PolicyManager::new(Address::default(), Policies(commitment));
+        let engine = ComputeEngine::new().with_policy_manager(policy_manager);
+        request.entry_commit = Some(request.entry_commitment(salt));
+        assert_eq!(
+            engine.process_attestation_request(&request).unwrap_err(),
+            ComputeError::CommitmentMismatch { policy_id }
+        );
+        request.entry_commit = None;
+        assert_eq!(
+            engine.process_attestation_request(&request).unwrap_err(),
+            ComputeError::CommitmentMismatch { policy_id }
+        );
+        request.initial_token_a_amount = request.initial_token_a_amount - U256::ONE;
+        assert!(engine.process_attestation_request(&request).is_ok());
+
+        request.policy_id = U256::from(2);
+        assert_eq!(engine.process_attestation_request(&request).unwrap_err().code(), -32022);
+    }
+
+    #[test]
+    fn uncommitted_requests_need_an_explicit_opt_in() {
+        let request = pool_request(PoolType::ConstantProduct, 250);
+        let policy_id = request.policy_id;
+        assert_eq!(
+            ComputeEngine::new().process_attestation_request(&request).unwrap_err(),
+            ComputeError::CommitmentUnavailable {
+                policy_id,
+                reason: "no policy manager and no entry_commit".to_string(),
+            }
+        );
+        let engine = ComputeEngine::new().allow_uncommitted_requests();
+        assert!(engine.process_attestation_request(&request).unwrap().is_valid);
+
+        // The opt-in does not skip a commitment the request carries
+        let mut request = request;
+        request.entry_commit = Some(B256([1; 32]));
+        request.position_salt = Some(B256([5; 32]));
+        assert_eq!(
+            engine.process_attestation_request(&request).unwrap_err(),
+            ComputeError::CommitmentMismatch { policy_id }
+        );
+    }
+
+    #[test]
+    fn aggregation_threshold_uses_full_width() {
+        let service = ComputeEngine::new();
+        assert_eq!(
+            service.aggregate_attestations(
+                U256::ONE,
+                &[U256::from(10)],
+                &[Bytes(vec![1])],
+                &[Bytes(vec![1])],
+                U256::ONE << 64,
+                &AggregationPolicy::default(),
+            ),
+            Err(ComputeError::InsufficientAttestations {
+                threshold: U256::ONE << 64,
+                submitted: 1
+            })
+        );
+        let (signatures, public_keys) = operators(&[U256::MAX, U256::MAX]);
+        assert_eq!(
+            service.aggregate_attestations(
+                U256::ONE,
+                &[U256::MAX, U256::MAX],
+                &signatures,
+                &public_keys,
+                U256::ONE,
+                &AggregationPolicy::default(),
+            ),
+            Err(ComputeError::ArithmeticOverflow {
+                parameter: "attestations",
+                index: Some(1)
+            })
+        );
+    }
+
+    // Signatures of operators with secret keys 1, 2, ... over `attestations` of policy 1
+    fn operators(attestations: &[U256]) -> (Vec<Bytes>, Vec<Bytes>) {
+        attestations
+            .iter()
+            .enumerate()
+            .map(|(i, attestation)| {
+                let mut secret = [0u8; 32];
+                secret[31] = i as u8 + 1;
+                let message = bls::attestation_message(U256::ONE, *attestation);
+                (
+                    Bytes(bls::sign(&secret, &message).unwrap().to_vec()),
+                    Bytes(bls::derive_public_key(&secret).unwrap().to_vec()),
+                )
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn ecdsa_aggregation_counts_distinct_known_signers() {
+        let service = ComputeEngine::new();
+        let secret = |seed: u8| {
+            let mut key = [0u8; 32];
+            key[31] = seed;
+            key
+        };
+        let domain_separator = keccak256("domain");
+        let digest = ecdsa::Digest::Eip712 { domain_separator };
+        let sign = |seed: u8, attestation: u64| {
+            Bytes(ecdsa::sign(&secret(seed), digest.of(U256::from(attestation))).unwrap().to_vec())
+        };
+        let operators: Vec<Address> = [1, 2, 3]
+            .iter()
+            .map(|seed| ecdsa::derive_address(&secret(*seed)).unwrap())
+            .collect();
+
+        // Operator 1 signs twice, a stranger signs once, operator 3 signs
+        // over EIP-191 instead of the typed digest
+        let attestations = [100, 200, 300, 999, 400, 0].map(U256::from);
+        let signatures = [
+            sign(1, 100),
+            sign(2, 200),
+            sign(1, 300),
+            sign(9, 999),
+            Bytes(ecdsa::sign(&secret(3), ecdsa::Digest::Eip191.of(U256::from(400))).unwrap().to_vec()),
+            Bytes(vec![]),
+        ];
+        let result = service
+            .aggregate_ecdsa_attestations(
+                &attestations,
+                &signatures,
+                Some(&operators),
+                U256::from(2),
+                digest,
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert!(result.meets_threshold);
+        assert_eq!(result.value, U256::from(150));
+        assert_eq!(result.signers, operators[..2].to_vec());
+        assert_eq!(result.rejected, vec![2, 3, 4]);
+
+        let result = service
+            .aggregate_ecdsa_attestations(
+                &attestations,
+                &signatures,
+                Some(&operators),
+                U256::from(3),
+                digest,
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert!(!result.meets_threshold);
+        assert_eq!(result.value, U256::ZERO);
+        assert_eq!(result.signers.len(), 2);
+
+        assert_eq!(
+            service.aggregate_ecdsa_attestations(
+                &attestations,
+                &signatures,
+                Some(&[]),
+                U256::ONE,
+                digest,
+                &AggregationPolicy::default(),
+            ),
+            Err(ComputeError::EmptyInput { parameter: "operators" })
+        );
+    }
+
+    #[test]
+    fn aggregation_verifies_bls_signatures() {
+        let service = ComputeEngine::new();
+        let attestations = [U256::from(100), U256::from(100), U256::from(400)];
+        let (mut signatures, public_keys) = operators(&attestations);
+        // Operator 1 signs a different value than it attests
+        signatures[1] = operators(&[U256::from(100), U256::from(999)]).0[1].clone();
+
+        let result = service
+            .aggregate_attestations(
+                U256::ONE,
+                &attestations,
+                &signatures,
+                &public_keys,
+                U256::from(2),
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert!(result.meets_threshold);
+        assert_eq!(result.signer_bitmap, U256::from(0b101));
+        assert_eq!(result.value, U256::from(250));
+        assert_eq!(result.aggregate_signature.0.len(), bls::SIGNATURE_LENGTH);
+
+        // Below the threshold the signers are still reported
+        let result = service
+            .aggregate_attestations(
+                U256::ONE,
+                &attestations,
+                &signatures,
+                &public_keys,
+                U256::from(3),
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert!(!result.meets_threshold);
+        assert_eq!(result.signer_bitmap, U256::from(0b101));
+        assert_eq!(result.value, U256::ZERO);
+        assert!(result.aggregate_signature.is_empty());
+    }
+
+    #[test]
+    fn bls_signatures_count_once_per_key_and_only_for_their_policy() {
+        let service = ComputeEngine::new();
+        let policy = AggregationPolicy::default();
+        let (signatures, public_keys) = operators(&[U256::from(100)]);
+        let attestations = [U256::from(100); 5];
+        let signatures = vec![signatures[0].clone(); 5];
+        let public_keys = vec![public_keys[0].clone(); 5];
+
+        // One key repeated five times is one operator, short of five
+        let result = service
+            .aggregate_attestations(U256::ONE, &attestations, &signatures, &public_keys, U256::from(5), &policy)
+            .unwrap();
+        assert!(!result.meets_threshold);
+        assert_eq!(result.signer_bitmap, U256::ONE);
+
+        // A signature for policy 1 does not attest policy 2
+        let result = service
+            .aggregate_attestations(U256::from(2), &attestations, &signatures, &public_keys, U256::ONE, &policy)
+            .unwrap();
+        assert!(!result.meets_threshold);
+        assert_eq!(result.signer_bitmap, U256::ZERO);
+    }
+
+    #[test]
+    fn aggregations_carry_the_signed_result_hash_of_their_transcript() {
+        let attestations = [U256::from(100); 3];
+        let (signatures, public_keys) = operators(&attestations);
+        let policy = AggregationPolicy::default();
+        let mut secret = [0u8; 32];
+        secret[31] = 9;
+        let engine = ComputeEngine::new().with_key(OperatorKey::new(KeyScheme::Ecdsa, Zeroizing::new(secret)).unwrap());
+        let result = engine
+            .aggregate_attestations(U256::ONE, &attestations, &signatures, &public_keys, U256::from(2), &policy)
+            .unwrap();
+        let traced = engine
+            .trace(&ComputeCall::AggregateAttestations {
+                policy_id: U256::ONE,
+                attestations: attestations.to_vec(),
+                signatures,
+                operator_public_keys: public_keys,
+                threshold: U256::from(2),
+                policy,
+            })
+            .unwrap();
+        assert_eq!(result.result_hash, traced.transcript.result_hash);
+        assert_eq!((&result.signature, &result.signer), (&traced.signature, &traced.signer));
+        assert!(traced.verify_signature());
+    }
+
+    #[test]
+    fn bls_aggregation_applies_the_policy_and_reports_deviations() {
+        let attestations = [100, 101, 99, 5000].map(U256::from);
+        let (signatures, public_keys) = operators(&attestations);
+        let policy = AggregationPolicy {
+            strategy: Strategy::Median,
+            stakes: vec![],
+            deviation_tolerance_bps: 500,
+        };
+        let result = ComputeEngine::new()
+            .aggregate_attestations(U256::ONE, &attestations, &signatures, &public_keys, U256::from(3), &policy)
+            .unwrap();
+        assert!(result.meets_threshold);
+        assert_eq!(result.value, U256::from(100));
+        assert_eq!(result.deviations.len(), 1);
+        assert_eq!(result.deviations[0].index, 3);
+        assert_eq!(result.signer_bitmap, U256::from(0b1111));
+
+        let stake_weighted = AggregationPolicy {
+            strategy: Strategy::StakeWeighted { quorum_bps: 5000 },
+            ..AggregationPolicy::default()
+        };
+        assert_eq!(
+            ComputeEngine::new().aggregate_attestations(
+                U256::ONE,
+                &attestations,
+                &signatures,
+                &public_keys,
+                U256::ONE,
+                &stake_weighted
+            ),
+            Err(ComputeError::LengthMismatch {
+                parameter: "stakes",
+                expected: 4,
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn registry_decides_which_operators_count_and_their_stake() {
+        let attestations = [100, 300, 100, 200].map(U256::from);
+        let (signatures, mut public_keys) = operators(&attestations);
+        // The last slot repeats operator 0's key with its own valid signature
+        public_keys[3] = public_keys[0].clone();
+        let mut signatures = signatures;
+        signatures[3] = operators(&[U256::from(200)]).0[0].clone();
+
+        let registered = |i: usize, stake: u64, status: OperatorStatus| Operator {
+            bls_public_key: public_keys[i].clone(),
+            stake: U256::from(stake),
+            status,
+            ..Operator::new(Address([i as u8 + 1; 20]))
+        };
+        // Operator 1 is slashed, operator 2 is not registered and a third
+        // registered operator does not attest
+        let registry = OperatorRegistry::new(vec![
+            registered(0, 60, OperatorStatus::Active),
+            registered(1, 50, OperatorStatus::Slashed),
+            Operator {
+                stake: U256::from(30),
+                ..Operator::new(Address([9; 20]))
+            },
+        ])
+        .unwrap();
+        let engine = ComputeEngine::with_registry(registry);
+        let policy = AggregationPolicy::default();
+        let result = engine
+            .aggregate_attestations(U256::ONE, &attestations, &signatures, &public_keys, U256::ONE, &policy)
+            .unwrap();
+        assert_eq!(result.signer_bitmap, U256::from(0b1));
+        assert_eq!(result.value, U256::from(100));
+
+        // The request's stakes are replaced by the registry's, where operator
+        // 0 holds less than 70% of the 90 actively staked
+        let stake_weighted = AggregationPolicy {
+            strategy: Strategy::StakeWeighted { quorum_bps: 7000 },
+            stakes: vec![U256::ONE; 4],
+            deviation_tolerance_bps: 0,
+        };
+        let result = engine
+            .aggregate_attestations(U256::ONE, &attestations, &signatures, &public_keys, U256::ONE, &stake_weighted)
+            .unwrap();
+        assert!(!result.meets_threshold);
+
+        // ECDSA signers resolve against the registry without an operator set
+        let secret = |seed: u8| {
+            let mut key = [0u8; 32];
+            key[31] = seed;
+            key
+        };
+        let signer = |seed: u8, status: OperatorStatus| Operator {
+            status,
+            ..Operator::new(ecdsa::derive_address(&secret(seed)).unwrap())
+        };
+        let engine = ComputeEngine::with_registry(
+            OperatorRegistry::new(vec![signer(1, OperatorStatus::Active), signer(2, OperatorStatus::Inactive)])
+                .unwrap(),
+        );
+        let digest = ecdsa::Digest::Eip191;
+        let signatures: Vec<Bytes> = [1, 2]
+            .iter()
+            .map(|seed| Bytes(ecdsa::sign(&secret(*seed), digest.of(U256::from(5))).unwrap().to_vec()))
+            .collect();
+        let result = engine
+            .aggregate_ecdsa_attestations(
+                &[U256::from(5), U256::from(5)],
+                &signatures,
+                None,
+                U256::ONE,
+                digest,
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert_eq!(result.signers, vec![ecdsa::derive_address(&secret(1)).unwrap()]);
+        assert_eq!(result.rejected, vec![1]);
+    }
+
+    #[test]
+    fn same_value_aggregate_verifies_against_the_summed_key() {
+        let attestations = [U256::from(7); 3];
+        let (signatures, public_keys) = operators(&attestations);
+        let result = ComputeEngine::new()
+            .aggregate_attestations(
+                U256::ONE,
+                &attestations,
+                &signatures,
+                &public_keys,
+                U256::from(3),
+                &AggregationPolicy::default(),
+            )
+            .unwrap();
+        assert!(result.meets_threshold);
+        assert!(bls::verify(
+            &result.aggregate_public_key.0,
+            &bls::attestation_message(U256::ONE, U256::from(7)),
+            &result.aggregate_signature.0
+        ));
+    }
+
+    #[test]
+    fn invalid_inputs_name_the_parameter() {
+        let service = ComputeEngine::new().allow_uncommitted_requests();
+        assert_eq!(
+            service.aggregate_attestations(U256::ONE, &[U256::ONE], &[], &[], U256::ONE, &AggregationPolicy::default()),
+            Err(ComputeError::LengthMismatch {
+                parameter: "signatures",
+                expected: 1,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            service.validate_oracle_prices(&[U256::ZERO, WAD], &[U256::ONE, U256::from(2)], U256::MAX),
+            Err(ComputeError::ZeroPrice {
+                parameter: "price_data",
+                index: Some(0)
+            })
+        );
+        assert_eq!(
+            service.verify_encrypted_attestation(&Bytes(vec![1]), &Bytes(vec![]), &[U256::ONE]),
+            Err(ComputeError::EmptyInput { parameter: "proof" })
+        );
+        let mut request = pool_request(PoolType::ConstantProduct, 1000);
+        request.initial_token_b_price = U256::ZERO;
+        assert_eq!(
+            service.process_attestation_request(&request).map(|response| response.is_valid),
+            Err(ComputeError::ZeroPrice {
+                parameter: "initial_token_b_price",
+                index: None
+            })
+        );
+    }
+
+    #[test]
+    fn verification_checks_the_proof_and_returns_the_first_public_input() {
+        let (config, proof, inputs) = crate::groth16::tests::fixture(b"ciphertext");
+        let engine = ComputeEngine::new().with_circuits(CircuitRegistry::from_json(&config.to_string()).unwrap());
+        let (attestation, proof) = (Bytes(b"ciphertext".to_vec()), Bytes(proof));
+        assert_eq!(engine.verify_encrypted_attestation(&attestation, &proof, &inputs), Ok((true, U256::from(42))));
+        assert_eq!(
+            engine.verify_encrypted_attestation(&Bytes(b"forged".to_vec()), &proof, &inputs),
+            Ok((false, U256::ZERO))
+        );
+        // Without a registered key nothing verifies
+        assert!(matches!(
+            ComputeEngine::new().verify_encrypted_attestation(&attestation, &proof, &inputs),
+            Err(ComputeError::UnknownCircuit { .. })
+        ));
+    }
+}
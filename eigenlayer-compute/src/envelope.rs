@@ -0,0 +1,326 @@
+//! Encrypted request envelopes.
+//!
+//! A client seals a request to the operator's X25519 encryption key with
+//! ECIES: it generates an ephemeral key, and both sides run HKDF-SHA-256 over
+//! the Diffie-Hellman secret, salted with the ephemeral and recipient public
+//! keys, to derive one ChaCha20-Poly1305 key for the request and another for
+//! the reply. Each key encrypts a single message, so the nonce is fixed at
+//! zero. Only the requester can read the reply; what the server publishes
+//! next to it is a commitment to the response, which the operator signs.
+
+use crate::abi::{keccak256_encode, Token, B256};
+use crate::error::ComputeError;
+use crate::keystore::{OperatorIdentity, OperatorKey};
+use crate::request::AttestationResponse;
+use crate::u256::Bytes;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2_v010::Sha256;
+use std::fmt;
+use x25519_dalek::{EphemeralSecret, PublicKey, SharedSecret, StaticSecret};
+use zeroize::Zeroizing;
+
+/// Identifies the key agreement, KDF and AEAD in [`EncryptionKey`].
+pub const SCHEME: &str = "x25519-hkdf-sha256-chacha20poly1305";
+
+const OPERATOR_KEY_INFO: &[u8] = b"confidential-insurance/envelope-key";
+const REQUEST_INFO: &[u8] = b"confidential-insurance/envelope/request";
+const REPLY_INFO: &[u8] = b"confidential-insurance/envelope/reply";
+
+/// Public encryption key a server publishes for clients to seal requests to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionKey {
+    pub scheme: String,
+    /// 32-byte X25519 public key
+    pub public_key: Bytes,
+}
+
+/// A request sealed to a server's [`EncryptionKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// 32-byte X25519 public key, fresh for every request
+    pub ephemeral_public_key: Bytes,
+    /// ChaCha20-Poly1305 ciphertext and tag of the request
+    pub ciphertext: Bytes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// A public key is not 32 bytes
+    InvalidPublicKey,
+    /// The key agreement produced the all-zero secret of a low-order point
+    LowOrderPoint,
+    /// The ciphertext was not sealed to this key, or was altered
+    Decryption,
+    /// The plaintext is not the expected JSON document
+    Payload,
+    /// An opened reply does not match the published commitment
+    CommitmentMismatch,
+}
+
+impl EnvelopeError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            EnvelopeError::InvalidPublicKey => "public key is not 32 bytes",
+            EnvelopeError::LowOrderPoint => "public key is a low-order point",
+            EnvelopeError::Decryption => "does not decrypt under the key",
+            EnvelopeError::Payload => "does not hold the expected payload",
+            EnvelopeError::CommitmentMismatch => "reply does not match its commitment",
+        }
+    }
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "envelope {}", self.reason())
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl From<EnvelopeError> for ComputeError {
+    fn from(err: EnvelopeError) -> Self {
+        ComputeError::InvalidEnvelope { reason: err.reason() }
+    }
+}
+
+/// The server's X25519 key pair. The secret is zeroized when dropped.
+pub struct EnvelopeKey {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl fmt::Debug for EnvelopeKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvelopeKey").field("public", &Bytes(self.public.as_bytes().to_vec())).finish()
+    }
+}
+
+impl EnvelopeKey {
+    /// A random key, which lives as long as the process.
+    pub fn generate() -> Self {
+        Self::from_secret(StaticSecret::random())
+    }
+
+    /// The key derived from the operator's keystore, so it survives
+    /// restarts and needs no keystore of its own.
+    pub fn derive(operator: &OperatorKey) -> Self {
+        Self::from_secret(StaticSecret::from(*operator.derive_subkey(OPERATOR_KEY_INFO)))
+    }
+
+    fn from_secret(secret: StaticSecret) -> Self {
+        let public = PublicKey::from(&secret);
+        EnvelopeKey { secret, public }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+
+    /// What the discovery method publishes.
+    pub fn descriptor(&self) -> EncryptionKey {
+        EncryptionKey {
+            scheme: SCHEME.to_string(),
+            public_key: Bytes(self.public_key().to_vec()),
+        }
+    }
+
+    /// Decrypts a request sealed to this key, returning the plaintext and
+    /// the key to encrypt the reply with.
+    pub fn open(&self, envelope: &Envelope) -> Result<(Zeroizing<Vec<u8>>, ReplyKey), EnvelopeError> {
+        let ephemeral = public_key(&envelope.ephemeral_public_key.0)?;
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let (request_key, reply_key) = derive_keys(&shared, &ephemeral, &self.public)?;
+        let plaintext = cipher(&request_key)
+            .decrypt(&Nonce::default(), envelope.ciphertext.0.as_slice())
+            .map_err(|_| EnvelopeError::Decryption)?;
+        Ok((Zeroizing::new(plaintext), reply_key))
+    }
+}
+
+/// Seals `plaintext` to the server key `recipient` and returns the envelope
+/// with the key that opens the server's reply. This is the client side.
+pub fn seal(recipient: &[u8], plaintext: &[u8]) -> Result<(Envelope, ReplyKey), EnvelopeError> {
+    let recipient = public_key(recipient)?;
+    let ephemeral = EphemeralSecret::random();
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    let (request_key, reply_key) = derive_keys(&shared, &ephemeral_public, &recipient)?;
+    let envelope = Envelope {
+        ephemeral_public_key: Bytes(ephemeral_public.as_bytes().to_vec()),
+        ciphertext: Bytes(seal_with(&request_key, plaintext)),
+    };
+    Ok((envelope, reply_key))
+}
+
+/// Key of the reply to one envelope, held by the server while it answers
+/// and by the client that sealed it.
+pub struct ReplyKey(Zeroizing<[u8; 32]>);
+
+impl fmt::Debug for ReplyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReplyKey(..)")
+    }
+}
+
+impl ReplyKey {
+    pub fn seal(&self, plaintext: &[u8]) -> Bytes {
+        Bytes(seal_with(&self.0, plaintext))
+    }
+
+    pub fn open(&self, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, EnvelopeError> {
+        cipher(&self.0)
+            .decrypt(&Nonce::default(), ciphertext)
+            .map(Zeroizing::new)
+            .map_err(|_| EnvelopeError::Decryption)
+    }
+}
+
+/// Reply to an encrypted attestation request. `ciphertext` holds the
+/// [`ResponseOpening`]; everything else is public.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedResponse {
+    pub ciphertext: Bytes,
+    /// [`ResponseOpening::commitment`] of the encrypted response
+    pub commitment: B256,
+    /// Operator signature of `commitment`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Bytes>,
+    /// Key that made `signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<OperatorIdentity>,
+}
+
+impl EncryptedResponse {
+    /// Whether the response carries a valid signature of its commitment.
+    pub fn verify_signature(&self) -> bool {
+        match (&self.signature, &self.signer) {
+            (Some(signature), Some(signer)) => signer.verify(&self.commitment.0, &signature.0),
+            _ => false,
+        }
+    }
+
+    /// Decrypts the response with the requester's reply key and checks it
+    /// against the commitment.
+    pub fn open(&self, reply_key: &ReplyKey) -> Result<ResponseOpening, EnvelopeError> {
+        let plaintext = reply_key.open(&self.ciphertext.0)?;
+        let opening: ResponseOpening = serde_json::from_slice(&plaintext).map_err(|_| EnvelopeError::Payload)?;
+        if opening.commitment() != self.commitment {
+            return Err(EnvelopeError::CommitmentMismatch);
+        }
+        Ok(opening)
+    }
+}
+
+/// The plaintext of an [`EncryptedResponse`]: the response and the salt
+/// that hides it in the commitment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseOpening {
+    pub response: AttestationResponse,
+    pub salt: B256,
+}
+
+impl ResponseOpening {
+    /// `keccak256(abi.encode(resultHash, salt))`, where `resultHash` is the
+    /// response's [`AttestationResponse::result_hash`]. The requester can
+    /// reveal the response and salt to let anyone check it against the
+    /// signed commitment.
+    pub fn commitment(&self) -> B256 {
+        keccak256_encode(&[Token::bytes32(self.response.result_hash), Token::bytes32(self.salt)])
+    }
+}
+
+fn public_key(bytes: &[u8]) -> Result<PublicKey, EnvelopeError> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| EnvelopeError::InvalidPublicKey)?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn derive_keys(
+    shared: &SharedSecret,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<(Zeroizing<[u8; 32]>, ReplyKey), EnvelopeError> {
+    if !shared.was_contributory() {
+        return Err(EnvelopeError::LowOrderPoint);
+    }
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes());
+
+    let mut request_key = Zeroizing::new([0u8; 32]);
+    let mut reply_key = Zeroizing::new([0u8; 32]);
+    for (info, key) in [(REQUEST_INFO, &mut request_key), (REPLY_INFO, &mut reply_key)] {
+        hkdf.expand(info, key.as_mut_slice()).expect("32 bytes is a valid HKDF-SHA-256 length");
+    }
+    Ok((request_key, ReplyKey(reply_key)))
+}
+
+fn cipher(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new_from_slice(key).expect("ChaCha20-Poly1305 keys are 32 bytes")
+}
+
+fn seal_with(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    cipher(key)
+        .encrypt(&Nonce::default(), plaintext)
+        .expect("ChaCha20-Poly1305 encrypts any message that fits in memory")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::KeyScheme;
+
+    #[test]
+    fn requests_and_replies_round_trip() {
+        let server = EnvelopeKey::generate();
+        let (envelope, client_reply_key) = seal(&server.descriptor().public_key.0, b"request").unwrap();
+        assert_eq!(envelope.ephemeral_public_key.0.len(), 32);
+
+        let (plaintext, server_reply_key) = server.open(&envelope).unwrap();
+        assert_eq!(plaintext.as_slice(), b"request");
+        let reply = server_reply_key.seal(b"reply");
+        assert_eq!(client_reply_key.open(&reply.0).unwrap().as_slice(), b"reply");
+
+        // The request key does not open the reply, nor the reply key the request
+        assert_eq!(server_reply_key.open(&envelope.ciphertext.0).unwrap_err(), EnvelopeError::Decryption);
+    }
+
+    #[test]
+    fn rejects_tampered_and_misaddressed_envelopes() {
+        let server = EnvelopeKey::generate();
+        let (envelope, _) = seal(&server.public_key(), b"request").unwrap();
+
+        let mut tampered = envelope.clone();
+        tampered.ciphertext.0[0] ^= 1;
+        assert_eq!(server.open(&tampered).unwrap_err(), EnvelopeError::Decryption);
+
+        let other = EnvelopeKey::generate();
+        assert_eq!(other.open(&envelope).unwrap_err(), EnvelopeError::Decryption);
+
+        let mut low_order = envelope.clone();
+        low_order.ephemeral_public_key = Bytes(vec![0; 32]);
+        assert_eq!(server.open(&low_order).unwrap_err(), EnvelopeError::LowOrderPoint);
+        assert_eq!(seal(&[0; 32], b"request").unwrap_err(), EnvelopeError::LowOrderPoint);
+
+        let mut short = envelope;
+        short.ephemeral_public_key.0.pop();
+        assert_eq!(server.open(&short).unwrap_err(), EnvelopeError::InvalidPublicKey);
+    }
+
+    #[test]
+    fn operator_keys_derive_a_stable_envelope_key() {
+        let mut secret = [0u8; 32];
+        secret[31] = 9;
+        let operator = OperatorKey::new(KeyScheme::Ecdsa, Zeroizing::new(secret)).unwrap();
+        let key = EnvelopeKey::derive(&operator);
+        assert_eq!(key.public_key(), EnvelopeKey::derive(&operator).public_key());
+        assert_ne!(key.public_key(), EnvelopeKey::generate().public_key());
+        assert!(!format!("{:?}", key).contains("secret"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Domain errors of the compute methods.
+//!
+//! Every variant has a stable JSON-RPC error code in the server-defined range
+//! and a `data` payload naming the offending parameter and, for arrays, the
+//! index. The message starts with the error name (`ZERO_PRICE: ...`) because
+//! the EigenCompute relay forwards it verbatim as the `error` string of the
+//! `ConfidentialInsuranceReceiver` callbacks, where contracts can match on
+//! the prefix.
+
+use crate::abi::B256;
+use crate::u256::U256;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComputeError {
+    /// An array does not have as many entries as the one it pairs with
+    LengthMismatch {
+        parameter: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// A required input is empty
+    EmptyInput { parameter: &'static str },
+    /// A price is zero; `index` is set for price arrays
+    ZeroPrice {
+        parameter: &'static str,
+        index: Option<usize>,
+    },
+    /// Fewer attestations were submitted than the threshold requires
+    InsufficientAttestations { threshold: U256, submitted: usize },
+    /// A value derived from this parameter does not fit in 256 bits
+    ArithmeticOverflow {
+        parameter: &'static str,
+        index: Option<usize>,
+    },
+    /// A parameter is outside its valid range
+    OutOfRange {
+        parameter: &'static str,
+        index: Option<usize>,
+    },
+    /// A zk proof is not a valid encoding of a proof
+    MalformedProof { reason: &'static str },
+    /// No verifying key is registered for the proof's circuit
+    UnknownCircuit { circuit_id: B256 },
+    /// An encrypted request cannot be opened with the server's key
+    InvalidEnvelope { reason: &'static str },
+    /// A threshold ciphertext cannot be decrypted
+    UndecryptableCiphertext { reason: &'static str },
+    /// Fewer valid decryption shares than the threshold key requires
+    InsufficientShares { threshold: usize, valid: usize },
+    /// The request's position data does not open the policy's entry
+    /// commitment
+    CommitmentMismatch { policy_id: U256 },
+    /// The policy's entry commitment could not be read, or is not set
+    CommitmentUnavailable { policy_id: U256, reason: String },
+    /// A disputed attestation does not carry its signer's signature, or is
+    /// for another policy or other prices than the request
+    UnverifiableAttestation { reason: &'static str },
+}
+
+/// `data` member of the JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorData {
+    pub parameter: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl ComputeError {
+    /// Stable JSON-RPC error code.
+    pub fn code(&self) -> i32 {
+        match self {
+            ComputeError::LengthMismatch { .. } => -32010,
+            ComputeError::EmptyInput { .. } => -32011,
+            ComputeError::ZeroPrice { .. } => -32012,
+            ComputeError::InsufficientAttestations { .. } => -32013,
+            ComputeError::ArithmeticOverflow { .. } => -32014,
+            ComputeError::OutOfRange { .. } => -32015,
+            ComputeError::MalformedProof { .. } => -32016,
+            ComputeError::UnknownCircuit { .. } => -32017,
+            ComputeError::InvalidEnvelope { .. } => -32018,
+            ComputeError::UndecryptableCiphertext { .. } => -32019,
+            ComputeError::InsufficientShares { .. } => -32020,
+            ComputeError::CommitmentMismatch { .. } => -32021,
+            ComputeError::CommitmentUnavailable { .. } => -32022,
+            ComputeError::UnverifiableAttestation { .. } => -32023,
+        }
+    }
+
+    /// Stable name used as the message prefix.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComputeError::LengthMismatch { .. } => "LENGTH_MISMATCH",
+            ComputeError::EmptyInput { .. } => "EMPTY_INPUT",
+            ComputeError::ZeroPrice { .. } => "ZERO_PRICE",
+            ComputeError::InsufficientAttestations { .. } => "INSUFFICIENT_ATTESTATIONS",
+            ComputeError::ArithmeticOverflow { .. } => "ARITHMETIC_OVERFLOW",
+            ComputeError::OutOfRange { .. } => "OUT_OF_RANGE",
+            ComputeError::MalformedProof { .. } => "MALFORMED_PROOF",
+            ComputeError::UnknownCircuit { .. } => "UNKNOWN_CIRCUIT",
+            ComputeError::InvalidEnvelope { .. } => "INVALID_ENVELOPE",
+            ComputeError::UndecryptableCiphertext { .. } => "UNDECRYPTABLE_CIPHERTEXT",
+            ComputeError::InsufficientShares { .. } => "INSUFFICIENT_SHARES",
+            ComputeError::CommitmentMismatch { .. } => "COMMITMENT_MISMATCH",
+            ComputeError::CommitmentUnavailable { .. } => "COMMITMENT_UNAVAILABLE",
+            ComputeError::UnverifiableAttestation { .. } => "UNVERIFIABLE_ATTESTATION",
+        }
+    }
+
+    pub fn data(&self) -> ErrorData {
+        match *self {
+            ComputeError::LengthMismatch { parameter, .. } | ComputeError::EmptyInput { parameter } => {
+                ErrorData { parameter, index: None }
+            }
+            ComputeError::InsufficientAttestations { .. } => ErrorData {
+                parameter: "attestations",
+                index: None,
+            },
+            ComputeError::MalformedProof { .. } | ComputeError::UnknownCircuit { .. } => ErrorData {
+                parameter: "proof",
+                index: None,
+            },
+            ComputeError::InvalidEnvelope { .. } => ErrorData {
+                parameter: "envelope",
+                index: None,
+            },
+            ComputeError::UndecryptableCiphertext { .. } => ErrorData {
+                parameter: "ciphertext",
+                index: None,
+            },
+            ComputeError::InsufficientShares { .. } => ErrorData {
+                parameter: "shares",
+                index: None,
+            },
+            ComputeError::CommitmentMismatch { .. } => ErrorData {
+                parameter: "position_salt",
+                index: None,
+            },
+            ComputeError::CommitmentUnavailable { .. } => ErrorData {
+                parameter: "policy_id",
+                index: None,
+            },
+            ComputeError::UnverifiableAttestation { .. } => ErrorData {
+                parameter: "attestation",
+                index: None,
+            },
+            ComputeError::ZeroPrice { parameter, index }
+            | ComputeError::ArithmeticOverflow { parameter, index }
+            | ComputeError::OutOfRange { parameter, index } => ErrorData { parameter, index },
+        }
+    }
+}
+
+impl fmt::Display for ComputeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.name())?;
+        match self {
+            ComputeError::LengthMismatch {
+                parameter,
+                expected,
+                actual,
+            } => write!(f, "{} has {} entries, expected {}", parameter, actual, expected),
+            ComputeError::EmptyInput { parameter } => write!(f, "{} is empty", parameter),
+            ComputeError::ZeroPrice { parameter, index: None } => write!(f, "{} is zero", parameter),
+            ComputeError::ZeroPrice {
+                parameter,
+                index: Some(index),
+            } => write!(f, "{}[{}] is zero", parameter, index),
+            ComputeError::InsufficientAttestations { threshold, submitted } => {
+                write!(f, "{} attestations submitted, threshold is {}", submitted, threshold)
+            }
+            ComputeError::ArithmeticOverflow { parameter, index: None } => {
+                write!(f, "value derived from {} overflows 256 bits", parameter)
+            }
+            ComputeError::ArithmeticOverflow {
+                parameter,
+                index: Some(index),
+            } => write!(f, "value derived from {}[{}] overflows 256 bits", parameter, index),
+            ComputeError::OutOfRange { parameter, index: None } => write!(f, "{} is out of range", parameter),
+            ComputeError::OutOfRange {
+                parameter,
+                index: Some(index),
+            } => write!(f, "{}[{}] is out of range", parameter, index),
+            ComputeError::MalformedProof { reason } => write!(f, "proof {}", reason),
+            ComputeError::UnknownCircuit { circuit_id } => write!(f, "no verifying key for circuit {}", circuit_id),
+            ComputeError::InvalidEnvelope { reason } => write!(f, "envelope {}", reason),
+            ComputeError::UndecryptableCiphertext { reason } => write!(f, "ciphertext {}", reason),
+            ComputeError::InsufficientShares { threshold, valid } => {
+                write!(f, "{} valid decryption shares, {} required", valid, threshold)
+            }
+            ComputeError::CommitmentMismatch { policy_id } => {
+                write!(f, "position does not open the entry commitment of policy {}", policy_id)
+            }
+            ComputeError::CommitmentUnavailable { policy_id, reason } => {
+                write!(f, "no entry commitment for policy {}: {}", policy_id, reason)
+            }
+            ComputeError::UnverifiableAttestation { reason } => write!(f, "attestation {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ComputeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_start_with_the_stable_name() {
+        let err = ComputeError::ZeroPrice {
+            parameter: "price_data",
+            index: Some(2),
+        };
+        assert_eq!(err.code(), -32012);
+        assert_eq!(err.to_string(), "ZERO_PRICE: price_data[2] is zero");
+        assert_eq!(
+            serde_json::to_value(err.data()).unwrap(),
+            serde_json::json!({ "parameter": "price_data", "index": 2 })
+        );
+
+        let err = ComputeError::LengthMismatch {
+            parameter: "signatures",
+            expected: 3,
+            actual: 2,
+        };
+        assert_eq!(err.to_string(), "LENGTH_MISMATCH: signatures has 2 entries, expected 3");
+        assert_eq!(
+            serde_json::to_value(err.data()).unwrap(),
+            serde_json::json!({ "parameter": "signatures" })
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::u256::U256;
+
+/// 18-decimal fixed-point unit (`1.0` as a WAD).
+pub const WAD: U256 = U256([1_000_000_000_000_000_000, 0, 0, 0]);
+
+/// Q64.96 unit used by Uniswap's `sqrtPriceX96` (`1 << 96`).
+pub const Q96: U256 = U256([0, 1 << 32, 0, 0]);
+
+/// `10^9`, the square root of `WAD`.
+const HALF_WAD: U256 = U256([1_000_000_000, 0, 0, 0]);
+
+/// `a * b` for two WAD values, rounded down.
+pub fn wad_mul(a: U256, b: U256) -> Option<U256> {
+    a.mul_div(b, WAD)
+}
+
+/// `a / b` for two WAD values, rounded down.
+pub fn wad_div(a: U256, b: U256) -> Option<U256> {
+    a.mul_div(WAD, b)
+}
+
+/// Floor of the square root of `value`.
+pub fn sqrt(value: U256) -> U256 {
+    if value.is_zero() {
+        return U256::ZERO;
+    }
+
+    // Start from a power of two at or above the root so Newton's iteration
+    // decreases monotonically towards the floor.
+    let mut x = U256::ONE << value.bits().div_ceil(2);
+    loop {
+        let y = (x + value / x) >> 1;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Square root of a WAD value, as a WAD.
+pub fn wad_sqrt(value: U256) -> U256 {
+    match value.checked_mul(WAD) {
+        Some(scaled) => sqrt(scaled),
+        // Only reachable for values above ~1.1e59; the root keeps 9 of its
+        // 18 decimals there, which is far below the unit anyway.
+        None => sqrt(value) * HALF_WAD,
+    }
+}
+
+/// Converts a WAD price (token1 per token0) into a Q64.96 `sqrtPriceX96`.
+pub fn wad_to_sqrt_price_x96(price: U256) -> Option<U256> {
+    // sqrt(price / WAD) * 2^96 == sqrt(price * 2^192 / WAD)
+    Some(sqrt(price.mul_div(Q96 * Q96, WAD)?))
+}
+
+/// Converts a Q64.96 `sqrtPriceX96` into a WAD price (token1 per token0).
+pub fn sqrt_price_x96_to_wad(sqrt_price_x96: U256) -> Option<U256> {
+    let price_x96 = sqrt_price_x96.mul_div(sqrt_price_x96, Q96)?;
+    price_x96.mul_div(WAD, Q96)
+}
+
+/// Relative price ratio `r = (pa_now / pb_now) / (pa_0 / pb_0)` as a WAD.
+pub fn price_ratio(
+    initial_token_a_price: U256,
+    initial_token_b_price: U256,
+    current_token_a_price: U256,
+    current_token_b_price: U256,
+) -> Option<U256> {
+    let a_change = current_token_a_price.mul_div(WAD, initial_token_a_price)?;
+    a_change.mul_div(initial_token_b_price, current_token_b_price)
+}
+
+/// Value of a full-range constant-product position relative to holding,
+/// `2 * sqrt(r) / (1 + r)`, as a WAD. Impermanent loss is `1 - multiplier`.
+pub fn lp_value_multiplier(price_ratio: U256) -> Option<U256> {
+    let numerator = wad_sqrt(price_ratio).checked_mul(U256::from(2))?;
+    numerator.mul_div(WAD, WAD.checked_add(price_ratio)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wad(value: u64) -> U256 {
+        U256::from(value) * WAD
+    }
+
+    #[test]
+    fn sqrt_is_exact_floor() {
+        assert_eq!(sqrt(U256::ZERO), U256::ZERO);
+        assert_eq!(sqrt(U256::from(15)), U256::from(3));
+        assert_eq!(sqrt(U256::from(16)), U256::from(4));
+        assert_eq!(sqrt(U256::MAX), U256::from_u128(u128::MAX));
+        assert_eq!(wad_sqrt(wad(4)), wad(2));
+    }
+
+    #[test]
+    fn multiplier_is_one_without_price_change() {
+        assert_eq!(lp_value_multiplier(WAD), Some(WAD));
+    }
+
+    #[test]
+    fn multiplier_below_one_when_price_falls() {
+        // r = 0.25: 2 * 0.5 / 1.25 = 0.8
+        let ratio = WAD / U256::from(4);
+        assert_eq!(lp_value_multiplier(ratio), Some(U256::from(800_000_000_000_000_000u64)));
+        // r = 4 gives the same multiplier
+        assert_eq!(lp_value_multiplier(wad(4)), Some(U256::from(800_000_000_000_000_000u64)));
+    }
+
+    #[test]
+    fn price_ratio_keeps_fractions() {
+        // Token A falls from 110 to 100 against a constant token B
+        let ratio = price_ratio(U256::from(110), U256::from(55), U256::from(100), U256::from(55)).unwrap();
+        assert_eq!(ratio, U256::from(909_090_909_090_909_090u64));
+    }
+
+    #[test]
+    fn sqrt_price_round_trip() {
+        assert_eq!(wad_to_sqrt_price_x96(WAD), Some(Q96));
+        assert_eq!(sqrt_price_x96_to_wad(Q96), Some(WAD));
+        let sqrt_price = wad_to_sqrt_price_x96(wad(4)).unwrap();
+        assert_eq!(sqrt_price, Q96 * U256::from(2));
+        assert_eq!(sqrt_price_x96_to_wad(sqrt_price), Some(wad(4)));
+    }
+}
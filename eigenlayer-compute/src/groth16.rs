@@ -0,0 +1,445 @@
+//! Groth16 zk-SNARK verification over BN254.
+//!
+//! BN254 (alt_bn128) is the curve of the EVM pairing precompiles, so proofs
+//! that verify here also verify in a Solidity verifier. Verifying keys are
+//! registered per circuit id in snarkjs' `verification_key.json` form. A
+//! proof is `abi.encode(bytes32 circuitId, uint256[2] a, uint256[2][2] b,
+//! uint256[2] c)`, with `b`'s coordinates in the precompile's `(c1, c0)`
+//! order, as snarkjs' `exportSolidityCallData` writes them.
+
+use crate::abi::{keccak256, B256};
+use crate::error::ComputeError;
+use crate::u256::U256;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInt, PrimeField};
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// Length of `abi.encode(circuitId, a, b, c)`.
+pub const PROOF_LENGTH: usize = 32 + 64 + 128 + 64;
+
+/// `keccak256(encryptedAttestation)` reduced modulo the BN254 scalar field
+/// order, the public input that binds a proof to its ciphertext.
+pub fn attestation_input(encrypted_attestation: &[u8]) -> U256 {
+    U256(Fr::from_be_bytes_mod_order(&keccak256(encrypted_attestation).0).into_bigint().0)
+}
+
+/// A verifying key as snarkjs exports it: decimal coordinates, G1 points as
+/// `[x, y, "1"]` and G2 points as `[[x.c0, x.c1], [y.c0, y.c1], ["1", "0"]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SnarkjsVerifyingKey {
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub curve: Option<String>,
+    #[serde(rename = "nPublic")]
+    pub n_public: usize,
+    pub vk_alpha_1: Vec<U256>,
+    pub vk_beta_2: Vec<Vec<U256>>,
+    pub vk_gamma_2: Vec<Vec<U256>>,
+    pub vk_delta_2: Vec<Vec<U256>>,
+    #[serde(rename = "IC")]
+    pub ic: Vec<Vec<U256>>,
+}
+
+impl SnarkjsVerifyingKey {
+    pub fn to_verifying_key(&self) -> Result<VerifyingKey<Bn254>, CircuitError> {
+        for (field, value, expected) in [("protocol", &self.protocol, "groth16"), ("curve", &self.curve, "bn128")] {
+            if value.as_deref().is_some_and(|value| value != expected) {
+                return Err(CircuitError::Config(format!("{} must be {}", field, expected)));
+            }
+        }
+        if self.ic.len() != self.n_public + 1 {
+            return Err(CircuitError::Config(format!(
+                "{} IC points for {} public inputs",
+                self.ic.len(),
+                self.n_public
+            )));
+        }
+        let g1 = |name: &str, point: &[U256]| {
+            snarkjs_g1(point).ok_or_else(|| CircuitError::Config(format!("{} is not a BN254 G1 point", name)))
+        };
+        let g2 = |name: &str, point: &[Vec<U256>]| {
+            snarkjs_g2(point).ok_or_else(|| CircuitError::Config(format!("{} is not a BN254 G2 point", name)))
+        };
+        Ok(VerifyingKey {
+            alpha_g1: g1("vk_alpha_1", &self.vk_alpha_1)?,
+            beta_g2: g2("vk_beta_2", &self.vk_beta_2)?,
+            gamma_g2: g2("vk_gamma_2", &self.vk_gamma_2)?,
+            delta_g2: g2("vk_delta_2", &self.vk_delta_2)?,
+            gamma_abc_g1: self.ic.iter().map(|point| g1("IC", point)).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    /// The config file could not be read
+    Io(String),
+    /// The config is not valid JSON or describes an invalid circuit
+    Config(String),
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::Io(message) => write!(f, "cannot read circuit keys: {}", message),
+            CircuitError::Config(message) => write!(f, "invalid circuit keys: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+/// Entry of the circuit config file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitConfig {
+    pub id: B256,
+    pub verifying_key: SnarkjsVerifyingKey,
+    /// Public input that must be [`attestation_input`] of the encrypted
+    /// attestation
+    #[serde(default)]
+    pub attestation_input: Option<usize>,
+}
+
+/// A circuit's prepared verifying key.
+#[derive(Debug, Clone)]
+pub struct Circuit {
+    id: B256,
+    key: PreparedVerifyingKey<Bn254>,
+    attestation_input: Option<usize>,
+}
+
+impl Circuit {
+    pub fn new(id: B256, key: &VerifyingKey<Bn254>, attestation_input: Option<usize>) -> Result<Self, CircuitError> {
+        let public_inputs = key.gamma_abc_g1.len().saturating_sub(1);
+        if attestation_input.is_some_and(|index| index >= public_inputs) {
+            return Err(CircuitError::Config(format!(
+                "attestationInput of circuit {} is not one of its {} public inputs",
+                id, public_inputs
+            )));
+        }
+        Ok(Circuit {
+            id,
+            key: prepare_verifying_key(key),
+            attestation_input,
+        })
+    }
+
+    pub fn from_config(config: &CircuitConfig) -> Result<Self, CircuitError> {
+        Self::new(config.id, &config.verifying_key.to_verifying_key()?, config.attestation_input)
+    }
+
+    pub fn id(&self) -> B256 {
+        self.id
+    }
+
+    pub fn public_input_count(&self) -> usize {
+        self.key.vk.gamma_abc_g1.len() - 1
+    }
+
+    /// Checks `proof` against `public_inputs` and, if the circuit binds one,
+    /// that the attestation input is `encrypted_attestation`'s.
+    pub fn verify(
+        &self,
+        encrypted_attestation: &[u8],
+        proof: &Proof<Bn254>,
+        public_inputs: &[U256],
+    ) -> Result<bool, ComputeError> {
+        if public_inputs.len() != self.public_input_count() {
+            return Err(ComputeError::LengthMismatch {
+                parameter: "public_inputs",
+                expected: self.public_input_count(),
+                actual: public_inputs.len(),
+            });
+        }
+        // Inputs at or above the field order would alias smaller ones
+        let inputs = public_inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                Fr::from_bigint(BigInt::new(input.0)).ok_or(ComputeError::OutOfRange {
+                    parameter: "public_inputs",
+                    index: Some(index),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(index) = self.attestation_input {
+            if public_inputs[index] != attestation_input(encrypted_attestation) {
+                return Ok(false);
+            }
+        }
+        Ok(Groth16::<Bn254>::verify_proof(&self.key, proof, &inputs).unwrap_or(false))
+    }
+}
+
+/// Verifying keys by circuit id.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitRegistry {
+    circuits: Vec<Circuit>,
+}
+
+#[derive(Deserialize)]
+struct CircuitFile {
+    circuits: Vec<CircuitConfig>,
+}
+
+impl CircuitRegistry {
+    /// Builds a registry, rejecting repeated circuit ids.
+    pub fn new(circuits: Vec<Circuit>) -> Result<Self, CircuitError> {
+        for (i, circuit) in circuits.iter().enumerate() {
+            if circuits[..i].iter().any(|other| other.id == circuit.id) {
+                return Err(CircuitError::Config(format!("duplicate circuit {}", circuit.id)));
+            }
+        }
+        Ok(CircuitRegistry { circuits })
+    }
+
+    /// Parses `{ "circuits": [{ "id", "verifyingKey", "attestationInput" }] }`.
+    pub fn from_json(json: &str) -> Result<Self, CircuitError> {
+        let file: CircuitFile = serde_json::from_str(json).map_err(|err| CircuitError::Config(err.to_string()))?;
+        Self::new(file.circuits.iter().map(Circuit::from_config).collect::<Result<_, _>>()?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CircuitError> {
+        let json = std::fs::read_to_string(path).map_err(|err| CircuitError::Io(err.to_string()))?;
+        Self::from_json(&json)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.circuits.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.circuits.len()
+    }
+
+    pub fn get(&self, id: B256) -> Option<&Circuit> {
+        self.circuits.iter().find(|circuit| circuit.id == id)
+    }
+
+    /// Verifies `abi.encode(circuitId, a, b, c)` with the circuit's key.
+    pub fn verify(&self, encrypted_attestation: &[u8], proof: &[u8], public_inputs: &[U256]) -> Result<bool, ComputeError> {
+        let (circuit_id, proof) = decode_proof(proof)?;
+        self.get(circuit_id)
+            .ok_or(ComputeError::UnknownCircuit { circuit_id })?
+            .verify(encrypted_attestation, &proof, public_inputs)
+    }
+}
+
+/// Splits `abi.encode(circuitId, a, b, c)` into the circuit id and a proof
+/// whose points are checked to be on the curve and in the prime-order
+/// subgroup.
+pub fn decode_proof(bytes: &[u8]) -> Result<(B256, Proof<Bn254>), ComputeError> {
+    if bytes.len() != PROOF_LENGTH {
+        return Err(ComputeError::MalformedProof {
+            reason: "is not abi.encode(circuitId, a, b, c)",
+        });
+    }
+    let word = |index: usize| U256::from_be_bytes(bytes[32 * index..32 * (index + 1)].try_into().expect("32-byte word"));
+    let malformed = |reason| ComputeError::MalformedProof { reason };
+    let proof = Proof {
+        a: g1(word(1), word(2)).ok_or(malformed("point a is not in BN254 G1"))?,
+        b: g2([word(4), word(3)], [word(6), word(5)]).ok_or(malformed("point b is not in BN254 G2"))?,
+        c: g1(word(7), word(8)).ok_or(malformed("point c is not in BN254 G1"))?,
+    };
+    Ok((B256(bytes[..32].try_into().expect("32-byte word")), proof))
+}
+
+// Coordinates at or above the field modulus are rejected, not reduced
+fn fq(value: U256) -> Option<Fq> {
+    Fq::from_bigint(BigInt::new(value.0))
+}
+
+fn g1(x: U256, y: U256) -> Option<G1Affine> {
+    let point = G1Affine::new_unchecked(fq(x)?, fq(y)?);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+// `x` and `y` as `[c0, c1]`
+fn g2(x: [U256; 2], y: [U256; 2]) -> Option<G2Affine> {
+    let point = G2Affine::new_unchecked(Fq2::new(fq(x[0])?, fq(x[1])?), Fq2::new(fq(y[0])?, fq(y[1])?));
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn snarkjs_g1(point: &[U256]) -> Option<G1Affine> {
+    match point {
+        [x, y] => g1(*x, *y),
+        [x, y, z] if *z == U256::ONE => g1(*x, *y),
+        _ => None,
+    }
+}
+
+fn snarkjs_g2(point: &[Vec<U256>]) -> Option<G2Affine> {
+    let coordinate = |index: usize| -> Option<[U256; 2]> { point.get(index)?.as_slice().try_into().ok() };
+    match point.len() {
+        2 => g2(coordinate(0)?, coordinate(1)?),
+        3 if coordinate(2)? == [U256::ONE, U256::ZERO] => g2(coordinate(0)?, coordinate(1)?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_snark::SNARK;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+    use serde_json::{json, Value};
+
+    pub(crate) const CIRCUIT_ID: B256 = B256([7; 32]);
+
+    // Knowledge of factors of the first public input; the second is the
+    // attestation input
+    struct Factors {
+        a: Fr,
+        b: Fr,
+        attestation: Fr,
+    }
+
+    impl ConstraintSynthesizer<Fr> for Factors {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let product = cs.new_input_variable(|| Ok(self.a * self.b))?;
+            cs.new_input_variable(|| Ok(self.attestation))?;
+            let a = cs.new_witness_variable(|| Ok(self.a))?;
+            let b = cs.new_witness_variable(|| Ok(self.b))?;
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + product)
+        }
+    }
+
+    fn decimal(value: Fq) -> String {
+        value.into_bigint().to_string()
+    }
+
+    fn word(value: Fq) -> [u8; 32] {
+        U256(value.into_bigint().0).to_be_bytes()
+    }
+
+    fn snarkjs_key(key: &VerifyingKey<Bn254>) -> Value {
+        let g1 = |point: &G1Affine| json!([decimal(point.x), decimal(point.y), "1"]);
+        let g2 = |point: &G2Affine| {
+            json!([
+                [decimal(point.x.c0), decimal(point.x.c1)],
+                [decimal(point.y.c0), decimal(point.y.c1)],
+                ["1", "0"]
+            ])
+        };
+        json!({
+            "protocol": "groth16",
+            "curve": "bn128",
+            "nPublic": key.gamma_abc_g1.len() - 1,
+            "vk_alpha_1": g1(&key.alpha_g1),
+            "vk_beta_2": g2(&key.beta_g2),
+            "vk_gamma_2": g2(&key.gamma_g2),
+            "vk_delta_2": g2(&key.delta_g2),
+            "IC": key.gamma_abc_g1.iter().map(g1).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Circuit config, proof and public inputs of `6 * 7 = 42` for
+    /// `encrypted_attestation`.
+    pub(crate) fn fixture(encrypted_attestation: &[u8]) -> (Value, Vec<u8>, Vec<U256>) {
+        let attestation = attestation_input(encrypted_attestation);
+        let circuit = || Factors {
+            a: Fr::from(6u64),
+            b: Fr::from(7u64),
+            attestation: Fr::from_bigint(BigInt::new(attestation.0)).unwrap(),
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let (proving_key, verifying_key) = Groth16::<Bn254>::circuit_specific_setup(circuit(), &mut rng).unwrap();
+        let proof = Groth16::<Bn254>::prove(&proving_key, circuit(), &mut rng).unwrap();
+
+        let mut bytes = CIRCUIT_ID.0.to_vec();
+        for coordinate in [proof.a.x, proof.a.y, proof.b.x.c1, proof.b.x.c0, proof.b.y.c1, proof.b.y.c0, proof.c.x, proof.c.y] {
+            bytes.extend_from_slice(&word(coordinate));
+        }
+        let config = json!({
+            "circuits": [{ "id": CIRCUIT_ID, "verifyingKey": snarkjs_key(&verifying_key), "attestationInput": 1 }]
+        });
+        (config, bytes, vec![U256::from(42), attestation])
+    }
+
+    #[test]
+    fn verifies_proofs_bound_to_their_attestation() {
+        let (config, proof, inputs) = fixture(b"ciphertext");
+        let registry = CircuitRegistry::from_json(&config.to_string()).unwrap();
+        assert_eq!(registry.get(CIRCUIT_ID).map(Circuit::public_input_count), Some(2));
+        assert_eq!(registry.verify(b"ciphertext", &proof, &inputs), Ok(true));
+
+        // Another statement, or the same proof offered for another ciphertext
+        assert_eq!(registry.verify(b"ciphertext", &proof, &[U256::from(43), inputs[1]]), Ok(false));
+        assert_eq!(
+            registry.verify(b"other", &proof, &[inputs[0], attestation_input(b"other")]),
+            Ok(false)
+        );
+        assert_eq!(registry.verify(b"other", &proof, &inputs), Ok(false));
+    }
+
+    #[test]
+    fn rejects_malformed_proofs_and_inputs() {
+        let (config, proof, inputs) = fixture(b"ciphertext");
+        let registry = CircuitRegistry::from_json(&config.to_string()).unwrap();
+
+        assert!(matches!(
+            registry.verify(b"ciphertext", &proof[..PROOF_LENGTH - 1], &inputs),
+            Err(ComputeError::MalformedProof { .. })
+        ));
+        let mut off_curve = proof.clone();
+        off_curve[63] ^= 1;
+        assert_eq!(
+            registry.verify(b"ciphertext", &off_curve, &inputs),
+            Err(ComputeError::MalformedProof {
+                reason: "point a is not in BN254 G1"
+            })
+        );
+        let mut unknown = proof.clone();
+        unknown[0] = 8;
+        assert!(matches!(
+            registry.verify(b"ciphertext", &unknown, &inputs),
+            Err(ComputeError::UnknownCircuit { .. })
+        ));
+        assert_eq!(
+            registry.verify(b"ciphertext", &proof, &inputs[..1]),
+            Err(ComputeError::LengthMismatch {
+                parameter: "public_inputs",
+                expected: 2,
+                actual: 1
+            })
+        );
+        // The scalar field order aliases zero
+        let order = U256(Fr::MODULUS.0);
+        assert_eq!(
+            registry.verify(b"ciphertext", &proof, &[order, inputs[1]]),
+            Err(ComputeError::OutOfRange {
+                parameter: "public_inputs",
+                index: Some(0)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_circuit_configs() {
+        let (config, _, _) = fixture(b"ciphertext");
+        let circuit = &config["circuits"][0];
+
+        let duplicated = json!({ "circuits": [circuit, circuit] });
+        assert!(CircuitRegistry::from_json(&duplicated.to_string()).is_err());
+        let mut unbound = circuit.clone();
+        unbound["attestationInput"] = json!(2);
+        assert!(CircuitRegistry::from_json(&json!({ "circuits": [unbound] }).to_string()).is_err());
+        let mut miscounted = circuit.clone();
+        miscounted["verifyingKey"]["nPublic"] = json!(3);
+        assert!(CircuitRegistry::from_json(&json!({ "circuits": [miscounted] }).to_string()).is_err());
+        let mut off_curve = circuit.clone();
+        off_curve["verifyingKey"]["vk_alpha_1"] = json!(["1", "3", "1"]);
+        assert_eq!(
+            CircuitRegistry::from_json(&json!({ "circuits": [off_curve] }).to_string()).unwrap_err(),
+            CircuitError::Config("vk_alpha_1 is not a BN254 G1 point".to_string())
+        );
+    }
+}
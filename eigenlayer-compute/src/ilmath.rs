@@ -0,0 +1,281 @@
+//! Bit-exact port of `contracts/libraries/ILMath.sol`.
+//!
+//! Every function evaluates the same expressions in the same order as the
+//! Solidity library, and each revert maps to an [`ILMathError`] whose
+//! [`revert_data`](ILMathError::revert_data) matches what the EVM returns.
+
+use crate::abi::{self, selector, Token};
+use crate::u256::U256;
+use std::fmt;
+
+/// `ILMath.BASIS_POINTS`
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// `ILMath.MAX_BASIS_POINTS`
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+/// Selector of Solidity's built-in `Panic(uint256)` error.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Panic code raised by checked arithmetic that over- or underflows.
+const PANIC_ARITHMETIC_OVERFLOW: u8 = 0x11;
+
+/// Reverts raised by `ILMath`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ILMathError {
+    /// `error InvalidPrice()`
+    InvalidPrice,
+    /// `error InvalidBasisPoints()`
+    InvalidBasisPoints,
+    /// `error CalculationOverflow()`
+    CalculationOverflow,
+    /// `Panic(0x11)` from Solidity's checked arithmetic on an expression the
+    /// library does not guard explicitly.
+    ArithmeticOverflow,
+}
+
+impl ILMathError {
+    /// Solidity error signature.
+    pub fn signature(&self) -> &'static str {
+        match self {
+            ILMathError::InvalidPrice => "InvalidPrice()",
+            ILMathError::InvalidBasisPoints => "InvalidBasisPoints()",
+            ILMathError::CalculationOverflow => "CalculationOverflow()",
+            ILMathError::ArithmeticOverflow => "Panic(uint256)",
+        }
+    }
+
+    /// ABI-encoded revert data as returned by `eth_call`.
+    pub fn revert_data(&self) -> Vec<u8> {
+        match self {
+            ILMathError::ArithmeticOverflow => {
+                let mut data = PANIC_SELECTOR.to_vec();
+                data.extend(abi::encode(&[Token::uint(U256::from(PANIC_ARITHMETIC_OVERFLOW as u64))]));
+                data
+            }
+            _ => selector(self.signature()).to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for ILMathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ILMathError::ArithmeticOverflow => write!(f, "Panic(0x11): arithmetic overflow"),
+            _ => f.write_str(self.signature()),
+        }
+    }
+}
+
+impl std::error::Error for ILMathError {}
+
+/// Result of [`calculate_full_il`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullIL {
+    pub hodl_value: U256,
+    pub lp_value: U256,
+    pub impermanent_loss: U256,
+    pub payout: U256,
+}
+
+/// `ILMath.calculateHodlValue`: `V_hodl = x0 * P1 + y0`
+pub fn calculate_hodl_value(x0: U256, y0: U256, p1: U256) -> Result<U256, ILMathError> {
+    if p1.is_zero() {
+        return Err(ILMathError::InvalidPrice);
+    }
+
+    if !x0.is_zero() && p1 > U256::MAX / x0 {
+        return Err(ILMathError::CalculationOverflow);
+    }
+
+    (x0 * p1).checked_add(y0).ok_or(ILMathError::ArithmeticOverflow)
+}
+
+/// `ILMath.calculateLPValue`: `V_lp = x1 * P1 + y1 + fees`
+pub fn calculate_lp_value(x1: U256, y1: U256, fees: U256, p1: U256) -> Result<U256, ILMathError> {
+    if p1.is_zero() {
+        return Err(ILMathError::InvalidPrice);
+    }
+
+    if !x1.is_zero() && p1 > U256::MAX / x1 {
+        return Err(ILMathError::CalculationOverflow);
+    }
+
+    let token_value = (x1 * p1).checked_add(y1).ok_or(ILMathError::ArithmeticOverflow)?;
+
+    if token_value > U256::MAX - fees {
+        return Err(ILMathError::CalculationOverflow);
+    }
+
+    Ok(token_value + fees)
+}
+
+/// `ILMath.calculateIL`: `IL = max(0, V_hodl - V_lp)`
+pub fn calculate_il(hodl_value: U256, lp_value: U256) -> U256 {
+    hodl_value.saturating_sub(lp_value)
+}
+
+/// `ILMath.calculatePayout`:
+/// `min(capBps * V_hodl / 10_000, max(0, IL - deductibleBps * IL / 10_000))`
+pub fn calculate_payout(
+    impermanent_loss: U256,
+    hodl_value: U256,
+    cap_bps: u16,
+    deductible_bps: u16,
+) -> Result<U256, ILMathError> {
+    if cap_bps > MAX_BASIS_POINTS || deductible_bps > MAX_BASIS_POINTS {
+        return Err(ILMathError::InvalidBasisPoints);
+    }
+
+    if impermanent_loss.is_zero() {
+        return Ok(U256::ZERO);
+    }
+
+    let deductible_amount = impermanent_loss
+        .checked_mul(U256::from(deductible_bps as u64))
+        .ok_or(ILMathError::ArithmeticOverflow)?
+        / U256::from(BASIS_POINTS);
+
+    if impermanent_loss <= deductible_amount {
+        return Ok(U256::ZERO);
+    }
+
+    let payout_before_cap = impermanent_loss - deductible_amount;
+
+    let cap_amount = hodl_value
+        .checked_mul(U256::from(cap_bps as u64))
+        .ok_or(ILMathError::ArithmeticOverflow)?
+        / U256::from(BASIS_POINTS);
+
+    Ok(payout_before_cap.min(cap_amount))
+}
+
+/// `ILMath.calculateFullIL`
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_full_il(
+    x0: U256,
+    y0: U256,
+    x1: U256,
+    y1: U256,
+    fees: U256,
+    p1: U256,
+    cap_bps: u16,
+    deductible_bps: u16,
+) -> Result<FullIL, ILMathError> {
+    let hodl_value = calculate_hodl_value(x0, y0, p1)?;
+    let lp_value = calculate_lp_value(x1, y1, fees, p1)?;
+    let impermanent_loss = calculate_il(hodl_value, lp_value);
+    let payout = calculate_payout(impermanent_loss, hodl_value, cap_bps, deductible_bps)?;
+    Ok(FullIL {
+        hodl_value,
+        lp_value,
+        impermanent_loss,
+        payout,
+    })
+}
+
+/// `ILMath.estimateILForPriceChange`, including its quadratic approximation.
+pub fn estimate_il_for_price_change(price_change_percent: U256) -> U256 {
+    if price_change_percent.is_zero() {
+        return U256::ZERO;
+    }
+
+    if price_change_percent >= U256::from(10_000) {
+        return U256::from(5000);
+    }
+
+    // Below 10_000 the square cannot overflow
+    let il_percent = price_change_percent * price_change_percent / U256::from(40_000);
+
+    il_percent.min(U256::from(5000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hodl_and_lp_values() {
+        assert_eq!(calculate_hodl_value(U256::from(10), U256::from(5), U256::from(3)), Ok(U256::from(35)));
+        assert_eq!(
+            calculate_lp_value(U256::from(8), U256::from(6), U256::from(2), U256::from(3)),
+            Ok(U256::from(32))
+        );
+        assert_eq!(calculate_il(U256::from(35), U256::from(32)), U256::from(3));
+        assert_eq!(calculate_il(U256::from(32), U256::from(35)), U256::ZERO);
+    }
+
+    #[test]
+    fn reverts_match_solidity() {
+        assert_eq!(calculate_hodl_value(U256::ONE, U256::ONE, U256::ZERO), Err(ILMathError::InvalidPrice));
+        assert_eq!(
+            calculate_hodl_value(U256::from(2), U256::ZERO, U256::MAX),
+            Err(ILMathError::CalculationOverflow)
+        );
+        // The unguarded `+ y0` panics instead of raising a custom error
+        assert_eq!(
+            calculate_hodl_value(U256::ONE, U256::ONE, U256::MAX),
+            Err(ILMathError::ArithmeticOverflow)
+        );
+        assert_eq!(
+            calculate_lp_value(U256::ONE, U256::ZERO, U256::MAX, U256::from(2)),
+            Err(ILMathError::CalculationOverflow)
+        );
+        assert_eq!(
+            calculate_payout(U256::ONE, U256::ONE, 10_001, 0),
+            Err(ILMathError::InvalidBasisPoints)
+        );
+        assert_eq!(
+            calculate_payout(U256::MAX, U256::ONE, 0, 2),
+            Err(ILMathError::ArithmeticOverflow)
+        );
+    }
+
+    #[test]
+    fn payout_applies_deductible_then_cap() {
+        // IL 1000 with a 10% deductible leaves 900, capped at 5% of 10_000
+        assert_eq!(calculate_payout(U256::from(1000), U256::from(10_000), 500, 1000), Ok(U256::from(500)));
+        assert_eq!(calculate_payout(U256::from(1000), U256::from(100_000), 500, 1000), Ok(U256::from(900)));
+        assert_eq!(calculate_payout(U256::from(1000), U256::from(100_000), 500, 10_000), Ok(U256::ZERO));
+        assert_eq!(calculate_payout(U256::ZERO, U256::ZERO, 10_001, 0), Err(ILMathError::InvalidBasisPoints));
+    }
+
+    #[test]
+    fn full_il_chains_the_steps() {
+        let result = calculate_full_il(
+            U256::from(100),
+            U256::from(1000),
+            U256::from(80),
+            U256::from(1100),
+            U256::from(10),
+            U256::from(12),
+            10_000,
+            0,
+        );
+        assert_eq!(
+            result,
+            Ok(FullIL {
+                hodl_value: U256::from(2200),
+                lp_value: U256::from(2070),
+                impermanent_loss: U256::from(130),
+                payout: U256::from(130),
+            })
+        );
+    }
+
+    #[test]
+    fn price_change_estimate() {
+        assert_eq!(estimate_il_for_price_change(U256::ZERO), U256::ZERO);
+        assert_eq!(estimate_il_for_price_change(U256::from(2000)), U256::from(100));
+        assert_eq!(estimate_il_for_price_change(U256::from(10_000)), U256::from(5000));
+    }
+
+    #[test]
+    fn revert_data_encoding() {
+        let panic = ILMathError::ArithmeticOverflow.revert_data();
+        assert_eq!(&panic[..4], &[0x4e, 0x48, 0x7b, 0x71]);
+        assert_eq!(panic.len(), 36);
+        assert_eq!(panic[35], 0x11);
+        assert_eq!(ILMathError::InvalidPrice.revert_data().len(), 4);
+    }
+}
@@ -0,0 +1,492 @@
+//! Operator signing keys, loaded from encrypted keystores.
+//!
+//! An ECDSA key comes from an Ethereum JSON keystore (version 3, as geth,
+//! ethers and the EigenLayer CLI write them), a BLS key from an EIP-2335
+//! keystore (version 4). Both derive a key from the password with scrypt or
+//! PBKDF2 and decrypt the secret with AES-128-CTR. The secret, the derived
+//! key and the decrypted buffer are zeroized when dropped.
+
+use crate::abi::{from_hex, keccak256, Address, B256};
+use crate::bls;
+use crate::ecdsa;
+use crate::u256::Bytes;
+use aes::Aes128;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hkdf::Hkdf;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2_v010::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScheme {
+    /// secp256k1, identified by its address
+    Ecdsa,
+    /// BLS12-381, identified by its public key
+    Bls,
+}
+
+/// Public half of an operator key, which signatures are checked against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorIdentity {
+    pub scheme: KeyScheme,
+    /// Compressed SEC1 point for ECDSA, compressed G1 point for BLS
+    pub public_key: Bytes,
+    /// Address of an ECDSA key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+}
+
+impl OperatorIdentity {
+    /// Whether `signature` is this operator's signature of `message`, as
+    /// [`OperatorKey::sign`] makes it.
+    pub fn verify(&self, message: &[u8; 32], signature: &[u8]) -> bool {
+        match self.scheme {
+            KeyScheme::Ecdsa => {
+                self.address.is_some() && ecdsa::recover(ecdsa::eip191_hash(message), signature) == self.address
+            }
+            KeyScheme::Bls => bls::verify(&self.public_key.0, message, signature),
+        }
+    }
+
+    /// Whether `signature` is this operator's signature of `digest`, as
+    /// [`OperatorKey::sign_digest`] makes it.
+    pub fn verify_digest(&self, digest: B256, signature: &[u8]) -> bool {
+        match self.scheme {
+            KeyScheme::Ecdsa => self.address.is_some() && ecdsa::recover(digest, signature) == self.address,
+            KeyScheme::Bls => bls::verify(&self.public_key.0, &digest.0, signature),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError {
+    /// The keystore file could not be read
+    Io(String),
+    /// The keystore is not valid JSON, or uses an unsupported version, KDF or
+    /// cipher
+    Format(String),
+    /// The MAC or checksum does not match, usually because of a wrong
+    /// password
+    WrongPassword,
+    /// The secret is not a valid key, or not the key of the keystore's
+    /// address or public key
+    InvalidKey,
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreError::Io(message) => write!(f, "cannot read keystore: {}", message),
+            KeystoreError::Format(message) => write!(f, "invalid keystore: {}", message),
+            KeystoreError::WrongPassword => f.write_str("keystore MAC mismatch, wrong password?"),
+            KeystoreError::InvalidKey => f.write_str("keystore holds an invalid key"),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+/// A decrypted operator key. `Debug` shows only its identity.
+pub struct OperatorKey {
+    secret: Zeroizing<[u8; 32]>,
+    identity: OperatorIdentity,
+}
+
+impl fmt::Debug for OperatorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperatorKey").field("identity", &self.identity).finish_non_exhaustive()
+    }
+}
+
+impl OperatorKey {
+    /// A key from its 32-byte big-endian secret.
+    pub fn new(scheme: KeyScheme, secret: Zeroizing<[u8; 32]>) -> Result<Self, KeystoreError> {
+        let identity = match scheme {
+            KeyScheme::Ecdsa => OperatorIdentity {
+                scheme,
+                public_key: Bytes(ecdsa::derive_public_key(&secret).ok_or(KeystoreError::InvalidKey)?.to_vec()),
+                address: ecdsa::derive_address(&secret),
+            },
+            KeyScheme::Bls => OperatorIdentity {
+                scheme,
+                public_key: Bytes(bls::derive_public_key(&secret).ok_or(KeystoreError::InvalidKey)?.to_vec()),
+                address: None,
+            },
+        };
+        Ok(OperatorKey { secret, identity })
+    }
+
+    /// Decrypts the keystore file at `path`.
+    pub fn load(path: impl AsRef<Path>, password: &str) -> Result<Self, KeystoreError> {
+        let json = std::fs::read_to_string(path).map_err(|err| KeystoreError::Io(err.to_string()))?;
+        Self::from_keystore(&json, password)
+    }
+
+    /// Decrypts a version 3 keystore into an ECDSA key or a version 4
+    /// (EIP-2335) keystore into a BLS key. EIP-2335 passwords have their
+    /// control characters stripped; other passwords must already be in
+    /// NFKD form.
+    pub fn from_keystore(json: &str, password: &str) -> Result<Self, KeystoreError> {
+        let file: KeystoreFile = serde_json::from_str(json).map_err(format_error)?;
+        match file.version {
+            3 => {
+                let crypto: Web3Crypto = parse(file.crypto)?;
+                if crypto.cipher != "aes-128-ctr" {
+                    return Err(KeystoreError::Format(format!("unsupported cipher {}", crypto.cipher)));
+                }
+                let ciphertext = hex(&crypto.ciphertext)?;
+                let derived = derive_key(&crypto.kdf, crypto.kdfparams, password.as_bytes())?;
+                if keccak256([&derived[16..32], &ciphertext].concat()).0[..] != hex(&crypto.mac)? {
+                    return Err(KeystoreError::WrongPassword);
+                }
+                let plaintext = aes_128_ctr(&derived[..16], &hex(&crypto.cipherparams.iv)?, &ciphertext)?;
+                let key = OperatorKey::new(KeyScheme::Ecdsa, secret(&plaintext)?)?;
+                match file.address {
+                    Some(address) if key.identity.address.map(|derived| derived.0.to_vec()) != Some(hex(&address)?) => {
+                        Err(KeystoreError::InvalidKey)
+                    }
+                    _ => Ok(key),
+                }
+            }
+            4 => {
+                let crypto: Eip2335Crypto = parse(file.crypto)?;
+                for (module, function, expected) in [
+                    ("cipher", &crypto.cipher.function, "aes-128-ctr"),
+                    ("checksum", &crypto.checksum.function, "sha256"),
+                ] {
+                    if function != expected {
+                        return Err(KeystoreError::Format(format!("unsupported {} {}", module, function)));
+                    }
+                }
+                let password = Zeroizing::new(password.chars().filter(|c| !c.is_control()).collect::<String>());
+                let ciphertext = hex(&crypto.cipher.message)?;
+                let derived = derive_key(&crypto.kdf.function, crypto.kdf.params, password.as_bytes())?;
+                let checksum = Sha256::new().chain_update(&derived[16..32]).chain_update(&ciphertext).finalize();
+                if checksum[..] != hex(&crypto.checksum.message)? {
+                    return Err(KeystoreError::WrongPassword);
+                }
+                let plaintext = aes_128_ctr(&derived[..16], &hex(&crypto.cipher.params.iv)?, &ciphertext)?;
+                let key = OperatorKey::new(KeyScheme::Bls, secret(&plaintext)?)?;
+                match file.pubkey {
+                    Some(public_key) if hex(&public_key)? != key.identity.public_key.0 => Err(KeystoreError::InvalidKey),
+                    _ => Ok(key),
+                }
+            }
+            version => Err(KeystoreError::Format(format!("unsupported version {}", version))),
+        }
+    }
+
+    /// The keystore at `OPERATOR_KEYSTORE`, decrypted with
+    /// `OPERATOR_KEYSTORE_PASSWORD` or the first line of
+    /// `OPERATOR_KEYSTORE_PASSWORD_FILE`. `None` when no keystore is set.
+    pub fn from_env() -> Result<Option<Self>, KeystoreError> {
+        let Ok(path) = std::env::var("OPERATOR_KEYSTORE") else {
+            return Ok(None);
+        };
+        let password = match std::env::var("OPERATOR_KEYSTORE_PASSWORD_FILE") {
+            Ok(file) => {
+                let contents = Zeroizing::new(std::fs::read_to_string(file).map_err(|err| KeystoreError::Io(err.to_string()))?);
+                Zeroizing::new(contents.lines().next().unwrap_or_default().to_string())
+            }
+            Err(_) => Zeroizing::new(std::env::var("OPERATOR_KEYSTORE_PASSWORD").unwrap_or_default()),
+        };
+        Self::load(path, &password).map(Some)
+    }
+
+    pub fn scheme(&self) -> KeyScheme {
+        self.identity.scheme
+    }
+
+    pub fn identity(&self) -> &OperatorIdentity {
+        &self.identity
+    }
+
+    /// Signs a 32-byte message: ECDSA keys sign its EIP-191 hash, as
+    /// `wallet.signMessage` does, and BLS keys sign it as [`bls::sign`].
+    pub fn sign(&self, message: &[u8; 32]) -> Bytes {
+        let signature = match self.identity.scheme {
+            KeyScheme::Ecdsa => ecdsa::sign(&self.secret, ecdsa::eip191_hash(message)).map(|signature| signature.to_vec()),
+            KeyScheme::Bls => bls::sign(&self.secret, message).map(|signature| signature.to_vec()),
+        };
+        Bytes(signature.expect("key validated by OperatorKey::new"))
+    }
+
+    /// Signs a digest that is already domain-separated, such as an EIP-712
+    /// digest: ECDSA keys sign it as is, as `eth_signTypedData_v4` does, and
+    /// BLS keys sign it as [`bls::sign`].
+    pub fn sign_digest(&self, digest: B256) -> Bytes {
+        let signature = match self.identity.scheme {
+            KeyScheme::Ecdsa => ecdsa::sign(&self.secret, digest).map(|signature| signature.to_vec()),
+            KeyScheme::Bls => bls::sign(&self.secret, &digest.0).map(|signature| signature.to_vec()),
+        };
+        Bytes(signature.expect("key validated by OperatorKey::new"))
+    }
+
+    /// A 32-byte key for another purpose, derived from the secret with
+    /// HKDF-SHA-256 under `info`, so one keystore backs every key the
+    /// operator publishes.
+    pub(crate) fn derive_subkey(&self, info: &[u8]) -> Zeroizing<[u8; 32]> {
+        let mut subkey = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, self.secret.as_slice())
+            .expand(info, subkey.as_mut_slice())
+            .expect("32 bytes is a valid HKDF-SHA-256 length");
+        subkey
+    }
+}
+
+#[derive(Deserialize)]
+struct KeystoreFile {
+    version: u64,
+    #[serde(alias = "Crypto")]
+    crypto: Value,
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    pubkey: Option<String>,
+}
+
+// Version 3 `crypto` section
+#[derive(Deserialize)]
+struct Web3Crypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: Value,
+    mac: String,
+}
+
+// EIP-2335 `crypto` section
+#[derive(Deserialize)]
+struct Eip2335Crypto {
+    kdf: Module<Value>,
+    checksum: Module<Value>,
+    cipher: Module<CipherParams>,
+}
+
+#[derive(Deserialize)]
+struct Module<P> {
+    function: String,
+    params: P,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Deserialize)]
+struct ScryptParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct Pbkdf2Params {
+    dklen: usize,
+    c: u32,
+    prf: String,
+    salt: String,
+}
+
+fn derive_key(function: &str, params: Value, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+    match function {
+        "scrypt" => {
+            let params: ScryptParams = parse(params)?;
+            if !params.n.is_power_of_two() || params.n < 2 {
+                return Err(KeystoreError::Format("scrypt n must be a power of two".to_string()));
+            }
+            let cost = scrypt::Params::new(params.n.trailing_zeros() as u8, params.r, params.p, params.dklen)
+                .map_err(format_error)?;
+            let mut key = derived_key(params.dklen)?;
+            scrypt::scrypt(password, &hex(&params.salt)?, &cost, &mut key).map_err(format_error)?;
+            Ok(key)
+        }
+        "pbkdf2" => {
+            let params: Pbkdf2Params = parse(params)?;
+            if params.prf != "hmac-sha256" {
+                return Err(KeystoreError::Format(format!("unsupported prf {}", params.prf)));
+            }
+            let mut key = derived_key(params.dklen)?;
+            pbkdf2::pbkdf2_hmac::<Sha256>(password, &hex(&params.salt)?, params.c, &mut key);
+            Ok(key)
+        }
+        function => Err(KeystoreError::Format(format!("unsupported kdf {}", function))),
+    }
+}
+
+// The first half of the derived key is the cipher key, the second
+// authenticates the ciphertext
+fn derived_key(dklen: usize) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+    if dklen < 32 {
+        return Err(KeystoreError::Format("dklen below 32".to_string()));
+    }
+    Ok(Zeroizing::new(vec![0; dklen]))
+}
+
+fn aes_128_ctr(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+    let mut cipher = ctr::Ctr128BE::<Aes128>::new_from_slices(key, iv)
+        .map_err(|_| KeystoreError::Format("iv must be 16 bytes".to_string()))?;
+    let mut plaintext = Zeroizing::new(ciphertext.to_vec());
+    cipher.apply_keystream(&mut plaintext);
+    Ok(plaintext)
+}
+
+fn secret(plaintext: &[u8]) -> Result<Zeroizing<[u8; 32]>, KeystoreError> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    if plaintext.len() != secret.len() {
+        return Err(KeystoreError::InvalidKey);
+    }
+    secret.copy_from_slice(plaintext);
+    Ok(secret)
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, KeystoreError> {
+    serde_json::from_value(value).map_err(format_error)
+}
+
+// Keystores write hex without a prefix
+fn hex(text: &str) -> Result<Vec<u8>, KeystoreError> {
+    from_hex(&format!("0x{}", text.strip_prefix("0x").unwrap_or(text)))
+        .ok_or_else(|| KeystoreError::Format(format!("invalid hex {:?}", text)))
+}
+
+fn format_error(err: impl fmt::Display) -> KeystoreError {
+    KeystoreError::Format(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::to_hex;
+
+    const IV: &str = "83dbcc02d8ccb40e466191a123791e0e";
+    const SALT: &str = "ab0c7876052600dd703518d6fc3fe8984592145b591fc8fb5c6d43190334ba19";
+
+    fn secret_key(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[31] = seed;
+        key
+    }
+
+    // Encrypts as geth and EIP-2335 tooling do, with a cheap scrypt
+    fn keystore(version: u64, secret_key: [u8; 32], password: &str) -> Value {
+        let kdfparams = serde_json::json!({ "dklen": 32, "n": 16, "r": 8, "p": 1, "salt": SALT });
+        let derived = derive_key("scrypt", kdfparams.clone(), password.as_bytes()).unwrap();
+        let ciphertext = aes_128_ctr(&derived[..16], &hex(IV).unwrap(), &secret_key).unwrap();
+        let hex = |bytes: &[u8]| to_hex(bytes)[2..].to_string();
+        if version == 3 {
+            let mac = keccak256([&derived[16..32], &ciphertext[..]].concat());
+            serde_json::json!({
+                "version": 3,
+                "crypto": {
+                    "cipher": "aes-128-ctr",
+                    "cipherparams": { "iv": IV },
+                    "ciphertext": hex(&ciphertext),
+                    "kdf": "scrypt",
+                    "kdfparams": kdfparams,
+                    "mac": hex(&mac.0),
+                },
+            })
+        } else {
+            let checksum = Sha256::new().chain_update(&derived[16..32]).chain_update(&ciphertext[..]).finalize();
+            serde_json::json!({
+                "version": 4,
+                "crypto": {
+                    "kdf": { "function": "scrypt", "params": kdfparams, "message": "" },
+                    "checksum": { "function": "sha256", "params": {}, "message": hex(&checksum) },
+                    "cipher": { "function": "aes-128-ctr", "params": { "iv": IV }, "message": hex(&ciphertext) },
+                },
+                "pubkey": hex(&bls::derive_public_key(&secret_key).unwrap()),
+            })
+        }
+    }
+
+    #[test]
+    fn decrypts_the_web3_secret_storage_pbkdf2_vector() {
+        let json = r#"{
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+                "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+                "kdf": "pbkdf2",
+                "kdfparams": {
+                    "c": 262144,
+                    "dklen": 32,
+                    "prf": "hmac-sha256",
+                    "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+                },
+                "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+            },
+            "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+            "version": 3
+        }"#;
+        let key = OperatorKey::from_keystore(json, "testpassword").unwrap();
+        let secret: [u8; 32] = hex("7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(key.scheme(), KeyScheme::Ecdsa);
+        assert_eq!(key.identity().address, ecdsa::derive_address(&secret));
+        assert_eq!(OperatorKey::from_keystore(json, "wrongpassword").unwrap_err(), KeystoreError::WrongPassword);
+    }
+
+    #[test]
+    fn loads_scrypt_ecdsa_and_eip2335_bls_keystores() {
+        let ecdsa_key = OperatorKey::from_keystore(&keystore(3, secret_key(1), "pass").to_string(), "pass").unwrap();
+        assert_eq!(ecdsa_key.identity().address, ecdsa::derive_address(&secret_key(1)));
+        assert_eq!(ecdsa_key.identity().public_key.0.len(), ecdsa::PUBLIC_KEY_LENGTH);
+
+        // EIP-2335 strips control characters from the password
+        let bls_key = OperatorKey::from_keystore(&keystore(4, secret_key(2), "pass").to_string(), "pa\u{7f}ss\n").unwrap();
+        assert_eq!(bls_key.scheme(), KeyScheme::Bls);
+        assert_eq!(bls_key.identity().public_key.0, bls::derive_public_key(&secret_key(2)).unwrap());
+        assert_eq!(bls_key.identity().address, None);
+        assert!(!format!("{:?}", bls_key).contains("secret"));
+
+        let mut mismatched = keystore(4, secret_key(2), "pass");
+        mismatched["pubkey"] = Value::String(to_hex(&bls::derive_public_key(&secret_key(3)).unwrap()));
+        assert_eq!(OperatorKey::from_keystore(&mismatched.to_string(), "pass").unwrap_err(), KeystoreError::InvalidKey);
+        let mut unsupported = keystore(3, secret_key(1), "pass");
+        unsupported["crypto"]["kdf"] = Value::String("argon2".to_string());
+        assert!(matches!(
+            OperatorKey::from_keystore(&unsupported.to_string(), "pass"),
+            Err(KeystoreError::Format(_))
+        ));
+        // A zero secret is not a key
+        assert_eq!(
+            OperatorKey::from_keystore(&keystore(3, [0; 32], "pass").to_string(), "pass").unwrap_err(),
+            KeystoreError::InvalidKey
+        );
+    }
+
+    #[test]
+    fn signatures_verify_against_the_identity() {
+        let message = keccak256("response").0;
+        for scheme in [KeyScheme::Ecdsa, KeyScheme::Bls] {
+            let key = OperatorKey::new(scheme, Zeroizing::new(secret_key(5))).unwrap();
+            let signature = key.sign(&message);
+            assert!(key.identity().verify(&message, &signature.0));
+            assert!(!key.identity().verify(&keccak256("other").0, &signature.0));
+
+            // Digests are signed without the EIP-191 prefix
+            let digest = keccak256("digest");
+            let signature = key.sign_digest(digest);
+            assert!(key.identity().verify_digest(digest, &signature.0));
+            assert!(!key.identity().verify_digest(keccak256("other"), &signature.0));
+            if scheme == KeyScheme::Ecdsa {
+                assert!(!key.identity().verify(&digest.0, &signature.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Compute core of the confidential insurance service.
+//!
+//! [`ComputeEngine`] implements the spec methods and attestation requests;
+//! the JSON-RPC server (`main.rs`) and the `simple` binary are front ends
+//! over it. The remaining modules are the pricing models the engine and the
+//! extension methods build on.
+
+pub mod abi;
+pub mod aggregation;
+pub mod amm;
+pub mod bls;
+pub mod challenge;
+pub mod concentrated;
+pub mod ecdsa;
+pub mod eip712;
+pub mod engine;
+pub mod envelope;
+pub mod error;
+pub mod fixed_point;
+pub mod groth16;
+pub mod ilmath;
+pub mod keystore;
+pub mod lvr;
+pub mod policy;
+pub mod registry;
+pub mod request;
+pub mod threshold;
+pub mod transcript;
+pub mod u256;
+
+pub use abi::B256;
+pub use engine::ComputeEngine;
+pub use error::ComputeError;
+pub use u256::{Bytes, U256};
@@ -1,215 +1,326 @@
+// serve() replaces the generated start_server, which has no room for the
+// extension methods
+#[allow(dead_code)]
 mod gen;
+mod rpc;
 
+use alloy_primitives::{Address, B256};
 use gen::*;
-use std::collections::HashMap;
+use insurance_core::aggregation::AggregationPolicy;
+use insurance_core::challenge::Evidence;
+use insurance_core::eip712::{Domain, ILAttestation};
+use insurance_core::engine::{AggregatedAttestation, EcdsaAggregatedAttestation};
+use insurance_core::envelope::{EncryptedResponse, EncryptionKey, Envelope, EnvelopeKey};
+use insurance_core::groth16::CircuitRegistry;
+use insurance_core::keystore::{OperatorIdentity, OperatorKey};
+use insurance_core::policy::PolicyManager;
+use insurance_core::registry::{HttpNode, Operator, OperatorRegistry};
+use insurance_core::request::{AttestationRequest, AttestationResponse};
+use insurance_core::threshold::{DecryptionShare, KeyShare, ThresholdCiphertext, ThresholdKey};
+use insurance_core::transcript::{ComputeCall, SignedTranscript};
+use insurance_core::{concentrated, ecdsa, ilmath, lvr, ComputeEngine, ComputeError};
+use jsonrpsee::types::error::INTERNAL_ERROR_CODE;
+use jsonrpsee::server::ServerBuilder;
+use jsonrpsee::types::ErrorObject;
+use rpc::{compute_error, revert_error, ConfidentialInsuranceExtRpcServer};
+use std::net::SocketAddr;
 
-pub struct ServerImpl;
+pub struct ServerImpl {
+    engine: ComputeEngine,
+}
 
 #[async_trait::async_trait]
 impl ConfidentialInsuranceRpcServer for ServerImpl {
     // Call this method using the name: compute_aggregateAttestations
     async fn aggregate_attestations(&self, attestations: Vec<U256>, signatures: Vec<Bytes>, operator_public_keys: Vec<Bytes>, threshold: U256) -> RpcResult<(U256, bool)> {
-        // Aggregate multiple operator attestations using BLS signatures
-        
-        if attestations.len() != signatures.len() || signatures.len() != operator_public_keys.len() {
-            return Ok((U256::ZERO, false));
-        }
-        
-        if attestations.len() < threshold.as_u64() as usize {
-            return Ok((U256::ZERO, false));
-        }
-        
-        // Simple aggregation logic - in production this would use proper BLS signature verification
-        let mut aggregated_value = U256::ZERO;
-        let mut valid_attestations = 0u64;
-        
-        // Calculate weighted average of attestations
-        for (i, attestation) in attestations.iter().enumerate() {
-            // In a real implementation, we would verify each BLS signature here
-            // For now, we assume all signatures are valid for demonstration
-            if !attestation.is_zero() && !signatures[i].is_empty() && !operator_public_keys[i].is_empty() {
-                aggregated_value = aggregated_value + attestation;
-                valid_attestations += 1;
-            }
-        }
-        
-        let meets_threshold = valid_attestations >= threshold.as_u64();
-        
-        if meets_threshold && valid_attestations > 0 {
-            aggregated_value = aggregated_value / U256::from(valid_attestations);
-        } else {
-            aggregated_value = U256::ZERO;
-        }
-        
-        Ok((aggregated_value, meets_threshold))
+        // The spec signature has no policy id; its attestations are signed for policy 0
+        let aggregated = self
+            .engine
+            .aggregate_attestations(core(U256::ZERO), &core_values(&attestations), &core_bytes(&signatures), &core_bytes(&operator_public_keys), core(threshold), &AggregationPolicy::default())
+            .map_err(compute_error)?;
+        Ok((alloy(aggregated.value), aggregated.meets_threshold))
     }
 
     // Call this method using the name: compute_calculateImpermanentLoss
     async fn calculate_impermanent_loss(&self, initial_token_a_amount: U256, initial_token_b_amount: U256, current_token_a_price: U256, current_token_b_price: U256, initial_token_a_price: U256, initial_token_b_price: U256, pool_fee_rate: U256) -> RpcResult<(U256, bool)> {
-        // Calculate impermanent loss for liquidity providers
-        // IL = (2 * sqrt(price_ratio) / (1 + price_ratio)) - 1
-        
-        let price_ratio = if initial_token_a_price.is_zero() || initial_token_b_price.is_zero() {
-            return Ok((U256::ZERO, false));
-        } else {
-            (current_token_a_price * initial_token_b_price) / (initial_token_a_price * current_token_b_price)
-        };
-        
-        // Calculate initial portfolio value
-        let initial_value = initial_token_a_amount * initial_token_a_price + initial_token_b_amount * initial_token_b_price;
-        
-        // Calculate current value if held (not in LP)
-        let hold_value = initial_token_a_amount * current_token_a_price + initial_token_b_amount * current_token_b_price;
-        
-        // Calculate LP value with impermanent loss
-        // Simplified calculation for demonstration
-        let sqrt_ratio = isqrt(price_ratio);
-        let lp_multiplier = (U256::from(2) * sqrt_ratio) / (U256::from(1) + price_ratio);
-        let lp_value = (initial_value * lp_multiplier) / U256::from(1);
-        
-        // Add fees earned
-        let fees_earned = (initial_value * pool_fee_rate) / U256::from(10000); // basis points
-        let total_lp_value = lp_value + fees_earned;
-        
-        // Calculate impermanent loss
-        let impermanent_loss = if hold_value > total_lp_value {
-            hold_value - total_lp_value
-        } else {
-            U256::ZERO
-        };
-        
-        let has_loss = impermanent_loss > U256::ZERO;
-        
-        Ok((impermanent_loss, has_loss))
+        let (impermanent_loss, has_loss) = self
+            .engine
+            .calculate_impermanent_loss(
+                core(initial_token_a_amount),
+                core(initial_token_b_amount),
+                core(current_token_a_price),
+                core(current_token_b_price),
+                core(initial_token_a_price),
+                core(initial_token_b_price),
+                core(pool_fee_rate),
+            )
+            .map_err(compute_error)?;
+        Ok((alloy(impermanent_loss), has_loss))
     }
 
     // Call this method using the name: compute_calculatePayout
     async fn calculate_payout(&self, policy_id: U256, impermanent_loss: U256, coverage_amount: U256, deductible: U256, coverage_ratio: U256) -> RpcResult<U256> {
-        // Calculate insurance payout based on policy parameters
-        
-        if impermanent_loss <= deductible {
-            // Loss is below deductible threshold
-            return Ok(U256::ZERO);
-        }
-        
-        // Calculate loss above deductible
-        let covered_loss = impermanent_loss - deductible;
-        
-        // Apply coverage ratio (e.g., 80% coverage)
-        let payout_before_cap = (covered_loss * coverage_ratio) / U256::from(10000); // basis points
-        
-        // Apply coverage amount cap
-        let final_payout = if payout_before_cap > coverage_amount {
-            coverage_amount
-        } else {
-            payout_before_cap
-        };
-        
-        Ok(final_payout)
+        Ok(alloy(self.engine.calculate_payout(
+            core(policy_id),
+            core(impermanent_loss),
+            core(coverage_amount),
+            core(deductible),
+            core(coverage_ratio),
+        )))
     }
 
     // Call this method using the name: compute_validateOraclePrices
     async fn validate_oracle_prices(&self, price_data: Vec<U256>, timestamps: Vec<U256>, deviation_threshold: U256) -> RpcResult<(bool, Vec<U256>)> {
-        // Validate oracle price data for anomalies and consistency
-        
-        if price_data.len() != timestamps.len() || price_data.is_empty() {
-            return Ok((false, vec![]));
-        }
-        
-        let mut valid_prices = Vec::new();
-        let mut is_valid = true;
-        
-        // Check for price deviations
-        for i in 1..price_data.len() {
-            let prev_price = price_data[i - 1];
-            let curr_price = price_data[i];
-            
-            if prev_price.is_zero() {
-                is_valid = false;
-                continue;
-            }
-            
-            // Calculate percentage deviation
-            let deviation = if curr_price > prev_price {
-                ((curr_price - prev_price) * U256::from(10000)) / prev_price
-            } else {
-                ((prev_price - curr_price) * U256::from(10000)) / prev_price
-            };
-            
-            if deviation > deviation_threshold {
-                is_valid = false;
-            } else {
-                valid_prices.push(curr_price);
-            }
-        }
-        
-        // Check timestamp ordering
-        for i in 1..timestamps.len() {
-            if timestamps[i] <= timestamps[i - 1] {
-                is_valid = false;
-                break;
-            }
-        }
-        
-        Ok((is_valid, valid_prices))
+        let (is_valid, valid_prices) = self
+            .engine
+            .validate_oracle_prices(&core_values(&price_data), &core_values(&timestamps), core(deviation_threshold))
+            .map_err(compute_error)?;
+        Ok((is_valid, valid_prices.into_iter().map(alloy).collect()))
     }
 
     // Call this method using the name: compute_verifyEncryptedAttestation
     async fn verify_encrypted_attestation(&self, encrypted_attestation: Bytes, proof: Bytes, public_inputs: Vec<U256>) -> RpcResult<(bool, U256)> {
-        // Verify encrypted attestation using zero-knowledge proofs
-        
-        if encrypted_attestation.is_empty() || proof.is_empty() {
-            return Ok((false, U256::ZERO));
-        }
-        
-        // In a real implementation, this would:
-        // 1. Decrypt the attestation using FHE
-        // 2. Verify the ZK proof of correct computation
-        // 3. Extract the computed value
-        
-        // For demonstration, we simulate the verification process
-        let attestation_hash = keccak256(&encrypted_attestation);
-        let proof_hash = keccak256(&proof);
-        
-        // Simple verification logic - check if proof and attestation are consistent
-        let is_valid = !attestation_hash.is_zero() && !proof_hash.is_zero() && !public_inputs.is_empty();
-        
-        // Extract a simulated computed value from the public inputs
-        let computed_value = if is_valid && !public_inputs.is_empty() {
-            public_inputs[0] // First public input as the computed result
-        } else {
-            U256::ZERO
+        let (is_valid, computed_value) = self
+            .engine
+            .verify_encrypted_attestation(
+                &insurance_core::Bytes(encrypted_attestation.to_vec()),
+                &insurance_core::Bytes(proof.to_vec()),
+                &core_values(&public_inputs),
+            )
+            .map_err(compute_error)?;
+        Ok((is_valid, alloy(computed_value)))
+    }
+
+}
+
+#[async_trait::async_trait]
+impl ConfidentialInsuranceExtRpcServer for ServerImpl {
+    // Call this method using the name: compute_ilmathCalculateHodlValue
+    async fn ilmath_calculate_hodl_value(&self, x0: U256, y0: U256, p1: U256) -> RpcResult<U256> {
+        ilmath::calculate_hodl_value(core(x0), core(y0), core(p1)).map(alloy).map_err(revert_error)
+    }
+
+    // Call this method using the name: compute_ilmathCalculateLPValue
+    async fn ilmath_calculate_lp_value(&self, x1: U256, y1: U256, fees: U256, p1: U256) -> RpcResult<U256> {
+        ilmath::calculate_lp_value(core(x1), core(y1), core(fees), core(p1)).map(alloy).map_err(revert_error)
+    }
+
+    // Call this method using the name: compute_ilmathCalculateIL
+    async fn ilmath_calculate_il(&self, hodl_value: U256, lp_value: U256) -> RpcResult<U256> {
+        Ok(alloy(ilmath::calculate_il(core(hodl_value), core(lp_value))))
+    }
+
+    // Call this method using the name: compute_ilmathCalculatePayout
+    async fn ilmath_calculate_payout(&self, impermanent_loss: U256, hodl_value: U256, cap_bps: u16, deductible_bps: u16) -> RpcResult<U256> {
+        ilmath::calculate_payout(core(impermanent_loss), core(hodl_value), cap_bps, deductible_bps)
+            .map(alloy)
+            .map_err(revert_error)
+    }
+
+    // Call this method using the name: compute_ilmathCalculateFullIL
+    async fn ilmath_calculate_full_il(&self, x0: U256, y0: U256, x1: U256, y1: U256, fees: U256, p1: U256, cap_bps: u16, deductible_bps: u16) -> RpcResult<(U256, U256, U256, U256)> {
+        let result = ilmath::calculate_full_il(core(x0), core(y0), core(x1), core(y1), core(fees), core(p1), cap_bps, deductible_bps)
+            .map_err(revert_error)?;
+        Ok((alloy(result.hodl_value), alloy(result.lp_value), alloy(result.impermanent_loss), alloy(result.payout)))
+    }
+
+    // Call this method using the name: compute_ilmathEstimateILForPriceChange
+    async fn ilmath_estimate_il_for_price_change(&self, price_change_percent: U256) -> RpcResult<U256> {
+        Ok(alloy(ilmath::estimate_il_for_price_change(core(price_change_percent))))
+    }
+
+    // Call this method using the name: compute_aggregateSignedAttestations
+    async fn aggregate_signed_attestations(&self, policy_id: U256, attestations: Vec<U256>, signatures: Vec<Bytes>, operator_public_keys: Vec<Bytes>, threshold: U256, policy: Option<AggregationPolicy>) -> RpcResult<AggregatedAttestation> {
+        self.engine
+            .aggregate_attestations(core(policy_id), &core_values(&attestations), &core_bytes(&signatures), &core_bytes(&operator_public_keys), core(threshold), &policy.unwrap_or_default())
+            .map_err(compute_error)
+    }
+
+    // Call this method using the name: compute_aggregateEcdsaAttestations
+    async fn aggregate_ecdsa_attestations(&self, attestations: Vec<U256>, signatures: Vec<Bytes>, operators: Option<Vec<Address>>, threshold: U256, domain_separator: Option<B256>, policy: Option<AggregationPolicy>) -> RpcResult<EcdsaAggregatedAttestation> {
+        let operators: Option<Vec<_>> = operators.map(|operators| operators.iter().copied().map(core_address).collect());
+        let digest = match domain_separator {
+            Some(separator) => ecdsa::Digest::Eip712 { domain_separator: insurance_core::B256(separator.0) },
+            None => ecdsa::Digest::Eip191,
         };
-        
-        Ok((is_valid, computed_value))
+        self.engine
+            .aggregate_ecdsa_attestations(&core_values(&attestations), &core_bytes(&signatures), operators.as_deref(), core(threshold), digest, &policy.unwrap_or_default())
+            .map_err(compute_error)
+    }
+
+    // Call this method using the name: compute_getOperators
+    async fn get_operators(&self) -> RpcResult<Vec<Operator>> {
+        Ok(self.engine.registry().operators().to_vec())
+    }
+
+    // Call this method using the name: compute_getOperatorPublicKey
+    async fn get_operator_public_key(&self) -> RpcResult<Option<OperatorIdentity>> {
+        Ok(self.engine.operator_identity().cloned())
+    }
+
+    // Call this method using the name: compute_processAttestationRequest
+    async fn process_attestation_request(&self, request: AttestationRequest) -> RpcResult<AttestationResponse> {
+        let engine = self.engine.clone();
+        blocking(move || engine.process_attestation_request(&request)).await
+    }
+
+    // Call this method using the name: encryption.discover
+    async fn encryption_discover(&self) -> RpcResult<Option<EncryptionKey>> {
+        Ok(self.engine.encryption_key())
+    }
+
+    // Call this method using the name: compute_processEncryptedAttestationRequest
+    async fn process_encrypted_attestation_request(&self, envelope: Envelope) -> RpcResult<EncryptedResponse> {
+        let engine = self.engine.clone();
+        blocking(move || engine.process_encrypted_attestation_request(&envelope)).await
+    }
+
+    // Call this method using the name: compute_getThresholdKey
+    async fn get_threshold_key(&self) -> RpcResult<Option<ThresholdKey>> {
+        Ok(self.engine.threshold_key().cloned())
+    }
+
+    // Call this method using the name: compute_thresholdEncrypt
+    async fn threshold_encrypt(&self, policy_id: U256, value: U256) -> RpcResult<ThresholdCiphertext> {
+        self.engine.threshold_encrypt(core(policy_id), core(value)).map_err(compute_error)
+    }
+
+    // Call this method using the name: compute_partialDecrypt
+    async fn partial_decrypt(&self, ciphertext: ThresholdCiphertext) -> RpcResult<DecryptionShare> {
+        self.engine.partial_decrypt(&ciphertext).map_err(compute_error)
+    }
+
+    // Call this method using the name: compute_combineDecryptionShares
+    async fn combine_decryption_shares(&self, ciphertext: ThresholdCiphertext, shares: Vec<DecryptionShare>) -> RpcResult<U256> {
+        self.engine.combine_decryption_shares(&ciphertext, &shares).map(alloy).map_err(compute_error)
     }
 
+    // Call this method using the name: compute_trace
+    async fn trace(&self, call: ComputeCall) -> RpcResult<SignedTranscript> {
+        let engine = self.engine.clone();
+        blocking(move || engine.trace(&call)).await
+    }
+
+    // Call this method using the name: compute_challengeAttestation
+    async fn challenge_attestation(&self, request: AttestationRequest, attestation: AttestationResponse) -> RpcResult<Option<Evidence>> {
+        let engine = self.engine.clone();
+        blocking(move || engine.challenge_attestation(request, attestation)).await
+    }
+
+    // Call this method using the name: compute_hashILAttestation
+    async fn hash_il_attestation(&self, attestation: ILAttestation, domain: Domain) -> RpcResult<B256> {
+        Ok(B256::from(attestation.digest(&domain).0))
+    }
+
+    // Call this method using the name: compute_recoverILAttestationSigner
+    async fn recover_il_attestation_signer(&self, attestation: ILAttestation, domain: Domain, signature: Bytes) -> RpcResult<Option<Address>> {
+        Ok(attestation.recover(&domain, &signature).map(|signer| Address::from(signer.0)))
+    }
+
+    // Call this method using the name: compute_calculateConcentratedIL
+    async fn calculate_concentrated_il(&self, liquidity: U256, tick_lower: i32, tick_upper: i32, sqrt_price_entry_x96: U256, sqrt_price_current_x96: U256) -> RpcResult<concentrated::ConcentratedIL> {
+        concentrated::calculate_concentrated_il(core(liquidity), tick_lower, tick_upper, core(sqrt_price_entry_x96), core(sqrt_price_current_x96))
+            .map_err(compute_error)
+    }
+
+    // Call this method using the name: compute_calculateLVR
+    async fn calculate_lvr(&self, initial_token_a_amount: U256, initial_token_b_amount: U256, price_data: Vec<U256>, timestamps: Vec<U256>, pool_fee_rate: U256) -> RpcResult<lvr::LvrReport> {
+        lvr::calculate_lvr(core(initial_token_a_amount), core(initial_token_b_amount), &core_values(&price_data), &core_values(&timestamps), core(pool_fee_rate))
+            .map_err(compute_error)
+    }
 }
 
-// Helper function for integer square root
-fn isqrt(value: U256) -> U256 {
-    if value.is_zero() {
-        return U256::ZERO;
-    }
-    
-    let mut x = value;
-    let mut y = (value + U256::from(1)) / U256::from(2);
-    
-    while y < x {
-        x = y;
-        y = (y + value / y) / U256::from(2);
-    }
-    
-    x
+// The engine has its own U256 with the same little-endian limb layout
+fn core(value: U256) -> insurance_core::U256 {
+    insurance_core::U256(value.into_limbs())
 }
 
-// Helper function for keccak256 hash
-fn keccak256(data: &[u8]) -> U256 {
-    use sha3::{Digest, Keccak256};
-    let mut hasher = Keccak256::new();
-    hasher.update(data);
-    let result = hasher.finalize();
-    U256::from_big_endian(&result)
+fn alloy(value: insurance_core::U256) -> U256 {
+    U256::from_limbs(value.0)
+}
+
+fn core_values(values: &[U256]) -> Vec<insurance_core::U256> {
+    values.iter().copied().map(core).collect()
+}
+
+fn core_bytes(values: &[Bytes]) -> Vec<insurance_core::Bytes> {
+    values.iter().map(|bytes| insurance_core::Bytes(bytes.to_vec())).collect()
+}
+
+fn core_address(address: Address) -> insurance_core::abi::Address {
+    insurance_core::abi::Address(address.into_array())
+}
+
+/// Runs engine work that may read the policy manager from the node on the
+/// blocking pool.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T, ComputeError> + Send + 'static) -> RpcResult<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|err| ErrorObject::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>))?
+        .map_err(compute_error)
+}
+
+/// Loads the operator registry from `OPERATOR_REGISTRY`, if set, and syncs it
+/// from the `EigenAVSManager` at `AVS_MANAGER_ADDRESS` through `ETH_RPC_URL`,
+/// if both are set, now and every `REGISTRY_SYNC_INTERVAL` seconds. Entry
+/// commitments are read from `POLICY_MANAGER_ADDRESS` through the same node;
+/// without it, requests without an `entry_commit` are only accepted when
+/// `ALLOW_UNCOMMITTED_REQUESTS` is `true`.
+async fn configured_engine() -> Result<ComputeEngine, Box<dyn std::error::Error>> {
+    let engine = match std::env::var("OPERATOR_REGISTRY") {
+        Ok(path) => ComputeEngine::with_registry(OperatorRegistry::load(path)?),
+        Err(_) => ComputeEngine::new(),
+    };
+    // The envelope key follows the operator key across restarts when there is one
+    let engine = match OperatorKey::from_env()? {
+        Some(key) => engine.with_envelope_key(EnvelopeKey::derive(&key)).with_key(key),
+        None => engine.with_envelope_key(EnvelopeKey::generate()),
+    };
+    let engine = match (std::env::var("CHAIN_ID"), std::env::var("ATTESTATION_VERIFIER")) {
+        (Ok(chain_id), Ok(verifier)) => engine.with_domain(Domain::new(core(chain_id.parse()?), core_address(verifier.parse()?))),
+        _ => engine,
+    };
+    let engine = match std::env::var("CIRCUIT_KEYS") {
+        Ok(path) => engine.with_circuits(CircuitRegistry::load(path)?),
+        Err(_) => engine,
+    };
+    let engine = match std::env::var("THRESHOLD_KEY_SHARE") {
+        Ok(path) => engine.with_key_share(KeyShare::load(path)?),
+        Err(_) => engine,
+    };
+    let engine = match (std::env::var("POLICY_MANAGER_ADDRESS"), std::env::var("ETH_RPC_URL")) {
+        (Ok(address), Ok(url)) => engine.with_policy_manager(PolicyManager::new(core_address(address.parse()?), HttpNode::new(&url)?)),
+        _ => engine,
+    };
+    let engine = match std::env::var("ALLOW_UNCOMMITTED_REQUESTS").as_deref() {
+        Ok("true") => engine.allow_uncommitted_requests(),
+        _ => engine,
+    };
+    let (Ok(manager), Ok(url)) = (std::env::var("AVS_MANAGER_ADDRESS"), std::env::var("ETH_RPC_URL")) else {
+        return Ok(engine);
+    };
+    let manager = core_address(manager.parse()?);
+    let node = HttpNode::new(&url)?;
+    let interval = std::env::var("REGISTRY_SYNC_INTERVAL")
+        .ok()
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    let (syncing, sync_node) = (engine.clone(), node.clone());
+    tokio::task::spawn_blocking(move || syncing.sync_registry(manager, &sync_node)).await??;
+    let syncing = engine.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+            let (engine, node) = (syncing.clone(), node.clone());
+            match tokio::task::spawn_blocking(move || engine.sync_registry(manager, &node)).await {
+                Ok(Err(err)) => eprintln!("{}", err),
+                Err(err) => eprintln!("operator sync failed: {}", err),
+                Ok(Ok(_)) => {}
+            }
+        }
+    });
+    Ok(engine)
 }
 
 #[tokio::main]
@@ -218,5 +329,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .ok()
         .and_then(|p| p.parse::<u16>().ok())
         .unwrap_or(8080);
-    start_server(ServerImpl, port).await
+    let engine = configured_engine().await?;
+    serve(engine, port).await
+}
+
+// The generated start_server with the extension methods merged into the module
+async fn serve(engine: ComputeEngine, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let server_addr: SocketAddr = format!("0.0.0.0:{}", port).parse()?;
+    let server_handle = ServerBuilder::default().build(server_addr).await?;
+
+    let mut module = ConfidentialInsuranceRpcServer::into_rpc(ServerImpl { engine: engine.clone() });
+    module.merge(ConfidentialInsuranceExtRpcServer::into_rpc(ServerImpl { engine }))?;
+    module.register_method("system_health", |_, _, _| -> RpcResult<serde_json::Value> {
+        Ok(serde_json::json!({ "status": "ok" }))
+    })?;
+    module.register_method("rpc.discover", |_, _, _| discovery_document(OPENRPC_SCHEMA_JSON, "OpenRPC schema"))?;
+    module.register_method("abi.discover", |_, _, _| discovery_document(ABI_JSON, "ABI"))?;
+
+    let handle = server_handle.start(module);
+    println!("ConfidentialInsuranceSpec Server started on http://{}", server_addr);
+    handle.stopped().await;
+    Ok(())
+}
+
+// Discovery documents describe the generated spec; the extension methods are
+// documented in the README
+const OPENRPC_SCHEMA_JSON: &str = include_str!("gen/IConfidentialInsuranceSpec.openrpc.json");
+const ABI_JSON: &str = include_str!("gen/IConfidentialInsuranceSpec.abi.json");
+
+fn discovery_document(json: &str, what: &str) -> RpcResult<serde_json::Value> {
+    serde_json::from_str(json).map_err(|e| {
+        ErrorObject::owned(-32000, format!("Internal error: Failed to parse {}", what), Some(e.to_string()))
+    })
 }
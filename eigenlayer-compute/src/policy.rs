@@ -0,0 +1,47 @@
+//! Reads of `PolicyManager`, the contract that mints policies.
+//!
+//! A policy's `entryCommit` binds the position it insures. The engine
+//! fetches it through [`PolicyManager::entry_commitment`] to check the
+//! position data of attestation requests against it.
+
+use crate::abi::{self, selector, Address, Token, B256};
+use crate::registry::{EthCall, RegistryError};
+use crate::u256::U256;
+use std::fmt;
+
+pub struct PolicyManager {
+    address: Address,
+    node: Box<dyn EthCall + Send + Sync>,
+}
+
+impl fmt::Debug for PolicyManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyManager").field("address", &self.address).finish()
+    }
+}
+
+impl PolicyManager {
+    /// The `PolicyManager` at `address`, read through `node`.
+    pub fn new(address: Address, node: impl EthCall + Send + Sync + 'static) -> Self {
+        PolicyManager {
+            address,
+            node: Box::new(node),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// `getPolicyCommitment(policyId)`, which is zero for unknown policies.
+    pub fn entry_commitment(&self, policy_id: U256) -> Result<B256, RegistryError> {
+        let mut call = selector("getPolicyCommitment(uint256)").to_vec();
+        call.extend(abi::encode(&[Token::uint(policy_id)]));
+        let data = self.node.eth_call(self.address, &call)?;
+        let word: [u8; 32] = data
+            .get(..32)
+            .and_then(|word| word.try_into().ok())
+            .ok_or_else(|| RegistryError::Node("return data too short for a bytes32".to_string()))?;
+        Ok(B256(word))
+    }
+}
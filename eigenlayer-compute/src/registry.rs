@@ -0,0 +1,477 @@
+//! Registry of the AVS operators whose attestations count.
+//!
+//! Operators are loaded from a JSON config file holding their keys and can
+//! be synced from `EigenAVSManager`, which is the authority on stake and
+//! status but stores no keys:
+//!
+//! ```json
+//! { "operators": [{ "address": "0x…", "blsPublicKey": "0x…", "stake": "0x…", "status": "active" }] }
+//! ```
+
+use crate::abi::{self, selector, Address, Token};
+use crate::bls;
+use crate::engine::MAX_OPERATORS;
+use crate::u256::{Bytes, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperatorStatus {
+    #[default]
+    Active,
+    Inactive,
+    Slashed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operator {
+    pub address: Address,
+    /// Compressed G1 key its BLS attestations verify against, empty if it
+    /// does not sign with BLS
+    #[serde(default)]
+    pub bls_public_key: Bytes,
+    /// Address its ECDSA attestations recover to, when not `address`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecdsa_signer: Option<Address>,
+    #[serde(default)]
+    pub stake: U256,
+    #[serde(default)]
+    pub status: OperatorStatus,
+}
+
+impl Operator {
+    /// An active operator without keys, as a sync discovers it.
+    pub fn new(address: Address) -> Self {
+        Operator {
+            address,
+            bls_public_key: Bytes::default(),
+            ecdsa_signer: None,
+            stake: U256::ZERO,
+            status: OperatorStatus::Active,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == OperatorStatus::Active
+    }
+
+    /// Address its ECDSA attestations recover to.
+    pub fn signer(&self) -> Address {
+        self.ecdsa_signer.unwrap_or(self.address)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// The config file could not be read
+    Io(String),
+    /// The config is not valid JSON or describes an invalid operator
+    Config(String),
+    /// The node could not be reached or returned an error or bad data
+    Node(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Io(message) => write!(f, "cannot read operator registry: {}", message),
+            RegistryError::Config(message) => write!(f, "invalid operator registry: {}", message),
+            RegistryError::Node(message) => write!(f, "operator sync failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorRegistry {
+    operators: Vec<Operator>,
+}
+
+impl OperatorRegistry {
+    /// Builds a registry, rejecting duplicate operators and signers, and
+    /// BLS keys that do not decode.
+    pub fn new(operators: Vec<Operator>) -> Result<Self, RegistryError> {
+        if operators.len() > MAX_OPERATORS {
+            return Err(RegistryError::Config(format!("more than {} operators", MAX_OPERATORS)));
+        }
+        for (i, operator) in operators.iter().enumerate() {
+            let earlier = &operators[..i];
+            if earlier.iter().any(|other| other.address == operator.address) {
+                return Err(RegistryError::Config(format!("operator {} is listed twice", operator.address)));
+            }
+            if earlier.iter().any(|other| other.signer() == operator.signer()) {
+                return Err(RegistryError::Config(format!("signer {} is listed twice", operator.signer())));
+            }
+            if !operator.bls_public_key.is_empty() && bls::public_key(&operator.bls_public_key.0).is_none() {
+                return Err(RegistryError::Config(format!("operator {} has an invalid BLS key", operator.address)));
+            }
+        }
+        Ok(OperatorRegistry { operators })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, RegistryError> {
+        let config: OperatorRegistry =
+            serde_json::from_str(json).map_err(|err| RegistryError::Config(err.to_string()))?;
+        Self::new(config.operators)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RegistryError> {
+        let json = std::fs::read_to_string(path).map_err(|err| RegistryError::Io(err.to_string()))?;
+        Self::from_json(&json)
+    }
+
+    /// Whether no operators are registered, in which case callers' keys and
+    /// operator sets are trusted.
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operators.len()
+    }
+
+    pub fn operators(&self) -> &[Operator] {
+        &self.operators
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Operator> {
+        self.operators.iter().find(|operator| operator.address == *address)
+    }
+
+    /// Index of the active operator with this BLS key.
+    pub fn position_by_bls_key(&self, public_key: &[u8]) -> Option<usize> {
+        if public_key.is_empty() {
+            return None;
+        }
+        self.operators
+            .iter()
+            .position(|operator| operator.is_active() && operator.bls_public_key.0 == public_key)
+    }
+
+    /// Index of the active operator whose ECDSA signer is `signer`.
+    pub fn position_by_signer(&self, signer: &Address) -> Option<usize> {
+        self.operators
+            .iter()
+            .position(|operator| operator.is_active() && operator.signer() == *signer)
+    }
+
+    /// Stake of each operator, zero for those that are not active.
+    pub fn active_stakes(&self) -> Vec<U256> {
+        self.operators
+            .iter()
+            .map(|operator| if operator.is_active() { operator.stake } else { U256::ZERO })
+            .collect()
+    }
+
+    /// Updates stake and status from `EigenAVSManager` at `manager`.
+    /// Operators it does not list become inactive and new ones are added
+    /// without keys. Returns the number of operators it lists.
+    pub fn sync(&mut self, manager: Address, node: &impl EthCall) -> Result<usize, RegistryError> {
+        let count = decode_uint(&node.eth_call(manager, &selector("getOperatorCount()"))?, 0)?;
+        let count = count
+            .to_u64()
+            .filter(|count| *count as usize <= MAX_OPERATORS)
+            .ok_or_else(|| RegistryError::Node(format!("{} operators exceed the limit of {}", count, MAX_OPERATORS)))?;
+
+        let mut synced = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let mut call = selector("operatorList(uint256)").to_vec();
+            call.extend(abi::encode(&[Token::uint(U256::from(i))]));
+            let address = Address::from_word(decode_uint(&node.eth_call(manager, &call)?, 0)?.into());
+
+            let mut call = selector("getOperatorStatus(address)").to_vec();
+            call.extend(abi::encode(&[Token::Address(address)]));
+            let status = node.eth_call(manager, &call)?;
+            let (is_active, stake, slashing_history) = (
+                !decode_uint(&status, 0)?.is_zero(),
+                decode_uint(&status, 1)?,
+                decode_uint(&status, 2)?,
+            );
+            synced.push((address, is_active, stake, slashing_history));
+        }
+
+        let mut operators = self.operators.clone();
+        for operator in &mut operators {
+            operator.status = OperatorStatus::Inactive;
+        }
+        for (address, is_active, stake, slashing_history) in synced {
+            let index = match operators.iter().position(|operator| operator.address == address) {
+                Some(index) => index,
+                None => {
+                    operators.push(Operator::new(address));
+                    operators.len() - 1
+                }
+            };
+            operators[index].stake = stake;
+            operators[index].status = match (is_active, slashing_history.is_zero()) {
+                (true, _) => OperatorStatus::Active,
+                (false, true) => OperatorStatus::Inactive,
+                (false, false) => OperatorStatus::Slashed,
+            };
+        }
+        *self = Self::new(operators)?;
+        Ok(count as usize)
+    }
+}
+
+fn decode_uint(data: &[u8], index: usize) -> Result<U256, RegistryError> {
+    data.get(index * 32..index * 32 + 32)
+        .map(|word| U256::from_be_bytes(word.try_into().expect("32-byte word")))
+        .ok_or_else(|| RegistryError::Node(format!("return data too short for word {}", index)))
+}
+
+/// Read-only contract calls against a node.
+pub trait EthCall {
+    /// `eth_call` of `data` to `to` at the latest block, returning the
+    /// return data.
+    fn eth_call(&self, to: Address, data: &[u8]) -> Result<Vec<u8>, RegistryError>;
+}
+
+/// Blocking JSON-RPC client for a node over plain HTTP, such as a local
+/// `anvil` or `geth` at `http://127.0.0.1:8545`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpNode {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl HttpNode {
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(url: &str) -> Result<Self, RegistryError> {
+        let invalid = || RegistryError::Node(format!("{} is not an http:// URL", url));
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(HttpNode {
+            host: host.to_string(),
+            port,
+            path: if path.is_empty() { "/".to_string() } else { path.to_string() },
+        })
+    }
+
+    fn post(&self, body: &str) -> Result<Vec<u8>, RegistryError> {
+        let io = |err: std::io::Error| RegistryError::Node(err.to_string());
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).map_err(io)?;
+        stream.set_read_timeout(Some(Self::TIMEOUT)).map_err(io)?;
+        stream.set_write_timeout(Some(Self::TIMEOUT)).map_err(io)?;
+        let request = format!(
+            concat!(
+                "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n",
+                "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            ),
+            self.path,
+            self.host,
+            self.port,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).map_err(io)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).map_err(io)?;
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(RegistryError::Node(format!("node replied {}", status.trim())));
+        }
+        let mut chunked = false;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).map_err(io)?;
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                chunked |=
+                    name.eq_ignore_ascii_case("transfer-encoding") && value.trim().eq_ignore_ascii_case("chunked");
+            }
+        }
+
+        let mut body = Vec::new();
+        if !chunked {
+            reader.read_to_end(&mut body).map_err(io)?;
+            return Ok(body);
+        }
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).map_err(io)?;
+            let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or(""), 16)
+                .map_err(|_| RegistryError::Node("malformed chunked body".to_string()))?;
+            if size == 0 {
+                return Ok(body);
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            reader.read_exact(&mut body[start..]).map_err(io)?;
+            let mut crlf = [0u8; 2];
+            reader.read_exact(&mut crlf).map_err(io)?;
+        }
+    }
+}
+
+impl EthCall for HttpNode {
+    fn eth_call(&self, to: Address, data: &[u8]) -> Result<Vec<u8>, RegistryError> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_call",
+            "params": [{ "to": to, "data": Bytes(data.to_vec()) }, "latest"],
+        });
+        let response: serde_json::Value = serde_json::from_slice(&self.post(&request.to_string())?)
+            .map_err(|err| RegistryError::Node(err.to_string()))?;
+        if let Some(error) = response.get("error") {
+            return Err(RegistryError::Node(error.to_string()));
+        }
+        serde_json::from_value::<Bytes>(response["result"].clone())
+            .map(|result| result.0)
+            .map_err(|err| RegistryError::Node(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn address(seed: u8) -> Address {
+        Address([seed; 20])
+    }
+
+    fn bls_key(seed: u8) -> Bytes {
+        let mut secret = [0u8; 32];
+        secret[31] = seed;
+        Bytes(bls::derive_public_key(&secret).unwrap().to_vec())
+    }
+
+    // EigenAVSManager with operators 1 (active), 2 (slashed) and 4 (new)
+    struct Manager;
+
+    impl EthCall for Manager {
+        fn eth_call(&self, _to: Address, data: &[u8]) -> Result<Vec<u8>, RegistryError> {
+            let listed = [(address(1), true, 100, 0), (address(2), false, 50, 1), (address(4), true, 70, 0)];
+            let argument = U256::from_be_bytes(data[4..].try_into().unwrap_or([0; 32]));
+            let function: [u8; 4] = data[..4].try_into().unwrap();
+            let words = match function {
+                s if s == selector("getOperatorCount()") => vec![Token::uint(U256::from(3))],
+                s if s == selector("operatorList(uint256)") => {
+                    vec![Token::Address(listed[argument.to_u64().unwrap() as usize].0)]
+                }
+                s if s == selector("getOperatorStatus(address)") => {
+                    let (_, is_active, stake, slashing_history) = *listed
+                        .iter()
+                        .find(|(address, ..)| Address::from_word(abi::B256::from(argument)) == *address)
+                        .unwrap();
+                    vec![
+                        Token::Bool(is_active),
+                        Token::uint(U256::from(stake)),
+                        Token::uint(U256::from(slashing_history)),
+                    ]
+                }
+                _ => return Err(RegistryError::Node("execution reverted".to_string())),
+            };
+            Ok(abi::encode(&words))
+        }
+    }
+
+    #[test]
+    fn loads_operators_from_json() {
+        let json = format!(
+            r#"{{"operators": [
+                {{"address": "{}", "blsPublicKey": "{}", "stake": "1000"}},
+                {{"address": "{}", "ecdsaSigner": "{}", "status": "slashed"}}
+            ]}}"#,
+            address(1),
+            abi::to_hex(&bls_key(1).0),
+            address(2),
+            address(9)
+        );
+        let registry = OperatorRegistry::from_json(&json).unwrap();
+        assert_eq!(registry.position_by_bls_key(&bls_key(1).0), Some(0));
+        assert_eq!(registry.position_by_bls_key(&[]), None);
+        assert_eq!(registry.get(&address(2)).unwrap().signer(), address(9));
+        // Slashed operators do not count
+        assert_eq!(registry.position_by_signer(&address(9)), None);
+        assert_eq!(registry.active_stakes(), vec![U256::from(1000), U256::ZERO]);
+
+        let duplicate = OperatorRegistry::new(vec![Operator::new(address(1)), Operator::new(address(1))]);
+        assert!(matches!(duplicate, Err(RegistryError::Config(_))));
+        let mut bad_key = Operator::new(address(1));
+        bad_key.bls_public_key = Bytes(vec![1; 48]);
+        assert!(matches!(OperatorRegistry::new(vec![bad_key]), Err(RegistryError::Config(_))));
+    }
+
+    #[test]
+    fn sync_takes_stake_and_status_from_the_manager() {
+        let mut first = Operator::new(address(1));
+        first.bls_public_key = bls_key(1);
+        let mut registry =
+            OperatorRegistry::new(vec![first, Operator::new(address(2)), Operator::new(address(3))]).unwrap();
+        assert_eq!(registry.sync(address(0xaa), &Manager), Ok(3));
+
+        let statuses: Vec<_> = registry
+            .operators()
+            .iter()
+            .map(|operator| (operator.address, operator.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (address(1), OperatorStatus::Active),
+                (address(2), OperatorStatus::Slashed),
+                (address(3), OperatorStatus::Inactive),
+                (address(4), OperatorStatus::Active),
+            ]
+        );
+        // Keys from the config survive the sync
+        assert_eq!(registry.position_by_bls_key(&bls_key(1).0), Some(0));
+        assert_eq!(registry.active_stakes(), [100, 0, 0, 70].map(U256::from).to_vec());
+    }
+
+    #[test]
+    fn http_node_posts_eth_call() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains(r#""latest"]"#) {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x00ff"}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let node = HttpNode::new(&format!("http://127.0.0.1:{}", port)).unwrap();
+        assert_eq!(node.eth_call(address(1), &[0xab]), Ok(vec![0x00, 0xff]));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1"));
+        assert!(request.contains(r#""data":"0xab""#));
+
+        assert!(HttpNode::new("https://node").is_err());
+        assert_eq!(HttpNode::new("http://node/rpc").unwrap().path, "/rpc");
+    }
+}
@@ -0,0 +1,309 @@
+//! Attestation requests and responses, and their token decimal handling.
+
+use crate::abi::{keccak256_encode, Address, Token, B256};
+use crate::amm::{Deposit, PoolType, Prices};
+use crate::eip712::{self, Domain, ILAttestation};
+use crate::keystore::{OperatorIdentity, OperatorKey};
+use crate::u256::{Bytes, U256};
+use serde::{Deserialize, Serialize};
+
+/// Decimals used for every amount, price and value inside the compute paths.
+pub const NORMALIZED_DECIMALS: u8 = 18;
+
+fn default_decimals() -> u8 {
+    NORMALIZED_DECIMALS
+}
+
+/// ERC-20 metadata needed to interpret raw token amounts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    #[serde(default)]
+    pub address: Address,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+}
+
+impl Default for TokenMetadata {
+    fn default() -> Self {
+        TokenMetadata {
+            address: Address::default(),
+            symbol: String::new(),
+            decimals: NORMALIZED_DECIMALS,
+        }
+    }
+}
+
+impl TokenMetadata {
+    /// Scales a native amount of this token to 18 decimals.
+    pub fn normalize(&self, amount: U256) -> Option<U256> {
+        rescale(amount, self.decimals, NORMALIZED_DECIMALS)
+    }
+
+    /// Scales an 18-decimal amount back to this token's decimals, rounding down.
+    pub fn denormalize(&self, amount: U256) -> Option<U256> {
+        rescale(amount, NORMALIZED_DECIMALS, self.decimals)
+    }
+}
+
+/// Converts `value` from `from` to `to` decimals, rounding down. Returns
+/// `None` if scaling up overflows.
+pub fn rescale(value: U256, from: u8, to: u8) -> Option<U256> {
+    if from <= to {
+        value.checked_mul(pow10(to - from)?)
+    } else {
+        // A divisor above U256::MAX leaves nothing of the value
+        Some(pow10(from - to).map_or(U256::ZERO, |divisor| value / divisor))
+    }
+}
+
+/// `10^exponent`, or `None` above `10^77`.
+fn pow10(exponent: u8) -> Option<U256> {
+    (0..exponent).try_fold(U256::ONE, |power, _| power.checked_mul(U256::from(10)))
+}
+
+/// Amounts are in each token's native decimals, prices in `price_decimals`
+/// per whole token, and `coverage_amount`/`deductible` in the payout token,
+/// which is also the quote currency. Omitted metadata means 18 decimals.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationRequest {
+    pub policy_id: U256,
+    pub initial_token_a_amount: U256,
+    pub initial_token_b_amount: U256,
+    pub current_token_a_price: U256,
+    pub current_token_b_price: U256,
+    pub initial_token_a_price: U256,
+    pub initial_token_b_price: U256,
+    pub pool_fee_rate: U256,
+    pub coverage_amount: U256,
+    pub deductible: U256,
+    pub coverage_ratio: U256,
+    #[serde(default)]
+    pub pool_type: PoolType,
+    #[serde(default)]
+    pub token_a: TokenMetadata,
+    #[serde(default)]
+    pub token_b: TokenMetadata,
+    #[serde(default)]
+    pub payout_token: TokenMetadata,
+    /// Decimals of the price fields, e.g. 8 for Chainlink USD feeds
+    #[serde(default = "default_decimals")]
+    pub price_decimals: u8,
+    /// Fee-growth snapshots of the position; when present they replace the
+    /// flat `pool_fee_rate`
+    #[serde(default)]
+    pub fee_growth: Option<FeeGrowthSnapshots>,
+    /// Salt that opens the policy's entry commitment over this request's
+    /// position data
+    #[serde(default)]
+    pub position_salt: Option<B256>,
+    /// `PolicyManager.getPolicyCommitment(policy_id)`, when the caller read
+    /// it; a commitment the engine fetches itself takes precedence
+    #[serde(default)]
+    pub entry_commit: Option<B256>,
+    /// The policy's claim counter, so the signed response cannot be
+    /// replayed for a later claim
+    #[serde(default)]
+    pub nonce: U256,
+    /// Unix time after which the signed response is not accepted
+    #[serde(default)]
+    pub deadline: U256,
+}
+
+/// `feeGrowthInside{0,1}X128` of a position's range at entry and exit, with
+/// token A as the pool's token0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeGrowthSnapshots {
+    pub liquidity: U256,
+    pub fee_growth_inside0_entry_x128: U256,
+    pub fee_growth_inside1_entry_x128: U256,
+    pub fee_growth_inside0_exit_x128: U256,
+    pub fee_growth_inside1_exit_x128: U256,
+}
+
+impl FeeGrowthSnapshots {
+    /// Fees owed in token0 and token1 (native units), as `Position.update`
+    /// computes them: `(exit - entry) * liquidity / 2^128`, where the growth
+    /// counters are allowed to wrap.
+    pub fn fees_owed(&self) -> Option<(U256, U256)> {
+        let q128 = U256::ONE << 128;
+        let owed = |entry: U256, exit: U256| exit.overflowing_sub(entry).0.mul_div(self.liquidity, q128);
+        Some((
+            owed(self.fee_growth_inside0_entry_x128, self.fee_growth_inside0_exit_x128)?,
+            owed(self.fee_growth_inside1_entry_x128, self.fee_growth_inside1_exit_x128)?,
+        ))
+    }
+
+    /// Quote value of the fees owed at `prices`, in 18 decimals.
+    pub fn fees_value(&self, token_a: &TokenMetadata, token_b: &TokenMetadata, prices: &Prices) -> Option<U256> {
+        let (fees_a, fees_b) = self.fees_owed()?;
+        prices.value_of(token_a.normalize(fees_a)?, token_b.normalize(fees_b)?)
+    }
+}
+
+/// Request values scaled to 18 decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizedRequest {
+    pub deposit: Deposit,
+    pub current_prices: Prices,
+    pub coverage_amount: U256,
+    pub deductible: U256,
+}
+
+impl AttestationRequest {
+    /// Scales every amount and price to 18 decimals, or returns `None` if a
+    /// value overflows.
+    pub fn normalize(&self) -> Option<NormalizedRequest> {
+        let price = |value: U256| rescale(value, self.price_decimals, NORMALIZED_DECIMALS);
+        Some(NormalizedRequest {
+            deposit: Deposit {
+                amount_a: self.token_a.normalize(self.initial_token_a_amount)?,
+                amount_b: self.token_b.normalize(self.initial_token_b_amount)?,
+                entry_prices: Prices {
+                    token_a: price(self.initial_token_a_price)?,
+                    token_b: price(self.initial_token_b_price)?,
+                },
+            },
+            current_prices: Prices {
+                token_a: price(self.current_token_a_price)?,
+                token_b: price(self.current_token_b_price)?,
+            },
+            coverage_amount: self.payout_token.normalize(self.coverage_amount)?,
+            deductible: self.payout_token.normalize(self.deductible)?,
+        })
+    }
+
+    /// Commitment to the position as it was entered, which `PolicyManager`
+    /// stores as the policy's `entryCommit`. `PositionCommitment.commit`
+    /// computes the same hash on-chain:
+    ///
+    /// ```text
+    /// keccak256(abi.encode(
+    ///     address tokenA, address tokenB,
+    ///     uint256 initialTokenAAmount, uint256 initialTokenBAmount,
+    ///     uint256 initialTokenAPrice, uint256 initialTokenBPrice, uint8 priceDecimals,
+    ///     uint8 poolKind, uint256 poolParam0, uint256 poolParam1,
+    ///     uint256 liquidity, uint256 feeGrowthInside0EntryX128, uint256 feeGrowthInside1EntryX128,
+    ///     bytes32 salt))
+    /// ```
+    ///
+    /// Amounts are in the tokens' native decimals. The pool kinds are 0 for
+    /// constant product, 1 for weighted `(weightABps, 0)`, 2 for StableSwap
+    /// `(amplification, 0)` and 3 for concentrated `(priceLower,
+    /// priceUpper)`. The fee-growth words are zero without snapshots.
+    pub fn entry_commitment(&self, salt: B256) -> B256 {
+        let (pool_kind, pool_param0, pool_param1) = self.pool_type.encoded();
+        let fee_growth = self.fee_growth.map_or([U256::ZERO; 3], |snapshots| {
+            [
+                snapshots.liquidity,
+                snapshots.fee_growth_inside0_entry_x128,
+                snapshots.fee_growth_inside1_entry_x128,
+            ]
+        });
+        let mut tokens = vec![
+            Token::Address(self.token_a.address),
+            Token::Address(self.token_b.address),
+            Token::uint(self.initial_token_a_amount),
+            Token::uint(self.initial_token_b_amount),
+            Token::uint(self.initial_token_a_price),
+            Token::uint(self.initial_token_b_price),
+            Token::Uint(U256::from(self.price_decimals as u64), 8),
+            Token::Uint(U256::from(pool_kind as u64), 8),
+            Token::uint(pool_param0),
+            Token::uint(pool_param1),
+        ];
+        tokens.extend(fee_growth.into_iter().map(Token::uint));
+        tokens.push(Token::bytes32(salt));
+        keccak256_encode(&tokens)
+    }
+
+    /// [`eip712::prices_hash`] of the current prices as the request reports
+    /// them, token A then token B, without timestamps.
+    pub fn prices_hash(&self) -> B256 {
+        eip712::prices_hash(&[self.current_token_a_price, self.current_token_b_price], &[])
+    }
+}
+
+/// Loss and payout in the payout token's native decimals, signed by the
+/// operator when the engine has a key and a domain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationResponse {
+    pub policy_id: U256,
+    pub impermanent_loss: U256,
+    pub has_loss: bool,
+    pub payout: U256,
+    pub is_valid: bool,
+    /// [`AttestationRequest::prices_hash`] of the request
+    pub prices_hash: B256,
+    pub nonce: U256,
+    pub deadline: U256,
+    /// Result hash of the request's transcript, which commits to the
+    /// request and to every result
+    pub result_hash: B256,
+    /// Domain the signature is made under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<Domain>,
+    /// Operator signature of the EIP-712 digest of
+    /// [`AttestationResponse::attestation`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Bytes>,
+    /// Key that made `signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<OperatorIdentity>,
+}
+
+impl AttestationResponse {
+    pub fn invalid(policy_id: U256) -> Self {
+        AttestationResponse {
+            policy_id,
+            impermanent_loss: U256::ZERO,
+            has_loss: false,
+            payout: U256::ZERO,
+            is_valid: false,
+            prices_hash: B256::default(),
+            nonce: U256::ZERO,
+            deadline: U256::ZERO,
+            result_hash: B256::default(),
+            domain: None,
+            signature: None,
+            signer: None,
+        }
+    }
+
+    /// The typed attestation of the response on `domain`'s chain.
+    pub fn attestation(&self, domain: &Domain) -> ILAttestation {
+        ILAttestation {
+            policy_id: self.policy_id,
+            impermanent_loss: self.impermanent_loss,
+            payout: self.payout,
+            prices_hash: self.prices_hash,
+            nonce: self.nonce,
+            chain_id: domain.chain_id,
+            deadline: self.deadline,
+            result_hash: self.result_hash,
+        }
+    }
+
+    /// Signs the attestation's EIP-712 digest under `domain` with `key` and
+    /// records the domain and the key's identity.
+    pub fn sign(&mut self, key: &OperatorKey, domain: &Domain) {
+        self.signature = Some(key.sign_digest(self.attestation(domain).digest(domain)));
+        self.domain = Some(domain.clone());
+        self.signer = Some(key.identity().clone());
+    }
+
+    /// Whether the response carries a valid signature by its signer. The
+    /// attestation has no `has_loss` or `is_valid`, so a signed response
+    /// must also be valid and report a loss exactly when it has one.
+    pub fn verify_signature(&self) -> bool {
+        match (&self.signature, &self.signer, &self.domain) {
+            (Some(signature), Some(signer), Some(domain)) => {
+                self.is_valid
+                    && self.has_loss != self.impermanent_loss.is_zero()
+                    && signer.verify_digest(self.attestation(domain).digest(domain), &signature.0)
+            }
+            _ => false,
+        }
+    }
+}
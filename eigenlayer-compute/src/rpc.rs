@@ -0,0 +1,126 @@
+//! JSON-RPC methods served alongside the generated `IConfidentialInsuranceSpec`
+//! interface. They are not part of the on-chain spec, so they live outside
+//! `gen/` and survive regeneration.
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use insurance_core::aggregation::AggregationPolicy;
+use insurance_core::challenge::Evidence;
+use insurance_core::concentrated::ConcentratedIL;
+use insurance_core::eip712::{Domain, ILAttestation};
+use insurance_core::engine::{AggregatedAttestation, EcdsaAggregatedAttestation};
+use insurance_core::envelope::{EncryptedResponse, EncryptionKey, Envelope};
+use insurance_core::ilmath::ILMathError;
+use insurance_core::keystore::OperatorIdentity;
+use insurance_core::lvr::LvrReport;
+use insurance_core::registry::Operator;
+use insurance_core::request::{AttestationRequest, AttestationResponse};
+use insurance_core::threshold::{DecryptionShare, ThresholdCiphertext, ThresholdKey};
+use insurance_core::transcript::{ComputeCall, SignedTranscript};
+use insurance_core::ComputeError;
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::{ErrorObject, ErrorObjectOwned},
+};
+
+/// JSON-RPC error code used by Ethereum nodes for `execution reverted`.
+pub const EXECUTION_REVERTED: i32 = 3;
+
+pub use server::ConfidentialInsuranceExtRpcServer;
+
+// #[rpc] forwards only doc comments to the server trait it generates, so the
+// allow for ilmath_calculate_full_il, which mirrors ILMath.calculateFullIL,
+// goes on a module holding just the trait
+#[allow(clippy::too_many_arguments)]
+mod server {
+    use super::*;
+
+    #[rpc(server)]
+    pub trait ConfidentialInsuranceExtRpc {
+        /// ILMath.calculateHodlValue
+        #[method(name = "compute_ilmathCalculateHodlValue")]
+        async fn ilmath_calculate_hodl_value(&self, x0: U256, y0: U256, p1: U256) -> RpcResult<U256>;
+        /// ILMath.calculateLPValue
+        #[method(name = "compute_ilmathCalculateLPValue")]
+        async fn ilmath_calculate_lp_value(&self, x1: U256, y1: U256, fees: U256, p1: U256) -> RpcResult<U256>;
+        /// ILMath.calculateIL
+        #[method(name = "compute_ilmathCalculateIL")]
+        async fn ilmath_calculate_il(&self, hodl_value: U256, lp_value: U256) -> RpcResult<U256>;
+        /// ILMath.calculatePayout
+        #[method(name = "compute_ilmathCalculatePayout")]
+        async fn ilmath_calculate_payout(&self, impermanent_loss: U256, hodl_value: U256, cap_bps: u16, deductible_bps: u16) -> RpcResult<U256>;
+        /// ILMath.calculateFullIL, returning (hodlValue, lpValue, impermanentLoss, payout)
+        #[method(name = "compute_ilmathCalculateFullIL")]
+        async fn ilmath_calculate_full_il(&self, x0: U256, y0: U256, x1: U256, y1: U256, fees: U256, p1: U256, cap_bps: u16, deductible_bps: u16) -> RpcResult<(U256, U256, U256, U256)>;
+        /// ILMath.estimateILForPriceChange
+        #[method(name = "compute_ilmathEstimateILForPriceChange")]
+        async fn ilmath_estimate_il_for_price_change(&self, price_change_percent: U256) -> RpcResult<U256>;
+        /// aggregateAttestations for a policy with the BLS aggregate signature and signer bitmap, combining values under `policy` (the mean by default)
+        #[method(name = "compute_aggregateSignedAttestations")]
+        async fn aggregate_signed_attestations(&self, policy_id: U256, attestations: Vec<U256>, signatures: Vec<Bytes>, operator_public_keys: Vec<Bytes>, threshold: U256, policy: Option<AggregationPolicy>) -> RpcResult<AggregatedAttestation>;
+        /// aggregateAttestations over 65-byte ECDSA signatures from an operator set, the registry's when omitted.
+        /// Signers sign the EIP-191 message hash, or the EIP-712 digest when a domain separator is given.
+        #[method(name = "compute_aggregateEcdsaAttestations")]
+        async fn aggregate_ecdsa_attestations(&self, attestations: Vec<U256>, signatures: Vec<Bytes>, operators: Option<Vec<Address>>, threshold: U256, domain_separator: Option<B256>, policy: Option<AggregationPolicy>) -> RpcResult<EcdsaAggregatedAttestation>;
+        /// Operators of the registry with their stake and status
+        #[method(name = "compute_getOperators")]
+        async fn get_operators(&self) -> RpcResult<Vec<Operator>>;
+        /// Public key and address responses are signed with, or null when the server has no operator key
+        #[method(name = "compute_getOperatorPublicKey")]
+        async fn get_operator_public_key(&self) -> RpcResult<Option<OperatorIdentity>>;
+        /// Loss and payout of a policy's position, signed with the operator key
+        #[method(name = "compute_processAttestationRequest")]
+        async fn process_attestation_request(&self, request: AttestationRequest) -> RpcResult<AttestationResponse>;
+        /// X25519 key clients seal requests to, published next to rpc.discover; null when the server has none
+        #[method(name = "encryption.discover")]
+        async fn encryption_discover(&self) -> RpcResult<Option<EncryptionKey>>;
+        /// Attestation request sealed to the encryption key, answered with an encrypted response and a signed commitment
+        #[method(name = "compute_processEncryptedAttestationRequest")]
+        async fn process_encrypted_attestation_request(&self, envelope: Envelope) -> RpcResult<EncryptedResponse>;
+        /// Threshold key of the operator set this node holds a share of, or null without a share
+        #[method(name = "compute_getThresholdKey")]
+        async fn get_threshold_key(&self) -> RpcResult<Option<ThresholdKey>>;
+        /// A result encrypted to the operator set's threshold key
+        #[method(name = "compute_thresholdEncrypt")]
+        async fn threshold_encrypt(&self, policy_id: U256, value: U256) -> RpcResult<ThresholdCiphertext>;
+        /// This node's partial decryption of a threshold ciphertext, with its correctness proof
+        #[method(name = "compute_partialDecrypt")]
+        async fn partial_decrypt(&self, ciphertext: ThresholdCiphertext) -> RpcResult<DecryptionShare>;
+        /// Value of a threshold ciphertext, from the first threshold valid partial decryptions
+        #[method(name = "compute_combineDecryptionShares")]
+        async fn combine_decryption_shares(&self, ciphertext: ThresholdCiphertext, shares: Vec<DecryptionShare>) -> RpcResult<U256>;
+        /// A compute method call re-run into its canonical transcript, with the result hash signed by the operator key
+        #[method(name = "compute_trace")]
+        async fn trace(&self, call: ComputeCall) -> RpcResult<SignedTranscript>;
+        /// Evidence for challengeAttestation that a signed response contradicts the re-execution of its request, or null when it does not
+        #[method(name = "compute_challengeAttestation")]
+        async fn challenge_attestation(&self, request: AttestationRequest, attestation: AttestationResponse) -> RpcResult<Option<Evidence>>;
+        /// EIP-712 digest of an ILAttestation, as ILAttestation.hashTypedData computes it on-chain
+        #[method(name = "compute_hashILAttestation")]
+        async fn hash_il_attestation(&self, attestation: ILAttestation, domain: Domain) -> RpcResult<B256>;
+        /// Signer of an ILAttestation's 65-byte signature, or null if the signature is invalid
+        #[method(name = "compute_recoverILAttestationSigner")]
+        async fn recover_il_attestation_signer(&self, attestation: ILAttestation, domain: Domain, signature: Bytes) -> RpcResult<Option<Address>>;
+        /// Impermanent loss of a Uniswap v3/v4 range position
+        #[method(name = "compute_calculateConcentratedIL")]
+        async fn calculate_concentrated_il(&self, liquidity: U256, tick_lower: i32, tick_upper: i32, sqrt_price_entry_x96: U256, sqrt_price_current_x96: U256) -> RpcResult<ConcentratedIL>;
+        /// IL, loss-versus-rebalancing and fees of a full-range position over a price path
+        #[method(name = "compute_calculateLVR")]
+        async fn calculate_lvr(&self, initial_token_a_amount: U256, initial_token_b_amount: U256, price_data: Vec<U256>, timestamps: Vec<U256>, pool_fee_rate: U256) -> RpcResult<LvrReport>;
+    }
+}
+
+/// `execution reverted` error carrying the Solidity revert data, as a node
+/// would return for the same `ILMath` call.
+pub fn revert_error(err: ILMathError) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        EXECUTION_REVERTED,
+        format!("execution reverted: {}", err),
+        Some(Bytes::from(err.revert_data())),
+    )
+}
+
+/// Error object with the stable code, message and `data` of a compute error.
+pub fn compute_error(err: ComputeError) -> ErrorObjectOwned {
+    ErrorObject::owned(err.code(), err.to_string(), Some(err.data()))
+}
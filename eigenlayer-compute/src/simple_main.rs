@@ -1,110 +1,30 @@
-use serde::{Deserialize, Serialize};
-use tokio;
+use insurance_core::abi::{Address, B256};
+use insurance_core::aggregation::AggregationPolicy;
+use insurance_core::amm::PoolType;
+use insurance_core::eip712::Domain;
+use insurance_core::groth16::CircuitRegistry;
+use insurance_core::keystore::OperatorKey;
+use insurance_core::policy::PolicyManager;
+use insurance_core::registry::HttpNode;
+use insurance_core::request::{AttestationRequest, AttestationResponse, TokenMetadata};
+use insurance_core::threshold::{self, KeyShare};
+use insurance_core::{Bytes, ComputeEngine, U256};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct U256(pub [u64; 4]);
-
-impl U256 {
-    pub const ZERO: U256 = U256([0, 0, 0, 0]);
-    
-    pub fn from(value: u64) -> Self {
-        U256([value, 0, 0, 0])
-    }
-    
-    pub fn is_zero(&self) -> bool {
-        self.0[0] == 0 && self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0
-    }
-    
-    pub fn as_u64(&self) -> u64 {
-        self.0[0]
-    }
-}
-
-impl std::ops::Add for U256 {
-    type Output = U256;
-    fn add(self, other: U256) -> U256 {
-        // Simplified addition for demonstration
-        U256([self.0[0] + other.0[0], 0, 0, 0])
-    }
-}
-
-impl std::ops::Sub for U256 {
-    type Output = U256;
-    fn sub(self, other: U256) -> U256 {
-        // Simplified subtraction for demonstration
-        U256([self.0[0].saturating_sub(other.0[0]), 0, 0, 0])
-    }
-}
-
-impl std::ops::Mul for U256 {
-    type Output = U256;
-    fn mul(self, other: U256) -> U256 {
-        // Simplified multiplication for demonstration
-        U256([self.0[0] * other.0[0], 0, 0, 0])
-    }
-}
-
-impl std::ops::Div for U256 {
-    type Output = U256;
-    fn div(self, other: U256) -> U256 {
-        if other.is_zero() {
-            return U256::ZERO;
-        }
-        U256([self.0[0] / other.0[0], 0, 0, 0])
-    }
-}
-
-impl PartialOrd for U256 {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.0[0].cmp(&other.0[0]))
-    }
-}
-
-impl PartialEq for U256 {
-    fn eq(&self, other: &Self) -> bool {
-        self.0[0] == other.0[0]
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Bytes(pub Vec<u8>);
-
-impl Bytes {
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AttestationRequest {
-    pub policy_id: U256,
-    pub initial_token_a_amount: U256,
-    pub initial_token_b_amount: U256,
-    pub current_token_a_price: U256,
-    pub current_token_b_price: U256,
-    pub initial_token_a_price: U256,
-    pub initial_token_b_price: U256,
-    pub pool_fee_rate: U256,
-    pub coverage_amount: U256,
-    pub deductible: U256,
-    pub coverage_ratio: U256,
+/// Standalone front end over [`ComputeEngine`] that reports invalid input as
+/// zero or invalid results instead of errors.
+#[derive(Default)]
+pub struct ConfidentialInsuranceCompute {
+    engine: ComputeEngine,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AttestationResponse {
-    pub impermanent_loss: U256,
-    pub has_loss: bool,
-    pub payout: U256,
-    pub is_valid: bool,
-}
-
-pub struct ConfidentialInsuranceCompute;
-
 impl ConfidentialInsuranceCompute {
     pub fn new() -> Self {
-        Self
+        Self::default()
     }
 
+    /// Full-range impermanent loss for amounts and prices already in 18
+    /// decimals.
+    #[allow(clippy::too_many_arguments)]
     pub async fn calculate_impermanent_loss(
         &self,
         initial_token_a_amount: U256,
@@ -115,72 +35,29 @@ impl ConfidentialInsuranceCompute {
         initial_token_b_price: U256,
         pool_fee_rate: U256,
     ) -> (U256, bool) {
-        // Calculate impermanent loss for liquidity providers
-        // IL = (2 * sqrt(price_ratio) / (1 + price_ratio)) - 1
-        
-        let price_ratio = if initial_token_a_price.is_zero() || initial_token_b_price.is_zero() {
-            return (U256::ZERO, false);
-        } else {
-            (current_token_a_price * initial_token_b_price) / (initial_token_a_price * current_token_b_price)
-        };
-        
-        // Calculate initial portfolio value
-        let initial_value = initial_token_a_amount * initial_token_a_price + initial_token_b_amount * initial_token_b_price;
-        
-        // Calculate current value if held (not in LP)
-        let hold_value = initial_token_a_amount * current_token_a_price + initial_token_b_amount * current_token_b_price;
-        
-        // Calculate LP value with impermanent loss
-        // Simplified calculation for demonstration
-        let sqrt_ratio = Self::isqrt(price_ratio);
-        let lp_multiplier = (U256::from(2) * sqrt_ratio) / (U256::from(1) + price_ratio);
-        let lp_value = (initial_value * lp_multiplier) / U256::from(1);
-        
-        // Add fees earned
-        let fees_earned = (initial_value * pool_fee_rate) / U256::from(10000); // basis points
-        let total_lp_value = lp_value + fees_earned;
-        
-        // Calculate impermanent loss
-        let impermanent_loss = if hold_value > total_lp_value {
-            hold_value - total_lp_value
-        } else {
-            U256::ZERO
-        };
-        
-        let has_loss = impermanent_loss > U256::ZERO;
-        
-        (impermanent_loss, has_loss)
+        self.engine
+            .calculate_impermanent_loss(
+                initial_token_a_amount,
+                initial_token_b_amount,
+                current_token_a_price,
+                current_token_b_price,
+                initial_token_a_price,
+                initial_token_b_price,
+                pool_fee_rate,
+            )
+            .unwrap_or((U256::ZERO, false))
     }
 
     pub async fn calculate_payout(
         &self,
-        _policy_id: U256,
+        policy_id: U256,
         impermanent_loss: U256,
         coverage_amount: U256,
         deductible: U256,
         coverage_ratio: U256,
     ) -> U256 {
-        // Calculate insurance payout based on policy parameters
-        
-        if impermanent_loss <= deductible {
-            // Loss is below deductible threshold
-            return U256::ZERO;
-        }
-        
-        // Calculate loss above deductible
-        let covered_loss = impermanent_loss - deductible;
-        
-        // Apply coverage ratio (e.g., 80% coverage)
-        let payout_before_cap = (covered_loss * coverage_ratio) / U256::from(10000); // basis points
-        
-        // Apply coverage amount cap
-        let final_payout = if payout_before_cap > coverage_amount {
-            coverage_amount
-        } else {
-            payout_before_cap
-        };
-        
-        final_payout
+        self.engine
+            .calculate_payout(policy_id, impermanent_loss, coverage_amount, deductible, coverage_ratio)
     }
 
     pub async fn validate_oracle_prices(
@@ -189,48 +66,9 @@ impl ConfidentialInsuranceCompute {
         timestamps: Vec<U256>,
         deviation_threshold: U256,
     ) -> (bool, Vec<U256>) {
-        // Validate oracle price data for anomalies and consistency
-        
-        if price_data.len() != timestamps.len() || price_data.is_empty() {
-            return (false, vec![]);
-        }
-        
-        let mut valid_prices = Vec::new();
-        let mut is_valid = true;
-        
-        // Check for price deviations
-        for i in 1..price_data.len() {
-            let prev_price = price_data[i - 1].clone();
-            let curr_price = price_data[i].clone();
-            
-            if prev_price.is_zero() {
-                is_valid = false;
-                continue;
-            }
-            
-            // Calculate percentage deviation
-            let deviation = if curr_price > prev_price {
-                ((curr_price - prev_price) * U256::from(10000)) / prev_price
-            } else {
-                ((prev_price - curr_price) * U256::from(10000)) / prev_price
-            };
-            
-            if deviation > deviation_threshold {
-                is_valid = false;
-            } else {
-                valid_prices.push(curr_price);
-            }
-        }
-        
-        // Check timestamp ordering
-        for i in 1..timestamps.len() {
-            if timestamps[i] <= timestamps[i - 1] {
-                is_valid = false;
-                break;
-            }
-        }
-        
-        (is_valid, valid_prices)
+        self.engine
+            .validate_oracle_prices(&price_data, &timestamps, deviation_threshold)
+            .unwrap_or((false, vec![]))
     }
 
     pub async fn aggregate_attestations(
@@ -240,39 +78,16 @@ impl ConfidentialInsuranceCompute {
         operator_public_keys: Vec<Bytes>,
         threshold: U256,
     ) -> (U256, bool) {
-        // Aggregate multiple operator attestations using BLS signatures
-        
-        if attestations.len() != signatures.len() || signatures.len() != operator_public_keys.len() {
-            return (U256::ZERO, false);
-        }
-        
-        if attestations.len() < threshold.as_u64() as usize {
-            return (U256::ZERO, false);
-        }
-        
-        // Simple aggregation logic - in production this would use proper BLS signature verification
-        let mut aggregated_value = U256::ZERO;
-        let mut valid_attestations = 0u64;
-        
-        // Calculate weighted average of attestations
-        for (i, attestation) in attestations.iter().enumerate() {
-            // In a real implementation, we would verify each BLS signature here
-            // For now, we assume all signatures are valid for demonstration
-            if !attestation.is_zero() && !signatures[i].is_empty() && !operator_public_keys[i].is_empty() {
-                aggregated_value = aggregated_value + attestation.clone();
-                valid_attestations += 1;
-            }
-        }
-        
-        let meets_threshold = valid_attestations >= threshold.as_u64();
-        
-        if meets_threshold && valid_attestations > 0 {
-            aggregated_value = aggregated_value / U256::from(valid_attestations);
-        } else {
-            aggregated_value = U256::ZERO;
-        }
-        
-        (aggregated_value, meets_threshold)
+        self.engine
+            .aggregate_attestations(
+                U256::ZERO,
+                &attestations,
+                &signatures,
+                &operator_public_keys,
+                threshold,
+                &AggregationPolicy::default(),
+            )
+            .map_or((U256::ZERO, false), |aggregated| (aggregated.value, aggregated.meets_threshold))
     }
 
     pub async fn verify_encrypted_attestation(
@@ -281,123 +96,198 @@ impl ConfidentialInsuranceCompute {
         proof: Bytes,
         public_inputs: Vec<U256>,
     ) -> (bool, U256) {
-        // Verify encrypted attestation using zero-knowledge proofs
-        
-        if encrypted_attestation.is_empty() || proof.is_empty() {
-            return (false, U256::ZERO);
-        }
-        
-        // In a real implementation, this would:
-        // 1. Decrypt the attestation using FHE
-        // 2. Verify the ZK proof of correct computation
-        // 3. Extract the computed value
-        
-        // For demonstration, we simulate the verification process
-        let attestation_hash = Self::keccak256(&encrypted_attestation.0);
-        let proof_hash = Self::keccak256(&proof.0);
-        
-        // Simple verification logic - check if proof and attestation are consistent
-        let is_valid = !attestation_hash.is_zero() && !proof_hash.is_zero() && !public_inputs.is_empty();
-        
-        // Extract a simulated computed value from the public inputs
-        let computed_value = if is_valid && !public_inputs.is_empty() {
-            public_inputs[0].clone() // First public input as the computed result
-        } else {
-            U256::ZERO
-        };
-        
-        (is_valid, computed_value)
+        self.engine
+            .verify_encrypted_attestation(&encrypted_attestation, &proof, &public_inputs)
+            .unwrap_or((false, U256::ZERO))
     }
 
     pub async fn process_attestation_request(&self, request: AttestationRequest) -> AttestationResponse {
-        // Main function to process a complete attestation request
-        
-        let (impermanent_loss, has_loss) = self.calculate_impermanent_loss(
-            request.initial_token_a_amount,
-            request.initial_token_b_amount,
-            request.current_token_a_price,
-            request.current_token_b_price,
-            request.initial_token_a_price,
-            request.initial_token_b_price,
-            request.pool_fee_rate,
-        ).await;
-        
-        let payout = self.calculate_payout(
-            request.policy_id,
-            impermanent_loss.clone(),
-            request.coverage_amount,
-            request.deductible,
-            request.coverage_ratio,
-        ).await;
-        
-        AttestationResponse {
-            impermanent_loss,
-            has_loss,
-            payout,
-            is_valid: true,
-        }
+        self.engine
+            .process_attestation_request(&request)
+            .unwrap_or_else(|_| AttestationResponse::invalid(request.policy_id))
     }
+}
 
-    // Helper function for integer square root
-    fn isqrt(value: U256) -> U256 {
-        if value.is_zero() {
-            return U256::ZERO;
-        }
-        
-        let mut x = value.clone();
-        let mut y = (value + U256::from(1)) / U256::from(2);
-        
-        while y < x {
-            x = y.clone();
-            y = (y + value.clone() / y) / U256::from(2);
-        }
-        
-        x
+/// `simple deal <threshold> <operators> <dir>` writes one key share file per
+/// node, `share-1.json` to `share-<operators>.json`, for `THRESHOLD_KEY_SHARE`.
+fn deal_key_shares(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [threshold, operators, dir] = args else {
+        return Err("usage: simple deal <threshold> <operators> <dir>".into());
+    };
+    let shares = threshold::deal(threshold.parse()?, operators.parse()?, &mut rand_core::OsRng)?;
+    std::fs::create_dir_all(dir)?;
+    for share in &shares {
+        let path = std::path::Path::new(dir).join(format!("share-{}.json", share.index()));
+        std::fs::write(&path, share.to_json().as_bytes())?;
+        println!("🔑 Key share {} written to {}", share.index(), path.display());
     }
+    Ok(())
+}
 
-    // Helper function for keccak256 hash
-    fn keccak256(data: &[u8]) -> U256 {
-        use sha3::{Digest, Keccak256};
-        let mut hasher = Keccak256::new();
-        hasher.update(data);
-        let result = hasher.finalize();
-        U256::from(u64::from_be_bytes([
-            result[0], result[1], result[2], result[3],
-            result[4], result[5], result[6], result[7]
-        ]))
+/// `simple challenge <request.json> <attestation.json>` re-executes a
+/// disputed attestation against the entry commitment at
+/// `POLICY_MANAGER_ADDRESS`, read through `ETH_RPC_URL`, and prints the
+/// evidence for `challengeAttestation`.
+fn challenge_attestation(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [request, attestation] = args else {
+        return Err("usage: simple challenge <request.json> <attestation.json>".into());
+    };
+    let (Ok(address), Ok(url)) = (std::env::var("POLICY_MANAGER_ADDRESS"), std::env::var("ETH_RPC_URL")) else {
+        return Err("challenges read the entry commitment from POLICY_MANAGER_ADDRESS through ETH_RPC_URL".into());
+    };
+    let address: Address = serde_json::from_value(serde_json::Value::String(address))?;
+    let engine = ComputeEngine::new().with_policy_manager(PolicyManager::new(address, HttpNode::new(&url)?));
+    let request = serde_json::from_slice(&std::fs::read(request)?)?;
+    let attestation = serde_json::from_slice(&std::fs::read(attestation)?)?;
+    match engine.challenge_attestation(request, attestation)? {
+        Some(evidence) => {
+            for mismatch in &evidence.mismatches {
+                println!("❌ {}: attested {}, expected {}", mismatch.field, mismatch.attested, mismatch.expected);
+            }
+            println!("{}", serde_json::to_string_pretty(&evidence)?);
+            println!("Evidence: {}", evidence.encode());
+        }
+        None => println!("✅ The attestation matches its re-execution"),
     }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("deal") {
+        return deal_key_shares(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("challenge") {
+        return challenge_attestation(&args[1..]);
+    }
+
     println!("🚀 EigenLayer Confidential Insurance Compute Service");
     
-    let service = ConfidentialInsuranceCompute::new();
+    // Responses are signed for a local Anvil chain
+    let engine = match OperatorKey::from_env()? {
+        Some(key) => ComputeEngine::new()
+            .with_key(key)
+            .with_domain(Domain::new(U256::from(31337), Address::default())),
+        None => ComputeEngine::new(),
+    };
+    let engine = match std::env::var("CIRCUIT_KEYS") {
+        Ok(path) => engine.with_circuits(CircuitRegistry::load(path)?),
+        Err(_) => engine,
+    };
+    let engine = match std::env::var("THRESHOLD_KEY_SHARE") {
+        Ok(path) => engine.with_key_share(KeyShare::load(path)?),
+        Err(_) => engine,
+    };
+    let service = ConfidentialInsuranceCompute { engine };
+    
+    let usdc = TokenMetadata {
+        address: Address::default(),
+        symbol: "USDC".to_string(),
+        decimals: 6,
+    };
     
-    // Example computation
-    let request = AttestationRequest {
+    // Example computation: a WETH/USDC position priced by 8-decimal USD feeds
+    let salt = B256([7; 32]);
+    let mut request = AttestationRequest {
         policy_id: U256::from(1),
-        initial_token_a_amount: U256::from(1000),
-        initial_token_b_amount: U256::from(2000),
-        current_token_a_price: U256::from(100),
-        current_token_b_price: U256::from(50),
-        initial_token_a_price: U256::from(110),
-        initial_token_b_price: U256::from(55),
+        initial_token_a_amount: U256::from_u128(10_000_000_000_000_000_000), // 10 WETH
+        initial_token_b_amount: U256::from(22_000_000_000), // 22,000 USDC
+        current_token_a_price: U256::from(180_000_000_000), // $1,800
+        current_token_b_price: U256::from(100_000_000), // $1
+        initial_token_a_price: U256::from(220_000_000_000), // $2,200
+        initial_token_b_price: U256::from(100_000_000), // $1
         pool_fee_rate: U256::from(30), // 0.3%
-        coverage_amount: U256::from(5000),
-        deductible: U256::from(100),
+        coverage_amount: U256::from(5_000_000_000), // 5,000 USDC
+        deductible: U256::from(10_000_000), // 10 USDC
         coverage_ratio: U256::from(8000), // 80%
+        pool_type: PoolType::ConstantProduct,
+        token_a: TokenMetadata {
+            address: Address::default(),
+            symbol: "WETH".to_string(),
+            decimals: 18,
+        },
+        token_b: usdc.clone(),
+        payout_token: usdc,
+        price_decimals: 8,
+        fee_growth: None,
+        position_salt: Some(salt),
+        entry_commit: None,
+        nonce: U256::ONE,
+        deadline: U256::from(u64::MAX),
     };
+    // The commitment PolicyManager stored when the policy was bought
+    request.entry_commit = Some(request.entry_commitment(salt));
     
     let response = service.process_attestation_request(request).await;
     
     println!("✅ Attestation Result:");
-    println!("  Impermanent Loss: {:?}", response.impermanent_loss);
+    println!("  Impermanent Loss: {}", response.impermanent_loss);
     println!("  Has Loss: {}", response.has_loss);
-    println!("  Payout: {:?}", response.payout);
+    println!("  Payout: {}", response.payout);
     println!("  Is Valid: {}", response.is_valid);
+    if let (Some(signature), Some(signer)) = (&response.signature, &response.signer) {
+        println!("  Signed by: {}", signer.address.map_or(signer.public_key.to_string(), |address| address.to_string()));
+        println!("  Signature: {}", signature);
+    }
     
     println!("🎉 EigenLayer Compute Service running successfully!");
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_input_maps_to_default_results() {
+        let service = ConfidentialInsuranceCompute::new();
+        let (value, meets_threshold) = service
+            .aggregate_attestations(vec![U256::from(10)], vec![], vec![], U256::ONE)
+            .await;
+        assert_eq!((value, meets_threshold), (U256::ZERO, false));
+
+        let (is_valid, prices) = service.validate_oracle_prices(vec![], vec![], U256::ZERO).await;
+        assert!(!is_valid);
+        assert!(prices.is_empty());
+
+        let (is_valid, value) = service
+            .verify_encrypted_attestation(Bytes(vec![1]), Bytes(vec![1]), vec![])
+            .await;
+        assert_eq!((is_valid, value), (false, U256::ZERO));
+
+        let (impermanent_loss, has_loss) = service
+            .calculate_impermanent_loss(U256::ONE, U256::ONE, U256::ONE, U256::ZERO, U256::ONE, U256::ONE, U256::ZERO)
+            .await;
+        assert_eq!((impermanent_loss, has_loss), (U256::ZERO, false));
+    }
+
+    #[tokio::test]
+    async fn requests_round_trip_through_json() {
+        let request: AttestationRequest = serde_json::from_value(serde_json::json!({
+            "policy_id": "0x1",
+            "initial_token_a_amount": "1000000000000000000",
+            "initial_token_b_amount": "1000000000000000000",
+            "current_token_a_price": "250000000000000000",
+            "current_token_b_price": "1000000000000000000",
+            "initial_token_a_price": "1000000000000000000",
+            "initial_token_b_price": "1000000000000000000",
+            "pool_fee_rate": 0,
+            "coverage_amount": "0xffffffffffffffffffffffffffffffff",
+            "deductible": 0,
+            "coverage_ratio": 10000
+        }))
+        .unwrap();
+        let service = ConfidentialInsuranceCompute {
+            engine: ComputeEngine::new().allow_uncommitted_requests(),
+        };
+        let response = service.process_attestation_request(request).await;
+        // r = 0.25 costs 20% of the 1.25 hold value
+        assert!(response.is_valid);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap()["impermanent_loss"],
+            serde_json::json!("0x3782dace9d90000")
+        );
+    }
+}
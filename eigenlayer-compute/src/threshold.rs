@@ -0,0 +1,469 @@
+//! t-of-n threshold decryption of confidential results.
+//!
+//! Results are encrypted to the operator set's BLS12-381 G1 key `Y = xG`
+//! with hashed ElGamal: a random `r` gives the ephemeral key `U = rG`, and
+//! HKDF-SHA-256 of `rY` keys ChaCha20-Poly1305, which encrypts the value with
+//! the policy id as associated data. No node holds `x`. It is Shamir-shared
+//! by a dealer: node `i` holds `x_i = f(i)` for a random polynomial `f` of
+//! degree `t - 1` with `f(0) = x`, and `Y_i = x_i G` is public.
+//!
+//! A node's partial decryption is `D_i = x_i U` with a Chaum-Pedersen proof
+//! that `log_G Y_i = log_U D_i`, so the aggregator can reject wrong shares.
+//! Any `t` valid shares interpolate to `rY = Σ λ_i D_i`, with `λ_i` the
+//! Lagrange coefficients at zero.
+
+use crate::bls;
+use crate::error::ComputeError;
+use crate::u256::{Bytes, U256};
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2_v010::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Length of a compressed G1 point.
+pub const POINT_LENGTH: usize = 48;
+/// Length of a correctness proof: the challenge and the response, each a
+/// 32-byte big-endian scalar.
+pub const PROOF_LENGTH: usize = 64;
+
+const KEY_INFO: &[u8] = b"confidential-insurance/threshold/key";
+const PROOF_DOMAIN: &[u8] = b"confidential-insurance/threshold/dleq";
+
+/// Public key of an operator set, as the dealer publishes it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThresholdKey {
+    /// Shares needed to decrypt
+    pub threshold: usize,
+    /// Compressed G1 key results are encrypted to
+    pub public_key: Bytes,
+    /// Compressed G1 key of each share, `verification_keys[i]` for share
+    /// `i + 1`
+    pub verification_keys: Vec<Bytes>,
+}
+
+/// A value encrypted to a [`ThresholdKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThresholdCiphertext {
+    /// Policy the value belongs to, authenticated as associated data
+    pub policy_id: U256,
+    /// Compressed G1 point `U`
+    pub ephemeral_key: Bytes,
+    /// ChaCha20-Poly1305 ciphertext and tag of the 32-byte value
+    pub ciphertext: Bytes,
+}
+
+/// One node's partial decryption of a [`ThresholdCiphertext`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecryptionShare {
+    /// Index of the key share, from 1
+    pub index: usize,
+    /// Compressed G1 point `D_i`
+    pub share: Bytes,
+    /// Chaum-Pedersen proof that `share` used the key share of `index`
+    pub proof: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThresholdError {
+    /// The key share file could not be read
+    Io(String),
+    /// The key share or threshold key is invalid
+    Config(String),
+}
+
+impl fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThresholdError::Io(message) => write!(f, "cannot read key share: {}", message),
+            ThresholdError::Config(message) => write!(f, "invalid threshold key: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ThresholdError {}
+
+impl ThresholdKey {
+    pub fn operators(&self) -> usize {
+        self.verification_keys.len()
+    }
+
+    /// Checks that the threshold is reachable and every key is a G1 point.
+    pub fn validate(&self) -> Result<(), ThresholdError> {
+        if self.threshold == 0 || self.threshold > self.operators() {
+            return Err(ThresholdError::Config(format!(
+                "threshold {} of {} operators",
+                self.threshold,
+                self.operators()
+            )));
+        }
+        point(&self.public_key).ok_or_else(|| ThresholdError::Config("publicKey is not a G1 point".to_string()))?;
+        for (i, key) in self.verification_keys.iter().enumerate() {
+            point(key).ok_or_else(|| ThresholdError::Config(format!("verificationKeys[{}] is not a G1 point", i)))?;
+        }
+        Ok(())
+    }
+
+    /// Encrypts `value` for `policy_id` to the operator set.
+    pub fn encrypt(
+        &self,
+        policy_id: U256,
+        value: U256,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<ThresholdCiphertext, ThresholdError> {
+        let public_key =
+            point(&self.public_key).ok_or_else(|| ThresholdError::Config("publicKey is not a G1 point".to_string()))?;
+        let r = random_scalar(rng);
+        let ephemeral_key = G1Affine::from(G1Affine::generator() * r);
+        let cipher = cipher(&ephemeral_key, &G1Affine::from(public_key * r));
+        let aad = policy_id.to_be_bytes();
+        let ciphertext = cipher
+            .encrypt(&Nonce::default(), Payload { msg: &value.to_be_bytes(), aad: &aad })
+            .expect("ChaCha20-Poly1305 encrypts 32 bytes");
+        Ok(ThresholdCiphertext {
+            policy_id,
+            ephemeral_key: Bytes(ephemeral_key.to_compressed().to_vec()),
+            ciphertext: Bytes(ciphertext),
+        })
+    }
+
+    /// Whether `share` is a partial decryption of `ciphertext` by the key
+    /// share of its index.
+    pub fn verify_share(&self, ciphertext: &ThresholdCiphertext, share: &DecryptionShare) -> bool {
+        ephemeral_key(ciphertext).is_ok_and(|u| self.verified_share(&u, share).is_some())
+    }
+
+    /// Combines the first `threshold` valid shares, skipping invalid ones and
+    /// repeats of an index, and decrypts the value.
+    pub fn combine(&self, ciphertext: &ThresholdCiphertext, shares: &[DecryptionShare]) -> Result<U256, ComputeError> {
+        let u = ephemeral_key(ciphertext)?;
+        let mut valid: Vec<(usize, G1Affine)> = Vec::with_capacity(self.threshold);
+        for share in shares {
+            if valid.len() == self.threshold {
+                break;
+            }
+            if valid.iter().any(|(index, _)| *index == share.index) {
+                continue;
+            }
+            if let Some(point) = self.verified_share(&u, share) {
+                valid.push((share.index, point));
+            }
+        }
+        if valid.len() < self.threshold {
+            return Err(ComputeError::InsufficientShares {
+                threshold: self.threshold,
+                valid: valid.len(),
+            });
+        }
+
+        let indices: Vec<usize> = valid.iter().map(|(index, _)| *index).collect();
+        let shared = valid
+            .iter()
+            .fold(G1Projective::identity(), |sum, (index, point)| sum + point * lagrange_at_zero(*index, &indices));
+        let aad = ciphertext.policy_id.to_be_bytes();
+        let plaintext = cipher(&u, &G1Affine::from(shared))
+            .decrypt(&Nonce::default(), Payload { msg: &ciphertext.ciphertext.0, aad: &aad })
+            .map_err(|_| ComputeError::UndecryptableCiphertext {
+                reason: "does not decrypt under the combined key",
+            })?;
+        let value: [u8; 32] = plaintext.as_slice().try_into().map_err(|_| ComputeError::UndecryptableCiphertext {
+            reason: "does not hold a 32-byte value",
+        })?;
+        Ok(U256::from_be_bytes(value))
+    }
+
+    // The share's point if its index is in range and its proof verifies
+    fn verified_share(&self, u: &G1Affine, share: &DecryptionShare) -> Option<G1Affine> {
+        let verification_key = point(self.verification_keys.get(share.index.checked_sub(1)?)?)?;
+        let d = point(&share.share)?;
+        let proof: &[u8; PROOF_LENGTH] = share.proof.0.as_slice().try_into().ok()?;
+        let c = scalar(proof[..32].try_into().ok()?)?;
+        let z = scalar(proof[32..].try_into().ok()?)?;
+        let a1 = G1Affine::generator() * z - verification_key * c;
+        let a2 = u * z - d * c;
+        (challenge(&verification_key, u, &d, &a1.into(), &a2.into()) == c).then_some(d)
+    }
+}
+
+/// A node's share of the operator set's key. The secret is zeroized when
+/// dropped.
+pub struct KeyShare {
+    index: usize,
+    secret: Zeroizing<[u8; 32]>,
+    key: ThresholdKey,
+}
+
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyShare").field("index", &self.index).field("key", &self.key).finish()
+    }
+}
+
+// Key share file, as `deal` writes it
+#[derive(Serialize, Deserialize)]
+struct KeyShareFile {
+    index: usize,
+    /// 32-byte big-endian scalar
+    secret: Bytes,
+    key: ThresholdKey,
+}
+
+impl KeyShare {
+    /// Share `index` of `key`, whose verification key the secret must match.
+    pub fn new(index: usize, secret: Zeroizing<[u8; 32]>, key: ThresholdKey) -> Result<Self, ThresholdError> {
+        key.validate()?;
+        let verification_key = index
+            .checked_sub(1)
+            .and_then(|i| key.verification_keys.get(i))
+            .ok_or_else(|| ThresholdError::Config(format!("index {} of {} operators", index, key.operators())))?;
+        let x = bls::secret_scalar(&secret)
+            .ok_or_else(|| ThresholdError::Config("secret is not a nonzero scalar".to_string()))?;
+        if G1Affine::from(G1Affine::generator() * x).to_compressed().as_slice() != verification_key.0.as_slice() {
+            return Err(ThresholdError::Config(format!("secret does not match verificationKeys[{}]", index - 1)));
+        }
+        Ok(KeyShare { index, secret, key })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ThresholdError> {
+        let mut file: KeyShareFile =
+            serde_json::from_str(json).map_err(|err| ThresholdError::Config(err.to_string()))?;
+        let secret: Result<[u8; 32], _> = file.secret.0.as_slice().try_into();
+        file.secret.0.zeroize();
+        let secret = secret.map_err(|_| ThresholdError::Config("secret is not 32 bytes".to_string()))?;
+        Self::new(file.index, Zeroizing::new(secret), file.key)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ThresholdError> {
+        let json = Zeroizing::new(std::fs::read_to_string(path).map_err(|err| ThresholdError::Io(err.to_string()))?);
+        Self::from_json(&json)
+    }
+
+    /// The key share file, which holds the secret in the clear.
+    pub fn to_json(&self) -> Zeroizing<String> {
+        let file = KeyShareFile {
+            index: self.index,
+            secret: Bytes(self.secret.to_vec()),
+            key: self.key.clone(),
+        };
+        Zeroizing::new(serde_json::to_string_pretty(&file).expect("key shares serialize to JSON"))
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn key(&self) -> &ThresholdKey {
+        &self.key
+    }
+
+    /// This node's partial decryption of `ciphertext`, with its proof.
+    pub fn decrypt_share(
+        &self,
+        ciphertext: &ThresholdCiphertext,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<DecryptionShare, ComputeError> {
+        let u = ephemeral_key(ciphertext)?;
+        let x = bls::secret_scalar(&self.secret).expect("secret validated by KeyShare::new");
+        let verification_key = G1Affine::from(G1Affine::generator() * x);
+        let d = G1Affine::from(u * x);
+
+        let w = random_scalar(rng);
+        let a1 = G1Affine::from(G1Affine::generator() * w);
+        let a2 = G1Affine::from(u * w);
+        let c = challenge(&verification_key, &u, &d, &a1, &a2);
+        let z = w + c * x;
+
+        let mut proof = Vec::with_capacity(PROOF_LENGTH);
+        proof.extend_from_slice(&scalar_bytes(&c));
+        proof.extend_from_slice(&scalar_bytes(&z));
+        Ok(DecryptionShare {
+            index: self.index,
+            share: Bytes(d.to_compressed().to_vec()),
+            proof: Bytes(proof),
+        })
+    }
+}
+
+/// Splits a fresh random key into `operators` shares, any `threshold` of
+/// which decrypt. The dealer must discard the shares it does not hand out.
+pub fn deal(
+    threshold: usize,
+    operators: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<Vec<KeyShare>, ThresholdError> {
+    if threshold == 0 || threshold > operators {
+        return Err(ThresholdError::Config(format!("threshold {} of {} operators", threshold, operators)));
+    }
+    // f(z) = a_0 + a_1 z + ... + a_{t-1} z^{t-1}, with the key a_0 = f(0)
+    let coefficients: Vec<Scalar> = (0..threshold).map(|_| random_scalar(rng)).collect();
+    let secrets: Vec<Scalar> = (1..=operators)
+        .map(|index| {
+            let z = Scalar::from(index as u64);
+            coefficients.iter().rev().fold(Scalar::zero(), |acc, coefficient| acc * z + coefficient)
+        })
+        .collect();
+    let compressed = |x: &Scalar| Bytes(G1Affine::from(G1Affine::generator() * x).to_compressed().to_vec());
+    let key = ThresholdKey {
+        threshold,
+        public_key: compressed(&coefficients[0]),
+        verification_keys: secrets.iter().map(compressed).collect(),
+    };
+    secrets
+        .iter()
+        .enumerate()
+        .map(|(i, x)| KeyShare::new(i + 1, Zeroizing::new(scalar_bytes(x)), key.clone()))
+        .collect()
+}
+
+fn point(bytes: &Bytes) -> Option<G1Affine> {
+    let bytes: &[u8; POINT_LENGTH] = bytes.0.as_slice().try_into().ok()?;
+    Option::from(G1Affine::from_compressed(bytes))
+}
+
+fn ephemeral_key(ciphertext: &ThresholdCiphertext) -> Result<G1Affine, ComputeError> {
+    point(&ciphertext.ephemeral_key)
+        .filter(|u| !bool::from(u.is_identity()))
+        .ok_or(ComputeError::UndecryptableCiphertext {
+            reason: "ephemeral key is not a G1 point",
+        })
+}
+
+// Canonical 32-byte big-endian scalar
+fn scalar(bytes: &[u8; 32]) -> Option<Scalar> {
+    let mut little_endian = *bytes;
+    little_endian.reverse();
+    Option::from(Scalar::from_bytes(&little_endian))
+}
+
+fn scalar_bytes(x: &Scalar) -> [u8; 32] {
+    let mut bytes = x.to_bytes();
+    bytes.reverse();
+    bytes
+}
+
+fn random_scalar(rng: &mut (impl RngCore + CryptoRng)) -> Scalar {
+    let mut wide = Zeroizing::new([0u8; 64]);
+    rng.fill_bytes(wide.as_mut_slice());
+    Scalar::from_bytes_wide(&wide)
+}
+
+// Fiat-Shamir challenge of the proof that log_G(Y_i) = log_U(D_i)
+fn challenge(verification_key: &G1Affine, u: &G1Affine, d: &G1Affine, a1: &G1Affine, a2: &G1Affine) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(PROOF_DOMAIN);
+    for point in [verification_key, u, d, a1, a2] {
+        hasher.update(point.to_compressed());
+    }
+    Scalar::from_bytes_wide(&hasher.finalize().into())
+}
+
+// λ_i = Π_{j ≠ i} j / (j - i)
+fn lagrange_at_zero(index: usize, indices: &[usize]) -> Scalar {
+    let i = Scalar::from(index as u64);
+    let (numerator, denominator) = indices.iter().filter(|&&j| j != index).fold(
+        (Scalar::one(), Scalar::one()),
+        |(numerator, denominator), &j| {
+            let j = Scalar::from(j as u64);
+            (numerator * j, denominator * (j - i))
+        },
+    );
+    numerator * denominator.invert().expect("indices are distinct and below the group order")
+}
+
+fn cipher(ephemeral_key: &G1Affine, shared: &G1Affine) -> ChaCha20Poly1305 {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&ephemeral_key.to_compressed()), &shared.to_compressed())
+        .expand(KEY_INFO, key.as_mut_slice())
+        .expect("32 bytes is a valid HKDF-SHA-256 length");
+    ChaCha20Poly1305::new_from_slice(key.as_slice()).expect("ChaCha20-Poly1305 keys are 32 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+
+    #[test]
+    fn any_threshold_of_shares_decrypts() {
+        let shares = deal(3, 5, &mut OsRng).unwrap();
+        let key = shares[0].key();
+        let value = U256::from_u128(1_234_000_000_000_000_000_000);
+        let ciphertext = key.encrypt(U256::from(7), value, &mut OsRng).unwrap();
+
+        let partials: Vec<DecryptionShare> =
+            shares.iter().map(|share| share.decrypt_share(&ciphertext, &mut OsRng).unwrap()).collect();
+        assert!(partials.iter().all(|partial| key.verify_share(&ciphertext, partial)));
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|&i| partials[i].clone()).collect();
+            assert_eq!(key.combine(&ciphertext, &subset), Ok(value));
+        }
+
+        // Repeats of one index count once
+        assert_eq!(
+            key.combine(&ciphertext, &[partials[0].clone(), partials[0].clone(), partials[1].clone()]),
+            Err(ComputeError::InsufficientShares { threshold: 3, valid: 2 })
+        );
+
+        // The policy id is authenticated
+        let mut moved = ciphertext.clone();
+        moved.policy_id = U256::from(8);
+        assert_eq!(
+            key.combine(&moved, &partials).unwrap_err(),
+            ComputeError::UndecryptableCiphertext {
+                reason: "does not decrypt under the combined key"
+            }
+        );
+    }
+
+    #[test]
+    fn wrong_shares_are_rejected_by_their_proofs() {
+        let shares = deal(2, 3, &mut OsRng).unwrap();
+        let key = shares[0].key();
+        let ciphertext = key.encrypt(U256::ONE, U256::from(42), &mut OsRng).unwrap();
+        let partials: Vec<DecryptionShare> =
+            shares.iter().map(|share| share.decrypt_share(&ciphertext, &mut OsRng).unwrap()).collect();
+
+        // Node 1's share claimed under index 2, and a share for another ciphertext
+        let mut relabeled = partials[0].clone();
+        relabeled.index = 2;
+        let other = key.encrypt(U256::ONE, U256::from(42), &mut OsRng).unwrap();
+        let stale = shares[1].decrypt_share(&other, &mut OsRng).unwrap();
+        let mut forged = partials[1].clone();
+        forged.share = partials[2].share.clone();
+        for bad in [&relabeled, &stale, &forged] {
+            assert!(!key.verify_share(&ciphertext, bad));
+        }
+        assert_eq!(
+            key.combine(&ciphertext, &[relabeled.clone(), stale, forged, partials[2].clone()]),
+            Err(ComputeError::InsufficientShares { threshold: 2, valid: 1 })
+        );
+        // Bad shares are skipped when enough good ones follow
+        assert_eq!(
+            key.combine(&ciphertext, &[relabeled, partials[2].clone(), partials[0].clone()]),
+            Ok(U256::from(42))
+        );
+    }
+
+    #[test]
+    fn key_shares_round_trip_through_json() {
+        let shares = deal(2, 3, &mut OsRng).unwrap();
+        let json = shares[1].to_json();
+        let loaded = KeyShare::from_json(&json).unwrap();
+        assert_eq!((loaded.index(), loaded.key()), (2, shares[1].key()));
+        assert!(!format!("{:?}", loaded).contains("secret"));
+
+        // The secret must match the share's verification key
+        let mut file: serde_json::Value = serde_json::from_str(&json).unwrap();
+        file["index"] = 1.into();
+        assert!(KeyShare::from_json(&file.to_string()).is_err());
+        assert!(deal(3, 2, &mut OsRng).is_err());
+    }
+}
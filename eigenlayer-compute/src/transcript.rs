@@ -0,0 +1,450 @@
+//! Canonical execution transcripts of compute methods.
+//!
+//! Operators only agree on a result if they computed it bit for bit the
+//! same way. A [`Transcript`] records the engine version and rounding rules
+//! with every input, intermediate value and output of one call as named
+//! 256-bit words, and commits to them in a result hash that operators sign
+//! and challengers recompute:
+//!
+//! ```text
+//! section    = keccak256(abi.encode(string[] names, uint256[] values))
+//! resultHash = keccak256(abi.encode(string engineVersion, string rounding, string method,
+//!                                   bytes32 inputs, bytes32 intermediates, bytes32 outputs))
+//! ```
+//!
+//! Byte strings are recorded as their `keccak256`, addresses and booleans
+//! as integers, and arrays as one word per element named `name[i]`.
+
+use crate::abi::{keccak256, keccak256_encode, Address, Token, B256};
+use crate::aggregation::{AggregationPolicy, Deviation, Strategy};
+use crate::keystore::{OperatorIdentity, OperatorKey};
+use crate::request::{AttestationRequest, TokenMetadata};
+use crate::u256::{Bytes, U256};
+use serde::{Deserialize, Serialize};
+
+/// Version of the compute rules, recorded in every transcript.
+pub const ENGINE_VERSION: &str = concat!("insurance_core/", env!("CARGO_PKG_VERSION"));
+
+/// Rounding rules of the engine: every division rounds down, except the
+/// stake quorum of a stake-weighted aggregation, which rounds up.
+pub const ROUNDING: &str = "floor; stake quorum ceil";
+
+/// A named value of a transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Word {
+    pub name: String,
+    pub value: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    pub engine_version: String,
+    pub rounding: String,
+    /// JSON-RPC name of the method
+    pub method: String,
+    pub inputs: Vec<Word>,
+    pub intermediates: Vec<Word>,
+    pub outputs: Vec<Word>,
+    /// Commitment to everything above
+    pub result_hash: B256,
+}
+
+impl Transcript {
+    /// An empty transcript of `method` under this engine's version.
+    pub fn new(method: &str) -> Self {
+        Transcript {
+            engine_version: ENGINE_VERSION.to_string(),
+            rounding: ROUNDING.to_string(),
+            method: method.to_string(),
+            inputs: Vec::new(),
+            intermediates: Vec::new(),
+            outputs: Vec::new(),
+            result_hash: B256::default(),
+        }
+    }
+
+    pub fn input(&mut self, name: impl Into<String>, value: U256) {
+        self.inputs.push(word(name, value));
+    }
+
+    pub fn intermediate(&mut self, name: impl Into<String>, value: U256) {
+        self.intermediates.push(word(name, value));
+    }
+
+    pub fn output(&mut self, name: impl Into<String>, value: U256) {
+        self.outputs.push(word(name, value));
+    }
+
+    /// Sets the result hash once every word is recorded.
+    pub fn seal(mut self) -> Self {
+        self.result_hash = self.compute_hash();
+        self
+    }
+
+    pub fn compute_hash(&self) -> B256 {
+        keccak256_encode(&[
+            Token::String(self.engine_version.clone()),
+            Token::String(self.rounding.clone()),
+            Token::String(self.method.clone()),
+            Token::bytes32(section_hash(&self.inputs)),
+            Token::bytes32(section_hash(&self.intermediates)),
+            Token::bytes32(section_hash(&self.outputs)),
+        ])
+    }
+
+    /// Whether the result hash commits to the recorded words.
+    pub fn verify_hash(&self) -> bool {
+        self.compute_hash() == self.result_hash
+    }
+
+    /// The output named `name`.
+    pub fn output_value(&self, name: &str) -> Option<U256> {
+        self.outputs.iter().find(|word| word.name == name).map(|word| word.value)
+    }
+}
+
+/// A transcript with the operator's signature of its result hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTranscript {
+    pub transcript: Transcript,
+    /// Operator signature of `transcript.resultHash`, as
+    /// [`OperatorKey::sign`] makes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Bytes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<OperatorIdentity>,
+}
+
+impl SignedTranscript {
+    pub fn new(transcript: Transcript, key: Option<&OperatorKey>) -> Self {
+        SignedTranscript {
+            signature: key.map(|key| key.sign(&transcript.result_hash.0)),
+            signer: key.map(|key| key.identity().clone()),
+            transcript,
+        }
+    }
+
+    /// Whether the hash commits to the transcript and the signer signed it.
+    pub fn verify_signature(&self) -> bool {
+        match (&self.signature, &self.signer) {
+            (Some(signature), Some(signer)) => {
+                self.transcript.verify_hash() && signer.verify(&self.transcript.result_hash.0, &signature.0)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A compute method call with its parameters, named as in the JSON-RPC
+/// interface.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params")]
+pub enum ComputeCall {
+    #[serde(rename = "compute_calculateImpermanentLoss")]
+    CalculateImpermanentLoss {
+        initial_token_a_amount: U256,
+        initial_token_b_amount: U256,
+        current_token_a_price: U256,
+        current_token_b_price: U256,
+        initial_token_a_price: U256,
+        initial_token_b_price: U256,
+        pool_fee_rate: U256,
+    },
+    #[serde(rename = "compute_calculatePayout")]
+    CalculatePayout {
+        policy_id: U256,
+        impermanent_loss: U256,
+        coverage_amount: U256,
+        deductible: U256,
+        coverage_ratio: U256,
+    },
+    #[serde(rename = "compute_validateOraclePrices")]
+    ValidateOraclePrices {
+        price_data: Vec<U256>,
+        timestamps: Vec<U256>,
+        deviation_threshold: U256,
+    },
+    #[serde(rename = "compute_aggregateSignedAttestations")]
+    AggregateAttestations {
+        policy_id: U256,
+        attestations: Vec<U256>,
+        signatures: Vec<Bytes>,
+        operator_public_keys: Vec<Bytes>,
+        threshold: U256,
+        #[serde(default)]
+        policy: AggregationPolicy,
+    },
+    #[serde(rename = "compute_aggregateEcdsaAttestations")]
+    AggregateEcdsaAttestations {
+        attestations: Vec<U256>,
+        signatures: Vec<Bytes>,
+        #[serde(default)]
+        operators: Option<Vec<Address>>,
+        threshold: U256,
+        #[serde(default)]
+        domain_separator: Option<B256>,
+        #[serde(default)]
+        policy: AggregationPolicy,
+    },
+    #[serde(rename = "compute_verifyEncryptedAttestation")]
+    VerifyEncryptedAttestation {
+        encrypted_attestation: Bytes,
+        proof: Bytes,
+        public_inputs: Vec<U256>,
+    },
+    #[serde(rename = "compute_processAttestationRequest")]
+    ProcessAttestationRequest { request: Box<AttestationRequest> },
+}
+
+impl ComputeCall {
+    pub fn method(&self) -> &'static str {
+        match self {
+            ComputeCall::CalculateImpermanentLoss { .. } => "compute_calculateImpermanentLoss",
+            ComputeCall::CalculatePayout { .. } => "compute_calculatePayout",
+            ComputeCall::ValidateOraclePrices { .. } => "compute_validateOraclePrices",
+            ComputeCall::AggregateAttestations { .. } => "compute_aggregateSignedAttestations",
+            ComputeCall::AggregateEcdsaAttestations { .. } => "compute_aggregateEcdsaAttestations",
+            ComputeCall::VerifyEncryptedAttestation { .. } => "compute_verifyEncryptedAttestation",
+            ComputeCall::ProcessAttestationRequest { .. } => "compute_processAttestationRequest",
+        }
+    }
+
+    /// A transcript of this call with its inputs recorded.
+    pub fn transcript(&self) -> Transcript {
+        let mut transcript = Transcript::new(self.method());
+        match self {
+            ComputeCall::CalculateImpermanentLoss {
+                initial_token_a_amount,
+                initial_token_b_amount,
+                current_token_a_price,
+                current_token_b_price,
+                initial_token_a_price,
+                initial_token_b_price,
+                pool_fee_rate,
+            } => {
+                transcript.input("initial_token_a_amount", *initial_token_a_amount);
+                transcript.input("initial_token_b_amount", *initial_token_b_amount);
+                transcript.input("current_token_a_price", *current_token_a_price);
+                transcript.input("current_token_b_price", *current_token_b_price);
+                transcript.input("initial_token_a_price", *initial_token_a_price);
+                transcript.input("initial_token_b_price", *initial_token_b_price);
+                transcript.input("pool_fee_rate", *pool_fee_rate);
+            }
+            ComputeCall::CalculatePayout {
+                policy_id,
+                impermanent_loss,
+                coverage_amount,
+                deductible,
+                coverage_ratio,
+            } => {
+                transcript.input("policy_id", *policy_id);
+                transcript.input("impermanent_loss", *impermanent_loss);
+                transcript.input("coverage_amount", *coverage_amount);
+                transcript.input("deductible", *deductible);
+                transcript.input("coverage_ratio", *coverage_ratio);
+            }
+            ComputeCall::ValidateOraclePrices {
+                price_data,
+                timestamps,
+                deviation_threshold,
+            } => {
+                transcript.inputs("price_data", price_data);
+                transcript.inputs("timestamps", timestamps);
+                transcript.input("deviation_threshold", *deviation_threshold);
+            }
+            ComputeCall::AggregateAttestations {
+                policy_id,
+                attestations,
+                signatures,
+                operator_public_keys,
+                threshold,
+                policy,
+            } => {
+                transcript.input("policy_id", *policy_id);
+                transcript.inputs("attestations", attestations);
+                transcript.byte_inputs("signatures", signatures);
+                transcript.byte_inputs("operator_public_keys", operator_public_keys);
+                transcript.input("threshold", *threshold);
+                transcript.policy(policy);
+            }
+            ComputeCall::AggregateEcdsaAttestations {
+                attestations,
+                signatures,
+                operators,
+                threshold,
+                domain_separator,
+                policy,
+            } => {
+                transcript.inputs("attestations", attestations);
+                transcript.byte_inputs("signatures", signatures);
+                for (i, operator) in operators.iter().flatten().enumerate() {
+                    transcript.input(format!("operators[{}]", i), address_word(*operator));
+                }
+                transcript.input("threshold", *threshold);
+                transcript.input("domain_separator", domain_separator.unwrap_or_default().to_u256());
+                transcript.policy(policy);
+            }
+            ComputeCall::VerifyEncryptedAttestation {
+                encrypted_attestation,
+                proof,
+                public_inputs,
+            } => {
+                transcript.input("encrypted_attestation", bytes_word(encrypted_attestation));
+                transcript.input("proof", bytes_word(proof));
+                transcript.inputs("public_inputs", public_inputs);
+            }
+            ComputeCall::ProcessAttestationRequest { request } => transcript.request(request),
+        }
+        transcript
+    }
+}
+
+impl Transcript {
+    fn inputs(&mut self, name: &str, values: &[U256]) {
+        for (i, value) in values.iter().enumerate() {
+            self.input(format!("{}[{}]", name, i), *value);
+        }
+    }
+
+    fn byte_inputs(&mut self, name: &str, values: &[Bytes]) {
+        for (i, value) in values.iter().enumerate() {
+            self.input(format!("{}[{}]", name, i), bytes_word(value));
+        }
+    }
+
+    fn policy(&mut self, policy: &AggregationPolicy) {
+        let (strategy, parameter) = match policy.strategy {
+            Strategy::Mean => (0, 0),
+            Strategy::Median => (1, 0),
+            Strategy::TrimmedMean { trim_bps } => (2, trim_bps),
+            Strategy::StakeWeighted { quorum_bps } => (3, quorum_bps),
+            Strategy::Majority => (4, 0),
+        };
+        self.input("policy.strategy", U256::from(strategy));
+        self.input("policy.strategy_bps", U256::from(parameter as u64));
+        self.inputs("policy.stakes", &policy.stakes);
+        self.input("policy.deviation_tolerance_bps", U256::from(policy.deviation_tolerance_bps as u64));
+    }
+
+    fn request(&mut self, request: &AttestationRequest) {
+        self.input("policy_id", request.policy_id);
+        self.input("initial_token_a_amount", request.initial_token_a_amount);
+        self.input("initial_token_b_amount", request.initial_token_b_amount);
+        self.input("current_token_a_price", request.current_token_a_price);
+        self.input("current_token_b_price", request.current_token_b_price);
+        self.input("initial_token_a_price", request.initial_token_a_price);
+        self.input("initial_token_b_price", request.initial_token_b_price);
+        self.input("pool_fee_rate", request.pool_fee_rate);
+        self.input("coverage_amount", request.coverage_amount);
+        self.input("deductible", request.deductible);
+        self.input("coverage_ratio", request.coverage_ratio);
+        let (pool_kind, pool_param0, pool_param1) = request.pool_type.encoded();
+        self.input("pool_kind", U256::from(pool_kind as u64));
+        self.input("pool_param0", pool_param0);
+        self.input("pool_param1", pool_param1);
+        for (name, token) in [
+            ("token_a", &request.token_a),
+            ("token_b", &request.token_b),
+            ("payout_token", &request.payout_token),
+        ] {
+            self.token(name, token);
+        }
+        self.input("price_decimals", U256::from(request.price_decimals as u64));
+        if let Some(snapshots) = &request.fee_growth {
+            self.input("fee_growth.liquidity", snapshots.liquidity);
+            self.input("fee_growth.fee_growth_inside0_entry_x128", snapshots.fee_growth_inside0_entry_x128);
+            self.input("fee_growth.fee_growth_inside1_entry_x128", snapshots.fee_growth_inside1_entry_x128);
+            self.input("fee_growth.fee_growth_inside0_exit_x128", snapshots.fee_growth_inside0_exit_x128);
+            self.input("fee_growth.fee_growth_inside1_exit_x128", snapshots.fee_growth_inside1_exit_x128);
+        }
+        if let Some(salt) = request.position_salt {
+            self.input("position_salt", salt.to_u256());
+        }
+        if let Some(entry_commit) = request.entry_commit {
+            self.input("entry_commit", entry_commit.to_u256());
+        }
+    }
+
+    fn token(&mut self, name: &str, token: &TokenMetadata) {
+        self.input(format!("{}.address", name), address_word(token.address));
+        self.input(format!("{}.decimals", name), U256::from(token.decimals as u64));
+    }
+
+    /// Records each deviation's index, value and distance as outputs.
+    pub(crate) fn deviations(&mut self, deviations: &[Deviation]) {
+        for (i, deviation) in deviations.iter().enumerate() {
+            self.output(format!("deviations[{}].index", i), U256::from(deviation.index as u64));
+            self.output(format!("deviations[{}].value", i), deviation.value);
+            self.output(format!("deviations[{}].deviation_bps", i), deviation.deviation_bps);
+        }
+    }
+}
+
+fn word(name: impl Into<String>, value: U256) -> Word {
+    Word { name: name.into(), value }
+}
+
+pub(crate) fn bytes_word(bytes: &Bytes) -> U256 {
+    keccak256(&bytes.0).to_u256()
+}
+
+pub(crate) fn address_word(address: Address) -> U256 {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&address.0);
+    U256::from_be_bytes(word)
+}
+
+fn section_hash(words: &[Word]) -> B256 {
+    keccak256_encode(&[
+        Token::Array(words.iter().map(|word| Token::String(word.name.clone())).collect()),
+        Token::Array(words.iter().map(|word| Token::uint(word.value)).collect()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::KeyScheme;
+    use zeroize::Zeroizing;
+
+    #[test]
+    fn method_names_match_the_serialized_call() {
+        let call = ComputeCall::ValidateOraclePrices {
+            price_data: vec![U256::from(100), U256::from(101)],
+            timestamps: vec![U256::ONE, U256::from(2)],
+            deviation_threshold: U256::from(500),
+        };
+        let json = serde_json::to_value(&call).unwrap();
+        assert_eq!(json["method"], call.method());
+
+        let transcript = call.transcript();
+        let names: Vec<_> = transcript.inputs.iter().map(|word| word.name.as_str()).collect();
+        assert_eq!(names, ["price_data[0]", "price_data[1]", "timestamps[0]", "timestamps[1]", "deviation_threshold"]);
+    }
+
+    #[test]
+    fn the_result_hash_commits_to_names_and_sections() {
+        let mut transcript = Transcript::new("compute_calculatePayout");
+        transcript.input("impermanent_loss", U256::from(1000));
+        transcript.output("payout", U256::from(500));
+        let sealed = transcript.clone().seal();
+        assert!(sealed.verify_hash());
+
+        let mut renamed = sealed.clone();
+        renamed.outputs[0].name = "payout_before_cap".to_string();
+        assert!(!renamed.verify_hash());
+
+        // The same word in another section is another transcript
+        let mut moved = Transcript::new("compute_calculatePayout");
+        moved.input("impermanent_loss", U256::from(1000));
+        moved.intermediate("payout", U256::from(500));
+        assert_ne!(moved.seal().result_hash, sealed.result_hash);
+
+        let key = OperatorKey::new(KeyScheme::Bls, Zeroizing::new([7u8; 32])).unwrap();
+        let signed = SignedTranscript::new(sealed, Some(&key));
+        assert!(signed.verify_signature());
+        let json = serde_json::to_string(&signed).unwrap();
+        assert_eq!(serde_json::from_str::<SignedTranscript>(&json).unwrap(), signed);
+    }
+}
@@ -0,0 +1,629 @@
+//! 256-bit unsigned integer used throughout the compute core.
+
+use crate::abi::{from_hex, to_hex};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// 256-bit unsigned integer stored as four little-endian 64-bit limbs.
+///
+/// The arithmetic operators behave like the primitive integer types: `+`,
+/// `-` and `*` panic on overflow and `/` or `%` panic on a zero divisor.
+/// Anything derived from caller input should go through the `checked_*`,
+/// `overflowing_*` or `saturating_*` variants instead.
+///
+/// Serializes as a `0x`-prefixed hex quantity, like `alloy_primitives::U256`,
+/// and also deserializes from decimal strings and JSON numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256(pub [u64; 4]);
+
+impl U256 {
+    pub const ZERO: U256 = U256([0, 0, 0, 0]);
+    pub const ONE: U256 = U256([1, 0, 0, 0]);
+    pub const MAX: U256 = U256([u64::MAX, u64::MAX, u64::MAX, u64::MAX]);
+    pub const BITS: u32 = 256;
+
+    pub fn from(value: u64) -> Self {
+        U256([value, 0, 0, 0])
+    }
+
+    pub fn from_u128(value: u128) -> Self {
+        U256([value as u64, (value >> 64) as u64, 0, 0])
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+            limbs[3 - i] = u64::from_be_bytes(chunk.try_into().expect("8-byte chunk"));
+        }
+        U256(limbs)
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().rev().enumerate() {
+            bytes[i * 8..(i + 1) * 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Parses digits in `radix` (10 or 16), without a prefix.
+    pub fn from_str_radix(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        let base = U256::from(radix as u64);
+        digits.chars().try_fold(U256::ZERO, |value, c| {
+            let digit = c.to_digit(radix)?;
+            value.checked_mul(base)?.checked_add(U256::from(digit as u64))
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0[0] == 0 && self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0
+    }
+
+    /// Returns the value as a `u64`, or `None` if any higher limb is set.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0 {
+            Some(self.0[0])
+        } else {
+            None
+        }
+    }
+
+    /// Number of significant bits (zero for `U256::ZERO`).
+    pub fn bits(&self) -> u32 {
+        for i in (0..4).rev() {
+            if self.0[i] != 0 {
+                return 64 * i as u32 + (64 - self.0[i].leading_zeros());
+            }
+        }
+        0
+    }
+
+    pub fn bit(&self, index: u32) -> bool {
+        if index >= Self::BITS {
+            return false;
+        }
+        (self.0[(index / 64) as usize] >> (index % 64)) & 1 == 1
+    }
+
+    pub fn overflowing_add(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        (U256(result), carry)
+    }
+
+    pub fn overflowing_sub(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = b1 || b2;
+        }
+        (U256(result), borrow)
+    }
+
+    /// Schoolbook multiplication keeping the low 256 bits; the flag reports
+    /// whether any of the discarded high bits were set.
+    pub fn overflowing_mul(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut overflow = false;
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let product = self.0[i] as u128 * other.0[j] as u128;
+                if i + j < 4 {
+                    let acc = result[i + j] as u128 + product + carry;
+                    result[i + j] = acc as u64;
+                    carry = acc >> 64;
+                } else if product != 0 || carry != 0 {
+                    overflow = true;
+                    carry = 0;
+                }
+            }
+            if carry != 0 {
+                overflow = true;
+            }
+        }
+        (U256(result), overflow)
+    }
+
+    pub fn checked_add(self, other: U256) -> Option<U256> {
+        match self.overflowing_add(other) {
+            (value, false) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn checked_sub(self, other: U256) -> Option<U256> {
+        match self.overflowing_sub(other) {
+            (value, false) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn checked_mul(self, other: U256) -> Option<U256> {
+        match self.overflowing_mul(other) {
+            (value, false) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn checked_div(self, other: U256) -> Option<U256> {
+        self.div_rem(other).map(|(quotient, _)| quotient)
+    }
+
+    pub fn checked_rem(self, other: U256) -> Option<U256> {
+        self.div_rem(other).map(|(_, remainder)| remainder)
+    }
+
+    pub fn saturating_add(self, other: U256) -> U256 {
+        self.checked_add(other).unwrap_or(U256::MAX)
+    }
+
+    pub fn saturating_sub(self, other: U256) -> U256 {
+        self.checked_sub(other).unwrap_or(U256::ZERO)
+    }
+
+    pub fn saturating_mul(self, other: U256) -> U256 {
+        self.checked_mul(other).unwrap_or(U256::MAX)
+    }
+
+    /// Binary long division returning `(quotient, remainder)`, or `None` when
+    /// dividing by zero.
+    pub fn div_rem(self, divisor: U256) -> Option<(U256, U256)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        if self < divisor {
+            return Some((U256::ZERO, self));
+        }
+        if self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0 {
+            // Both operands fit in a single limb
+            return Some((U256::from(self.0[0] / divisor.0[0]), U256::from(self.0[0] % divisor.0[0])));
+        }
+
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for i in (0..self.bits()).rev() {
+            remainder = remainder << 1;
+            if self.bit(i) {
+                remainder.0[0] |= 1;
+            }
+            if remainder >= divisor {
+                remainder = remainder.overflowing_sub(divisor).0;
+                quotient.0[(i / 64) as usize] |= 1 << (i % 64);
+            }
+        }
+        Some((quotient, remainder))
+    }
+
+    /// Full 512-bit product as eight little-endian limbs.
+    fn full_mul(self, other: U256) -> [u64; 8] {
+        let mut result = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let acc = result[i + j] as u128 + self.0[i] as u128 * other.0[j] as u128 + carry;
+                result[i + j] = acc as u64;
+                carry = acc >> 64;
+            }
+            result[i + 4] = carry as u64;
+        }
+        result
+    }
+
+    /// Computes `floor(self * multiplier / denominator)` with a 512-bit
+    /// intermediate product, mirroring Uniswap's `FullMath.mulDiv`.
+    ///
+    /// Returns the quotient and whether the division left a remainder, or
+    /// `None` if `denominator` is zero or the quotient does not fit in 256 bits.
+    fn mul_div_rem(self, multiplier: U256, denominator: U256) -> Option<(U256, bool)> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let product = self.full_mul(multiplier);
+        if product[4..].iter().all(|limb| *limb == 0) {
+            let (quotient, remainder) = U256([product[0], product[1], product[2], product[3]]).div_rem(denominator)?;
+            return Some((quotient, !remainder.is_zero()));
+        }
+
+        // Shift-subtract long division over the 512-bit product. The running
+        // remainder stays below `denominator`, so after shifting it needs at
+        // most one extra bit, tracked in `carry`.
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for i in (0..512u32).rev() {
+            let carry = remainder.bit(255);
+            remainder = remainder << 1;
+            if (product[(i / 64) as usize] >> (i % 64)) & 1 == 1 {
+                remainder.0[0] |= 1;
+            }
+            if carry || remainder >= denominator {
+                if i >= Self::BITS {
+                    return None;
+                }
+                remainder = remainder.overflowing_sub(denominator).0;
+                quotient.0[(i / 64) as usize] |= 1 << (i % 64);
+            }
+        }
+        Some((quotient, !remainder.is_zero()))
+    }
+
+    /// `floor(self * multiplier / denominator)` without intermediate overflow.
+    pub fn mul_div(self, multiplier: U256, denominator: U256) -> Option<U256> {
+        self.mul_div_rem(multiplier, denominator).map(|(quotient, _)| quotient)
+    }
+
+    /// `ceil(self * multiplier / denominator)` without intermediate overflow.
+    pub fn mul_div_rounding_up(self, multiplier: U256, denominator: U256) -> Option<U256> {
+        let (quotient, inexact) = self.mul_div_rem(multiplier, denominator)?;
+        if inexact {
+            quotient.checked_add(U256::ONE)
+        } else {
+            Some(quotient)
+        }
+    }
+}
+
+impl std::ops::Add for U256 {
+    type Output = U256;
+    fn add(self, other: U256) -> U256 {
+        self.checked_add(other).expect("attempt to add with overflow")
+    }
+}
+
+impl std::ops::Sub for U256 {
+    type Output = U256;
+    fn sub(self, other: U256) -> U256 {
+        self.checked_sub(other).expect("attempt to subtract with overflow")
+    }
+}
+
+impl std::ops::Mul for U256 {
+    type Output = U256;
+    fn mul(self, other: U256) -> U256 {
+        self.checked_mul(other).expect("attempt to multiply with overflow")
+    }
+}
+
+impl std::ops::Div for U256 {
+    type Output = U256;
+    fn div(self, other: U256) -> U256 {
+        self.checked_div(other).expect("attempt to divide by zero")
+    }
+}
+
+impl std::ops::Rem for U256 {
+    type Output = U256;
+    fn rem(self, other: U256) -> U256 {
+        self.checked_rem(other).expect("attempt to calculate the remainder with a divisor of zero")
+    }
+}
+
+impl std::ops::Shl<u32> for U256 {
+    type Output = U256;
+    fn shl(self, shift: u32) -> U256 {
+        if shift >= Self::BITS {
+            return U256::ZERO;
+        }
+        let limbs = (shift / 64) as usize;
+        let bits = shift % 64;
+        let mut result = [0u64; 4];
+        for (i, limb) in result.iter_mut().enumerate().skip(limbs) {
+            *limb = self.0[i - limbs] << bits;
+            if bits > 0 && i > limbs {
+                *limb |= self.0[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        U256(result)
+    }
+}
+
+impl std::ops::Shr<u32> for U256 {
+    type Output = U256;
+    fn shr(self, shift: u32) -> U256 {
+        if shift >= Self::BITS {
+            return U256::ZERO;
+        }
+        let limbs = (shift / 64) as usize;
+        let bits = shift % 64;
+        let mut result = [0u64; 4];
+        for (i, limb) in result.iter_mut().enumerate().take(4 - limbs) {
+            *limb = self.0[i + limbs] >> bits;
+            if bits > 0 && i + limbs + 1 < 4 {
+                *limb |= self.0[i + limbs + 1] << (64 - bits);
+            }
+        }
+        U256(result)
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Most significant limb first
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        // Peel off 19 decimal digits at a time
+        let chunk = U256::from(10_000_000_000_000_000_000);
+        let mut parts = Vec::new();
+        let mut value = *self;
+        while !value.is_zero() {
+            let (quotient, remainder) = value.div_rem(chunk).expect("non-zero divisor");
+            parts.push(remainder.0[0]);
+            value = quotient;
+        }
+        let mut digits = parts.pop().unwrap_or_default().to_string();
+        for part in parts.iter().rev() {
+            digits.push_str(&format!("{:019}", part));
+        }
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = String::new();
+        for limb in self.0.iter().rev() {
+            if digits.is_empty() {
+                if *limb != 0 {
+                    digits = format!("{:x}", limb);
+                }
+            } else {
+                digits.push_str(&format!("{:016x}", limb));
+            }
+        }
+        if digits.is_empty() {
+            digits.push('0');
+        }
+        f.pad_integral(true, "0x", &digits)
+    }
+}
+
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self))
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct U256Visitor;
+
+        impl de::Visitor<'_> for U256Visitor {
+            type Value = U256;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a 0x-prefixed hex or decimal string, or an unsigned integer")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+                Ok(U256::from(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+                let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                    Some(hex) => U256::from_str_radix(hex, 16),
+                    None => U256::from_str_radix(value, 10),
+                };
+                parsed.ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(U256Visitor)
+    }
+}
+
+/// Byte string, serialized as `0x`-prefixed hex like `alloy_primitives::Bytes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        from_hex(&text)
+            .map(Bytes)
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&text), &"0x-prefixed hex"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limbs(value: U256) -> [u64; 4] {
+        value.0
+    }
+
+    #[test]
+    fn add_carries_across_limbs() {
+        let value = U256([u64::MAX, u64::MAX, 0, 0]) + U256::ONE;
+        assert_eq!(limbs(value), [0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn sub_borrows_across_limbs() {
+        let value = U256([0, 0, 1, 0]) - U256::ONE;
+        assert_eq!(limbs(value), [u64::MAX, u64::MAX, 0, 0]);
+    }
+
+    #[test]
+    fn overflowing_flags_at_bounds() {
+        assert_eq!(U256::MAX.overflowing_add(U256::ONE), (U256::ZERO, true));
+        assert_eq!(U256::ZERO.overflowing_sub(U256::ONE), (U256::MAX, true));
+        assert_eq!(U256::MAX.overflowing_mul(U256::from(2)), (U256::MAX - U256::ONE, true));
+        assert_eq!((U256::ONE << 255).overflowing_mul(U256::from(2)), (U256::ZERO, true));
+        assert_eq!(U256::MAX.overflowing_mul(U256::ONE), (U256::MAX, false));
+    }
+
+    #[test]
+    fn checked_and_saturating_variants() {
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+        assert_eq!(U256::MAX.checked_mul(U256::from(3)), None);
+        assert_eq!(U256::ONE.checked_div(U256::ZERO), None);
+        assert_eq!(U256::ONE.checked_rem(U256::ZERO), None);
+        assert_eq!(U256::MAX.saturating_add(U256::ONE), U256::MAX);
+        assert_eq!(U256::ZERO.saturating_sub(U256::ONE), U256::ZERO);
+        assert_eq!(U256::MAX.saturating_mul(U256::from(3)), U256::MAX);
+    }
+
+    #[test]
+    fn mul_crosses_limbs() {
+        let value = U256::from(u64::MAX) * U256::from(u64::MAX);
+        assert_eq!(value, U256::from_u128(u64::MAX as u128 * u64::MAX as u128));
+    }
+
+    #[test]
+    fn div_rem_with_multi_limb_divisor() {
+        let divisor = U256([7, 3, 1, 0]);
+        let quotient = U256([12345, 9, 0, 0]);
+        let remainder = U256([5, 2, 0, 0]);
+        let dividend = quotient * divisor + remainder;
+        assert_eq!(dividend.div_rem(divisor), Some((quotient, remainder)));
+        assert_eq!(U256::MAX / U256::from(3) * U256::from(3), U256::MAX);
+        assert_eq!(U256::from(5).div_rem(divisor), Some((U256::ZERO, U256::from(5))));
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn div_by_zero_panics() {
+        let _ = U256::ONE / U256::ZERO;
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to subtract with overflow")]
+    fn sub_underflow_panics() {
+        let _ = U256::ZERO - U256::ONE;
+    }
+
+    #[test]
+    fn shifts_cross_limb_boundaries() {
+        assert_eq!(limbs(U256::ONE << 64), [0, 1, 0, 0]);
+        assert_eq!(limbs(U256::from(3) << 63), [1 << 63, 1, 0, 0]);
+        assert_eq!(limbs(U256::ONE << 255), [0, 0, 0, 1 << 63]);
+        assert_eq!(U256::ONE << 256, U256::ZERO);
+        assert_eq!(limbs(U256([0, 1, 0, 0]) >> 1), [1 << 63, 0, 0, 0]);
+        assert_eq!(limbs(U256([0, 0, 0, 1 << 63]) >> 255), [1, 0, 0, 0]);
+        assert_eq!(U256::MAX >> 256, U256::ZERO);
+    }
+
+    #[test]
+    fn ordering_uses_high_limbs_first() {
+        assert!(U256([0, 0, 0, 1]) > U256([u64::MAX, u64::MAX, u64::MAX, 0]));
+        assert!(U256([5, 1, 0, 0]) < U256([0, 2, 0, 0]));
+        assert_ne!(U256([1, 0, 0, 0]), U256([1, 1, 0, 0]));
+    }
+
+    #[test]
+    fn to_u64_rejects_high_limbs() {
+        assert_eq!(U256::from(42).to_u64(), Some(42));
+        assert_eq!((U256::ONE << 64).to_u64(), None);
+    }
+
+    #[test]
+    fn display_is_decimal() {
+        assert_eq!(U256::ZERO.to_string(), "0");
+        assert_eq!(U256::from_u128(u128::MAX).to_string(), u128::MAX.to_string());
+        assert_eq!(
+            U256::MAX.to_string(),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn mul_div_keeps_wide_intermediates() {
+        // MAX * MAX overflows 256 bits, but the quotient fits
+        assert_eq!(U256::MAX.mul_div(U256::MAX, U256::MAX), Some(U256::MAX));
+        let wad = U256::from(1_000_000_000_000_000_000);
+        let amount = U256::ONE << 200;
+        assert_eq!(amount.mul_div(wad, wad), Some(amount));
+        assert_eq!(
+            (U256::ONE << 255).mul_div(U256::from(6), U256::from(4)),
+            Some((U256::ONE << 255) + (U256::ONE << 254))
+        );
+    }
+
+    #[test]
+    fn mul_div_rounding() {
+        assert_eq!(U256::from(7).mul_div(U256::from(3), U256::from(2)), Some(U256::from(10)));
+        assert_eq!(U256::from(7).mul_div_rounding_up(U256::from(3), U256::from(2)), Some(U256::from(11)));
+        assert_eq!(U256::from(8).mul_div_rounding_up(U256::from(3), U256::from(2)), Some(U256::from(12)));
+        let odd = (U256::ONE << 255) + U256::ONE;
+        assert_eq!(odd.mul_div(U256::from(2), U256::from(4)), Some(U256::ONE << 254));
+        assert_eq!(odd.mul_div_rounding_up(U256::from(2), U256::from(4)), Some((U256::ONE << 254) + U256::ONE));
+        // Rounding up past MAX overflows
+        assert_eq!(U256::MAX.mul_div_rounding_up(U256::from(3), U256::from(2)), None);
+        assert_eq!(
+            U256::MAX.mul_div_rounding_up(U256::MAX - U256::ONE, U256::MAX),
+            Some(U256::MAX - U256::ONE)
+        );
+    }
+
+    #[test]
+    fn mul_div_rejects_zero_denominator_and_large_quotients() {
+        assert_eq!(U256::ONE.mul_div(U256::ONE, U256::ZERO), None);
+        assert_eq!(U256::ONE.mul_div_rounding_up(U256::ONE, U256::ZERO), None);
+        assert_eq!(U256::MAX.mul_div(U256::from(2), U256::ONE), None);
+        assert_eq!(U256::MAX.mul_div(U256::MAX, U256::MAX - U256::ONE), None);
+    }
+
+    #[test]
+    fn serde_matches_alloy_quantities() {
+        let value = U256([0, 1, 0, 0]);
+        assert_eq!(serde_json::to_string(&value).unwrap(), r#""0x10000000000000000""#);
+        assert_eq!(serde_json::to_string(&U256::ZERO).unwrap(), r#""0x0""#);
+        assert_eq!(serde_json::from_str::<U256>(r#""0x10000000000000000""#).unwrap(), value);
+        assert_eq!(serde_json::from_str::<U256>(r#""18446744073709551616""#).unwrap(), value);
+        assert_eq!(serde_json::from_str::<U256>("42").unwrap(), U256::from(42));
+        assert!(serde_json::from_str::<U256>(r#""0xg""#).is_err());
+        assert_eq!(U256::from_be_bytes(value.to_be_bytes()), value);
+        assert_eq!(U256::from_be_bytes(U256::MAX.to_be_bytes()), U256::MAX);
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MIT
+
+pragma solidity ^0.8.26;
+
+import {Test} from "forge-std/Test.sol";
+import {ILAttestation} from "../contracts/libraries/ILAttestation.sol";
+
+contract ILAttestationHarness {
+    function verify(ILAttestation.Attestation memory attestation, bytes memory signature)
+        external
+        view
+        returns (address)
+    {
+        return ILAttestation.verify(attestation, signature, address(0x1111111111111111111111111111111111111111));
+    }
+}
+
+/**
+ * @title ILAttestationTest
+ * @notice Checks the library against the vector signed by eigenlayer-compute/src/eip712.rs
+ */
+contract ILAttestationTest is Test {
+    address constant VERIFYING_CONTRACT = 0x1111111111111111111111111111111111111111;
+    // Address of secret key 1, which signed SIGNATURE
+    address constant SIGNER = 0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf;
+    bytes constant SIGNATURE =
+        hex"dd5f59b34faabe7aaed3c2395dc8b226e40f8a53f9953edec831ca46b303d5ea2f2630027a4d3b591f2299137c1e336d9c8380201b4b365f0b1670aa0ac52ebf1c";
+
+    ILAttestationHarness harness;
+
+    function setUp() public {
+        vm.chainId(31337);
+        vm.warp(1_600_000_000);
+        harness = new ILAttestationHarness();
+    }
+
+    function _attestation() internal pure returns (ILAttestation.Attestation memory) {
+        uint256[] memory prices = new uint256[](2);
+        prices[0] = 1;
+        prices[1] = 2;
+        uint256[] memory timestamps = new uint256[](2);
+        timestamps[0] = 100;
+        timestamps[1] = 200;
+        return ILAttestation.Attestation({
+            policyId: 7,
+            impermanentLoss: 0.25e18,
+            payout: 0.2e18,
+            pricesHash: ILAttestation.pricesHash(prices, timestamps),
+            nonce: 1,
+            chainId: 31337,
+            deadline: 1_700_000_000,
+            resultHash: keccak256("result")
+        });
+    }
+
+    function testHashesMatchOffChainComputation() public view {
+        ILAttestation.Attestation memory attestation = _attestation();
+        assertEq(attestation.pricesHash, 0x2e504e4a4076aebdbd2d82edf8b2326937d8c31ccedbf2ecaa35153936172639);
+        assertEq(
+            ILAttestation.domainSeparator(VERIFYING_CONTRACT),
+            0x223dfc5a266346ae8d5e958a8e56a7df45e7bb016f6a6a5ae331a793aaf9b0cc
+        );
+        assertEq(
+            ILAttestation.hashStruct(attestation), 0xf8423938bb436865955de3cb0025af35391d2576127c35bca812aee1d0093ac8
+        );
+        assertEq(
+            ILAttestation.hashTypedData(attestation, VERIFYING_CONTRACT),
+            0x28caa217ab265223e6ea27ec2d87ae68eb51ff15345598d822d0530e680bca8f
+        );
+    }
+
+    function testVerifyRecoversOffChainSigner() public view {
+        assertEq(harness.verify(_attestation(), SIGNATURE), SIGNER);
+    }
+
+    function testVerifyRejectsTamperedAttestation() public view {
+        ILAttestation.Attestation memory attestation = _attestation();
+        attestation.payout += 1;
+        assertTrue(harness.verify(attestation, SIGNATURE) != SIGNER);
+    }
+
+    function testVerifyRejectsExpiredAttestation() public {
+        vm.warp(1_700_000_001);
+        vm.expectRevert(ILAttestation.AttestationExpired.selector);
+        harness.verify(_attestation(), SIGNATURE);
+    }
+
+    function testVerifyRejectsOtherChain() public {
+        vm.chainId(1);
+        vm.expectRevert(ILAttestation.ChainMismatch.selector);
+        harness.verify(_attestation(), SIGNATURE);
+    }
+
+    function testVerifyRejectsMalformedSignature() public {
+        bytes memory truncated = new bytes(64);
+        vm.expectRevert(ILAttestation.InvalidSignature.selector);
+        harness.verify(_attestation(), truncated);
+    }
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MIT
+
+pragma solidity ^0.8.26;
+
+import {Test} from "forge-std/Test.sol";
+import {PositionCommitment} from "../contracts/libraries/PositionCommitment.sol";
+
+/**
+ * @title PositionCommitmentTest
+ * @notice Checks the library against the commitment computed by eigenlayer-compute/src/request.rs
+ */
+contract PositionCommitmentTest is Test {
+    bytes32 constant SALT = 0x0505050505050505050505050505050505050505050505050505050505050505;
+
+    function _position() internal pure returns (PositionCommitment.Position memory) {
+        return PositionCommitment.Position({
+            tokenA: 0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa,
+            tokenB: 0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB,
+            initialTokenAAmount: 1000e18,
+            initialTokenBAmount: 1000e18,
+            initialTokenAPrice: 1e18,
+            initialTokenBPrice: 1e18,
+            priceDecimals: 18,
+            poolKind: 0,
+            poolParam0: 0,
+            poolParam1: 0,
+            liquidity: 0,
+            feeGrowthInside0EntryX128: 0,
+            feeGrowthInside1EntryX128: 0
+        });
+    }
+
+    function testMatchesOffChainCommitment() public pure {
+        assertEq(
+            PositionCommitment.commit(_position(), SALT),
+            0x3dae2820de294d7eb3e1046991e0ee35d7ca3d9ed8be2f15dd5480fae8b4856d
+        );
+    }
+
+    function testInflatedAmountChangesCommitment() public pure {
+        PositionCommitment.Position memory position = _position();
+        bytes32 entry = PositionCommitment.commit(position, SALT);
+        position.initialTokenAAmount += 1;
+        assertTrue(PositionCommitment.commit(position, SALT) != entry);
+    }
+}